@@ -0,0 +1,40 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use dagrs::{Dag, DefaultTask, Output};
+
+/// Build a dag of `size` tasks, each depending on the previous one.
+fn build(size: usize, preallocate: bool) -> Dag {
+    let mut dag = if preallocate {
+        Dag::with_capacity(size)
+    } else {
+        Dag::with_tasks(Vec::<DefaultTask>::new())
+    };
+    let mut previous = None;
+    for i in 0..size {
+        let mut task = DefaultTask::with_closure(&i.to_string(), |_input, _env| Output::empty());
+        if let Some(previous) = previous {
+            task.set_predecessors_by_id([previous]);
+        }
+        previous = Some(dag.add_task(task));
+    }
+    dag
+}
+
+fn build_dag_bench(bencher: &mut Criterion) {
+    let mut group = bencher.benchmark_group("build dag");
+    group.sample_size(20);
+    for size in [5_000, 50_000] {
+        group.bench_with_input(BenchmarkId::new("growing", size), &size, |b, &size| {
+            b.iter(|| build(size, false))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("with capacity", size),
+            &size,
+            |b, &size| b.iter(|| build(size, true)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build_dag_bench);
+criterion_main!(benches);
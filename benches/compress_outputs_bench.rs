@@ -0,0 +1,35 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use dagrs::{Dag, DefaultTask, Output};
+
+/// Run a producer passing `size` bytes of text to a consumer.
+fn pass_text(size: usize, compress: bool) {
+    let producer = DefaultTask::with_closure("producer", move |_input, _env| {
+        let line = "lorem ipsum dolor sit amet, consectetur adipiscing elit\n";
+        Output::new(line.repeat(size / line.len() + 1))
+    });
+    let mut consumer = DefaultTask::with_closure("consumer", |input, _env| {
+        let text = input.get_iter().next().unwrap().get::<String>().unwrap();
+        Output::new(text.len())
+    });
+    consumer.set_predecessors(&[&producer]);
+    let mut dag = Dag::with_tasks(vec![producer, consumer]);
+    dag.set_compress_outputs(compress);
+    assert!(dag.start().unwrap());
+}
+
+fn compress_outputs_bench(bencher: &mut Criterion) {
+    let mut group = bencher.benchmark_group("compress outputs");
+    for size in [1024, 16 * 1024, 256 * 1024, 4 * 1024 * 1024] {
+        group.bench_with_input(BenchmarkId::new("plain", size), &size, |b, &size| {
+            b.iter(|| pass_text(size, false))
+        });
+        group.bench_with_input(BenchmarkId::new("compressed", size), &size, |b, &size| {
+            b.iter(|| pass_text(size, true))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, compress_outputs_bench);
+criterion_main!(benches);
@@ -28,7 +28,6 @@ use std::sync::Arc;
 /// Note that although task g has no successor tasks, "g->" must also be written. The return
 /// value type tasks is a Vec<DefaultTask>. The name of each task is the same as the given
 /// identifier, which can be expressed as an array as [ "a","b","c","d","e","f","g"].
-
 struct Compute(usize);
 
 impl Complex for Compute {
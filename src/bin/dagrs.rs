@@ -1,7 +1,7 @@
 use std::{collections::HashMap, fs::File, str::FromStr};
 
 use clap::Parser;
-use dagrs::Dag;
+use dagrs::{utils::logger, Dag, DagError};
 
 #[derive(Parser, Debug)]
 #[command(name = "dagrs", version = "0.2.0")]
@@ -15,6 +15,17 @@ struct Args {
     /// Log level, the default is 'info'.
     #[arg(long)]
     log_level: Option<String>,
+    /// Log level of a specific target, given as 'target=level', e.g. 'engine=debug'.
+    /// Can be given multiple times.
+    #[arg(long)]
+    log_target: Vec<String>,
+    /// Whether the logs are colored: 'auto', the default, colors them when printed to a
+    /// terminal, 'always' or 'never'.
+    #[arg(long)]
+    color: Option<String>,
+    /// Read the standard input and pass it to the tasks without predecessors.
+    #[arg(long)]
+    stdin: bool,
 }
 
 fn main() {
@@ -24,7 +35,15 @@ fn main() {
 
     let yaml_path = args.yaml;
     let mut dag = Dag::with_yaml(yaml_path.as_str(), HashMap::new()).unwrap();
-    assert!(dag.start().unwrap());
+    if args.stdin {
+        dag.set_root_input_from_stdin().unwrap();
+    }
+    dag.set_handle_signals(true);
+    match dag.start() {
+        // The conventional exit status of a process interrupted by a signal.
+        Err(DagError::Cancelled) => std::process::exit(130),
+        res => assert!(res.unwrap()),
+    }
 }
 
 fn init_logger(args: &Args) {
@@ -32,8 +51,12 @@ fn init_logger(args: &Args) {
         Some(level_str) => log::LevelFilter::from_str(level_str).unwrap(),
         None => log::LevelFilter::Info,
     };
+    let color = match &args.color {
+        Some(color) => logger::ColorMode::from_str(color).unwrap(),
+        None => logger::ColorMode::Auto,
+    };
     let mut logger_builder = env_logger::Builder::new();
-    logger_builder.filter_level(log_level);
+    logger_builder.write_style(color.into());
 
     // initialize the env_logger with the given log_path
     if let Some(log_path) = &args.log_path {
@@ -42,5 +65,12 @@ fn init_logger(args: &Args) {
         )));
     };
 
-    logger_builder.init();
+    logger::init_logger_with(log_level, logger_builder).unwrap();
+
+    for target_level in &args.log_target {
+        let (target, level) = target_level
+            .split_once('=')
+            .expect("log target should be given as 'target=level'");
+        logger::set_target_level(target, log::LevelFilter::from_str(level).unwrap());
+    }
 }
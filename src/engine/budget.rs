@@ -0,0 +1,89 @@
+//! Admission control of the tasks by the resources they request.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::Notify;
+
+use crate::task::ResourceRequest;
+
+/// The type of the function called when the budget becomes saturated or stops being saturated,
+/// see `Dag::set_saturation_callback`.
+pub(crate) type SaturationCallback = dyn Fn(bool) + Send + Sync;
+
+/// The resources left for the tasks of a dag, see `Dag::set_resource_budget`.
+pub(crate) struct ResourceBudget {
+    available: Mutex<ResourceRequest>,
+    /// Notified whenever resources are released.
+    released: Notify,
+    /// The number of tasks waiting for resources. The callback is called with the lock held, so
+    /// that the calls are made in the order of the transitions.
+    waiting: Mutex<usize>,
+    on_saturation: Option<Arc<SaturationCallback>>,
+}
+
+/// A task waiting for resources, which stops waiting once dropped.
+struct Waiting<'a>(&'a ResourceBudget);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self.0.waiting.lock().unwrap();
+        *waiting -= 1;
+        if let (0, Some(callback)) = (*waiting, &self.0.on_saturation) {
+            callback(false);
+        }
+    }
+}
+
+impl ResourceBudget {
+    pub(crate) fn new(
+        total: ResourceRequest,
+        on_saturation: Option<Arc<SaturationCallback>>,
+    ) -> Self {
+        Self {
+            available: Mutex::new(total),
+            released: Notify::new(),
+            waiting: Mutex::new(0),
+            on_saturation,
+        }
+    }
+
+    /// Wait until the request fits in the resources left, and take them.
+    pub(crate) async fn acquire(&self, request: ResourceRequest) {
+        let mut waiting = None;
+        loop {
+            // Created before checking, so that no release in between is missed.
+            let released = self.released.notified();
+            {
+                let mut available = self.available.lock().unwrap();
+                if request.fits(&available) {
+                    available.cpu_millis -= request.cpu_millis;
+                    available.memory_mb -= request.memory_mb;
+                    return;
+                }
+            }
+            if waiting.is_none() {
+                waiting = Some(self.wait());
+            }
+            released.await;
+        }
+    }
+
+    /// Count a task waiting for resources.
+    fn wait(&self) -> Waiting<'_> {
+        let mut waiting = self.waiting.lock().unwrap();
+        *waiting += 1;
+        if let (1, Some(callback)) = (*waiting, &self.on_saturation) {
+            callback(true);
+        }
+        Waiting(self)
+    }
+
+    /// Give back the resources taken by `acquire`.
+    pub(crate) fn release(&self, request: ResourceRequest) {
+        let mut available = self.available.lock().unwrap();
+        available.cpu_millis += request.cpu_millis;
+        available.memory_mb += request.memory_mb;
+        drop(available);
+        self.released.notify_waiters();
+    }
+}
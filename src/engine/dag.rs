@@ -1,19 +1,44 @@
-use super::{graph::Graph, DagError};
+use super::{
+    budget::{ResourceBudget, SaturationCallback},
+    drift::{TypeChange, TypeDrift},
+    event::{EventLog, RunEventKind},
+    finish::FinishLatch,
+    gantt::{assign_lanes, GanttBar},
+    graph::Graph,
+    import::{self, NameCollision},
+    schedule::{seeded_order, ScheduleOrder, Turn},
+    signal,
+    stats::{Phase, SchedulerRecorder},
+    unconsumed::UnconsumedOutput,
+    DagError, GroupCanceller, GroupReport, ReadyTasks, RunEvent, RunProgress, RunReport,
+    RunningTasks, SchedulerStats, TaskMiddleware, TaskReport, Topology,
+};
 use crate::{
-    task::{ExecState, Input, Task},
+    task::{
+        alloc_id, cancel_waiting_gates, compress, convert, convertible, kill_running_commands,
+        run_with_affinity, take_usage, Content, ExecState, Input, ResourceRequest, Task, TaskGroup,
+        TaskLabels, TaskResourceUsage, TaskState,
+    },
     utils::EnvVar,
-    Action, Parser,
+    Action, DefaultTask, Output, Parser, RetryPolicy,
 };
-use log::{debug, error};
+use log::{debug, error, log_enabled, warn, Level};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Debug,
+    fs,
     panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
+    time::{Duration, Instant},
+};
+use tokio::{
+    runtime::{self, Handle, Runtime},
+    task::JoinHandle,
 };
-use tokio::task::JoinHandle;
 
 /// [`Dag`] is dagrs's main body.
 ///
@@ -24,15 +49,15 @@ use tokio::task::JoinHandle;
 ///
 /// The execution process of Dag is roughly as follows:
 /// - The user gives a list of tasks `tasks`. These tasks can be parsed from configuration files, or provided
-/// by user programming implementations.
+///   by user programming implementations.
 /// - Internally generate `Graph` based on task dependencies, and generate execution sequences based on `rely_graph`.
 /// - The task is scheduled to start executing asynchronously.
 /// - The task will wait to get the result `execute_states` generated by the execution of the predecessor task.
 /// - If the result of the predecessor task can be obtained, check the continuation status `can_continue`, if it
-/// is true, continue to execute the defined logic, if it is false, trigger `handle_error`, and cancel the
-/// execution of the subsequent task.
+///   is true, continue to execute the defined logic, if it is false, trigger `handle_error`, and cancel the
+///   execution of the subsequent task.
 /// - After all tasks are executed, set the continuation status to false, which means that the tasks of the dag
-/// cannot be scheduled for execution again.
+///   cannot be scheduled for execution again.
 ///
 ///  # Example
 /// ```rust
@@ -46,7 +71,6 @@ use tokio::task::JoinHandle;
 /// assert!(dag.start().unwrap())
 ///
 /// ```
-#[derive(Debug)]
 pub struct Dag {
     /// Store all tasks' infos.
     ///
@@ -71,8 +95,107 @@ pub struct Dag {
     keep_going_errored: Arc<AtomicBool>,
     /// The execution sequence of tasks.
     exe_sequence: Vec<usize>,
+    /// Hooks invoked with the run report once the dag finishes.
+    on_complete: Vec<Arc<CompleteHook>>,
+    /// The maximum time the whole dag is allowed to run.
+    timeout: Option<Duration>,
+    /// Middlewares wrapping the action of every task, in registration order.
+    middlewares: Vec<Arc<dyn TaskMiddleware>>,
+    /// Whether SIGINT and SIGTERM cancel the run.
+    handle_signals: bool,
+    /// The directory the standard output and standard error of the commands are written to.
+    log_dir: Option<PathBuf>,
+    /// Whether all tasks must be connected to each other.
+    require_connected: bool,
+    /// Whether the redundant dependencies are logged when the dag starts.
+    warn_redundant_edges: bool,
+    /// The events recorded while running.
+    events: Arc<EventLog>,
+    /// When the last run started.
+    run_started: Mutex<Option<Instant>>,
+    /// Whether each task is given its own temporary directory.
+    temp_dirs: bool,
+    /// Whether the temporary directories of the failed tasks are kept.
+    keep_failed_temp_dirs: bool,
+    /// Whether the outputs are dropped once all the successors read them.
+    eager_output_drop: bool,
+    /// Whether to warn about outputs that are empty or not read by the successors.
+    warn_on_empty_consumed: bool,
+    /// Whether the outputs are converted into the input types declared by the successors.
+    convert_inputs: bool,
+    /// Whether the inputs of the tasks are retained after they ran.
+    retain_inputs: bool,
+    /// Whether every failure lets the successors run, see `start_test_mode`.
+    test_mode: bool,
+    /// The directory the script files are written to.
+    script_dir: Option<PathBuf>,
+    /// The state shared by the tasks, an `Arc<Mutex<T>>`.
+    shared_state: Option<Content>,
+    /// Whether the script files are kept once they ran.
+    keep_script_files: bool,
+    /// The input given to the tasks without predecessors.
+    root_input: Option<Content>,
+    /// The inputs given to tasks in place of the outputs of their predecessors, by task id.
+    seeded_inputs: HashMap<usize, Input>,
+    /// Whether large outputs are stored compressed.
+    compress_outputs: bool,
+    /// The task run at the end of a failed run.
+    on_failure: Option<Arc<dyn Task>>,
+    /// The tasks whose action is executing.
+    running: RunningTasks,
+    /// The tasks whose predecessors are done, but which have not started yet.
+    ready: ReadyTasks,
+    /// The tasks that are done in the current run.
+    progress: RunProgress,
+    /// The variables loaded from `.env` files, used where `env` does not set them.
+    file_env: EnvVar,
+    /// How long the tasks may make no progress before the run is regarded as stalled.
+    stall_timeout: Option<Duration>,
+    /// Whether a stalled run is aborted.
+    abort_on_stall: bool,
+    /// Whether the outputs of the predecessors are given to the tasks as JSON variables.
+    inputs_as_env: bool,
+    /// The largest number of tasks allowed along a chain of dependencies.
+    max_depth: Option<usize>,
+    /// The name of the task whose output is the result of the dag, if not the last task.
+    result_task: Option<String>,
+    /// The total resources the running tasks may request.
+    resource_budget: Option<ResourceRequest>,
+    /// The resources left during the current run.
+    budget: Option<Arc<ResourceBudget>>,
+    /// Called when the budget becomes saturated or stops being saturated.
+    saturation_callback: Option<Arc<SaturationCallback>>,
+    /// The task whose output ended the current run, see [`Output::finish`].
+    finish: Arc<FinishLatch>,
+    /// The teardown tasks of the task groups, along with the setup task of their group.
+    teardowns: HashMap<usize, Option<usize>>,
+    /// The task groups that were cancelled.
+    cancelled_groups: GroupCanceller,
+    /// The function applied to the output of every task before it is stored.
+    output_transform: Option<Arc<OutputTransform>>,
+    /// The seed of the order in which the ready tasks start, if it is randomized.
+    schedule_seed: Option<u64>,
+    /// The position in the execution sequence of the next task run by `step`, once stepping
+    /// started. Any other run resets it, so that the next `step` starts a new run.
+    step_position: Option<usize>,
+    /// The timeout of the tasks without a timeout of their own.
+    default_timeout: Option<Duration>,
+    /// The retry policy of the tasks without a policy of their own.
+    default_retry: Option<RetryPolicy>,
+    /// Records the phases of the tasks during the runs, if enabled.
+    scheduler_stats: Option<Arc<SchedulerRecorder>>,
+    /// Records the output types of the tasks across runs, if enabled.
+    type_drift: Option<Arc<TypeDrift>>,
+    /// What happens to the outputs of the tasks without successors.
+    unconsumed_output: UnconsumedOutput,
 }
 
+/// The type of hook invoked once a dag finishes.
+type CompleteHook = dyn Fn(&RunReport) + Send + Sync;
+
+/// The type of function applied to the output of every task, given the task's name.
+type OutputTransform = dyn Fn(&str, Output) -> Output + Send + Sync;
+
 impl Dag {
     /// Create a dag. This function is not open to the public. There are three ways to create a new
     /// dag, corresponding to three functions: `with_tasks`, `with_yaml`, `with_config_file_and_parser`.
@@ -86,9 +209,77 @@ impl Dag {
             exe_sequence: Vec::new(),
             keep_going: false,
             keep_going_errored: Arc::new(AtomicBool::new(false)),
+            on_complete: Vec::new(),
+            timeout: None,
+            middlewares: Vec::new(),
+            handle_signals: false,
+            log_dir: None,
+            require_connected: false,
+            warn_redundant_edges: false,
+            events: Arc::new(EventLog::default()),
+            run_started: Mutex::new(None),
+            temp_dirs: false,
+            keep_failed_temp_dirs: false,
+            eager_output_drop: false,
+            warn_on_empty_consumed: false,
+            convert_inputs: false,
+            retain_inputs: false,
+            test_mode: false,
+            script_dir: None,
+            shared_state: None,
+            keep_script_files: false,
+            root_input: None,
+            seeded_inputs: HashMap::new(),
+            compress_outputs: false,
+            on_failure: None,
+            running: RunningTasks::default(),
+            ready: ReadyTasks::default(),
+            progress: RunProgress::default(),
+            file_env: EnvVar::new(),
+            stall_timeout: None,
+            abort_on_stall: false,
+            inputs_as_env: false,
+            max_depth: None,
+            result_task: None,
+            resource_budget: None,
+            budget: None,
+            saturation_callback: None,
+            finish: Arc::new(FinishLatch::default()),
+            teardowns: HashMap::new(),
+            cancelled_groups: GroupCanceller::default(),
+            output_transform: None,
+            schedule_seed: None,
+            step_position: None,
+            default_timeout: None,
+            default_retry: None,
+            scheduler_stats: None,
+            type_drift: None,
+            unconsumed_output: UnconsumedOutput::Keep,
         }
     }
 
+    /// Create an empty dag with room for `capacity` tasks, to be added with `add_task`. This
+    /// saves reallocating the internal structures of the dag over and over when building large
+    /// dags programmatically, and behaves like `with_tasks` otherwise.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let mut dag = Dag::with_capacity(1000);
+    /// for i in 0..1000 {
+    ///     dag.add_task(DefaultTask::with_closure(&i.to_string(), |_, _| Output::empty()));
+    /// }
+    /// assert!(dag.start().unwrap());
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Dag {
+        let mut dag = Dag::new();
+        dag.tasks.reserve(capacity);
+        dag.execute_states.reserve(capacity);
+        dag.exe_sequence.reserve(capacity);
+        dag
+    }
+
     /// Create a dag by adding a series of tasks.
     pub fn with_tasks(tasks: Vec<impl Task + 'static>) -> Dag {
         let mut dag = Dag::new();
@@ -110,6 +301,151 @@ impl Dag {
         dag
     }
 
+    /// Add a task to the dag and return its id.
+    pub fn add_task(&mut self, task: impl Task + 'static) -> usize {
+        let id = task.id();
+        self.tasks.insert(id, Box::new(task));
+        id
+    }
+
+    /// Import the task of `source` with the given name into this dag, along with all the tasks
+    /// it transitively depends on, and return the id of the imported task, e.g. to assemble a
+    /// variant of a pipeline from pieces of existing ones.
+    ///
+    /// The tasks are copied like with `clone_structure`: the copies get new ids, and share
+    /// their actions with the tasks of `source`, while the settings of `source`, such as its
+    /// environment, are not imported. `collision` decides what happens to the tasks whose names
+    /// are already taken in this dag, see [`NameCollision`]; on failure, nothing is imported.
+    ///
+    /// Fails with [`DagError::TaskNotFound`] if `source` has no such task, and with
+    /// [`DagError::LoopGraph`] or [`DagError::RelyTaskIllegal`] if its dependencies loop or
+    /// refer to missing tasks. The imported tasks only depend on each other or on reused
+    /// tasks, so the import cannot make the dependencies of this dag loop.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, NameCollision, Output};
+    ///
+    /// let fetch = DefaultTask::with_closure("fetch", |_input, _env| Output::new(20usize));
+    /// let mut build = DefaultTask::with_closure("build", |input, _env| {
+    ///     Output::new(input.get_iter().next().unwrap().get::<usize>().unwrap() + 22)
+    /// });
+    /// build.set_predecessors(&[&fetch]);
+    /// let deploy = DefaultTask::with_closure("deploy", |_input, _env| Output::empty());
+    /// let source = Dag::with_tasks(vec![fetch, build, deploy]);
+    ///
+    /// let mut dag = Dag::with_tasks(Vec::<DefaultTask>::new());
+    /// dag.import_subgraph(&source, "build", &NameCollision::Fail).unwrap();
+    /// assert_eq!(dag.start_and_get::<usize>().unwrap(), Some(42));
+    /// ```
+    pub fn import_subgraph(
+        &mut self,
+        source: &Dag,
+        task_name: &str,
+        collision: &NameCollision,
+    ) -> Result<usize, DagError> {
+        let root = source
+            .find_task_id(task_name)
+            .ok_or_else(|| DagError::TaskNotFound(task_name.to_string()))?;
+        let reused = |task: &dyn Task| match collision {
+            NameCollision::Reuse => self.find_task_id(task.name()),
+            _ => None,
+        };
+        let order = import::ancestors(&source.tasks, root, |task| reused(task).is_none())?;
+        // The ids and names of the copies are all decided before the dag is changed.
+        let mut ids = HashMap::new();
+        let mut copies = Vec::new();
+        let mut names = HashSet::new();
+        for id in order {
+            let task = source.tasks[&id].as_ref();
+            if let Some(existing) = reused(task) {
+                ids.insert(id, existing);
+                continue;
+            }
+            let name = match collision {
+                NameCollision::Rename(suffix) if self.find_task_id(task.name()).is_some() => {
+                    format!("{}{}", task.name(), suffix)
+                }
+                _ => task.name().to_owned(),
+            };
+            if self.find_task_id(&name).is_some() || !names.insert(name.clone()) {
+                return Err(DagError::DuplicateTask(name));
+            }
+            ids.insert(id, alloc_id());
+            copies.push((id, name));
+        }
+        for (id, name) in copies {
+            let task = source.tasks[&id].as_ref();
+            let precursors = task.precursors().iter().map(|pre| ids[pre]).collect();
+            self.add_task(DefaultTask::copy_as(task, ids[&id], name, precursors));
+        }
+        Ok(ids[&root])
+    }
+
+    /// Add the tasks of a group to the dag, with its setup and teardown, see [`TaskGroup`].
+    pub fn add_group(&mut self, group: TaskGroup) {
+        let (tasks, setup, teardown) = group.into_tasks();
+        if let Some(teardown) = teardown {
+            self.teardowns.insert(teardown, setup);
+        }
+        for task in tasks {
+            self.add_task(task);
+        }
+    }
+
+    /// Cancel the task group with the given name: its members that have not started yet are
+    /// skipped, along with their successors, while the running ones are not interrupted. The
+    /// teardown of the group still runs. A cancelled group does not make the dag fail.
+    ///
+    /// To cancel a group while the dag runs, use the handle returned by `group_canceller`.
+    pub fn cancel_group(&self, name: &str) {
+        self.cancelled_groups.cancel(name);
+    }
+
+    /// A handle cancelling the task groups of the dag, which can be moved to another thread
+    /// before the dag starts.
+    pub fn group_canceller(&self) -> GroupCanceller {
+        self.cancelled_groups.clone()
+    }
+
+    /// Build one task per item with the given factory and add them all to the dag.
+    ///
+    /// The ids of the new tasks are returned in the order of the items, so they can be used to
+    /// wire up other tasks with `DefaultTask::set_predecessors_by_id`. The factory itself can
+    /// make the new tasks depend on tasks created earlier in the same way.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, Task};
+    ///
+    /// let root = DefaultTask::with_closure("root", |_, _| Output::new(1usize));
+    /// let root_id = root.id();
+    /// let mut dag = Dag::with_tasks(vec![root]);
+    /// let ids = dag.add_mapped_tasks(0..10usize, |i| {
+    ///     let mut task = DefaultTask::with_closure(&format!("square {}", i), move |_, _| {
+    ///         Output::new(i * i)
+    ///     });
+    ///     task.set_predecessors_by_id([root_id]);
+    ///     task
+    /// });
+    /// let mut sum = DefaultTask::with_closure("sum", |input, _| {
+    ///     Output::new(input.get_iter().map(|i| *i.get::<usize>().unwrap()).sum::<usize>())
+    /// });
+    /// sum.set_predecessors_by_id(ids);
+    /// dag.add_task(sum);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 285);
+    /// ```
+    pub fn add_mapped_tasks<I, T, F>(&mut self, items: I, make: F) -> Vec<usize>
+    where
+        I: IntoIterator,
+        T: Task + 'static,
+        F: FnMut(I::Item) -> T,
+    {
+        let tasks = items.into_iter().map(make);
+        tasks.map(|task| self.add_task(task)).collect()
+    }
+
     /// Given a yaml configuration file parsing task to generate a dag.
     #[cfg(feature = "yaml")]
     pub fn with_yaml(
@@ -117,7 +453,7 @@ impl Dag {
         specific_actions: HashMap<String, Action>,
     ) -> Result<Dag, DagError> {
         use crate::YamlParser;
-        let parser = Box::new(YamlParser);
+        let parser = Box::new(YamlParser::new());
         Dag::read_tasks(file, parser, specific_actions)
     }
 
@@ -128,10 +464,85 @@ impl Dag {
         specific_actions: HashMap<String, Action>,
     ) -> Result<Dag, DagError> {
         use crate::YamlParser;
-        let parser = Box::new(YamlParser);
+        let parser = Box::new(YamlParser::new());
         Dag::read_tasks_from_str(content, parser, specific_actions)
     }
 
+    /// Write the dag as a yaml configuration file, which `with_yaml_str` reads back.
+    ///
+    /// Each task is given the id `t<id>`, and its `after` attribute refers to the ids of its
+    /// predecessors. The actions are written with the attributes given by [`Action::config`],
+    /// e.g. `cmd` for a `CommandAction`, along with the `enabled` and `labels` attributes.
+    ///
+    /// Returns [`DagError::Unserializable`] with the name of the first task whose action
+    /// cannot be written, such as a closure.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{CommandAction, Dag, DefaultTask};
+    /// use std::collections::HashMap;
+    /// let a = DefaultTask::with_action("Task 1", CommandAction::new("echo a"));
+    /// let mut b = DefaultTask::with_action("Task 2", CommandAction::new("echo b"));
+    /// b.set_predecessors(&[&a]);
+    /// let yaml = Dag::with_tasks(vec![a, b]).to_yaml().unwrap();
+    /// let mut dag = Dag::with_yaml_str(&yaml, HashMap::new()).unwrap();
+    /// assert!(dag.start().unwrap());
+    /// ```
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String, DagError> {
+        use yaml_rust::{yaml::Hash, Yaml, YamlEmitter};
+
+        let key = |id: usize| Yaml::String(format!("t{}", id));
+        let mut ids: Vec<usize> = self.tasks.keys().copied().collect();
+        ids.sort_unstable();
+        let mut tasks = Hash::new();
+        for id in ids {
+            let task = &self.tasks[&id];
+            let attrs = task
+                .action()
+                .config()
+                .ok_or(DagError::Unserializable(task.name().to_string()))?;
+            let mut item = Hash::new();
+            item.insert(
+                Yaml::String("name".to_owned()),
+                Yaml::String(task.name().to_owned()),
+            );
+            if !task.precursors().is_empty() {
+                let after = task.precursors().iter().map(|id| key(*id)).collect();
+                item.insert(Yaml::String("after".to_owned()), Yaml::Array(after));
+            }
+            if !task.enabled() {
+                item.insert(Yaml::String("enabled".to_owned()), Yaml::Boolean(false));
+            }
+            if task.allow_failure() {
+                item.insert(
+                    Yaml::String("allow_failure".to_owned()),
+                    Yaml::Boolean(true),
+                );
+            }
+            let labels = task.labels();
+            if !labels.is_empty() {
+                let labels = labels
+                    .iter()
+                    .map(|(k, v)| (Yaml::String(k.to_owned()), Yaml::String(v.to_owned())))
+                    .collect();
+                item.insert(Yaml::String("labels".to_owned()), Yaml::Hash(labels));
+            }
+            for (attr, value) in attrs {
+                item.insert(Yaml::String(attr.to_owned()), json_to_yaml(value));
+            }
+            tasks.insert(key(id), Yaml::Hash(item));
+        }
+        let mut root = Hash::new();
+        root.insert(Yaml::String("dagrs".to_owned()), Yaml::Hash(tasks));
+
+        let mut out = String::new();
+        // Writing to a string cannot fail.
+        YamlEmitter::new(&mut out).dump(&Yaml::Hash(root)).unwrap();
+        out.push('\n');
+        Ok(out)
+    }
+
     /// Generates a dag with the user given path to a custom parser and task config file.
     pub fn with_config_file_and_parser(
         file: &str,
@@ -150,6 +561,69 @@ impl Dag {
         Dag::read_tasks_from_str(content, parser, specific_actions)
     }
 
+    /// Create a new dag with the same tasks, dependencies and settings, but none of the state
+    /// of this dag, so that several copies of one pipeline can run independently, e.g. with
+    /// different environment variables set by `set_env`.
+    ///
+    /// The tasks of the copy are [`DefaultTask`](crate::DefaultTask)s with the same ids, names,
+    /// dependencies and settings as the original tasks. Their actions are shared with the
+    /// original tasks rather than copied, which is sound as long as the actions do not rely on
+    /// interior mutable state: then their runs in different copies cannot affect each other.
+    /// The environment variables, middlewares and completion hooks are shared the same way.
+    pub fn clone_structure(&self) -> Dag {
+        let mut dag = Dag::with_tasks(
+            self.tasks
+                .values()
+                .map(|task| DefaultTask::copy_of(task.as_ref()))
+                .collect(),
+        );
+        dag.env = self.env.clone();
+        dag.keep_going = self.keep_going;
+        dag.on_complete = self.on_complete.clone();
+        dag.timeout = self.timeout;
+        dag.middlewares = self.middlewares.clone();
+        dag.handle_signals = self.handle_signals;
+        dag.log_dir = self.log_dir.clone();
+        dag.require_connected = self.require_connected;
+        dag.warn_redundant_edges = self.warn_redundant_edges;
+        dag.temp_dirs = self.temp_dirs;
+        dag.keep_failed_temp_dirs = self.keep_failed_temp_dirs;
+        dag.eager_output_drop = self.eager_output_drop;
+        dag.warn_on_empty_consumed = self.warn_on_empty_consumed;
+        dag.convert_inputs = self.convert_inputs;
+        dag.retain_inputs = self.retain_inputs;
+        dag.script_dir = self.script_dir.clone();
+        dag.shared_state = self.shared_state.clone();
+        dag.keep_script_files = self.keep_script_files;
+        dag.root_input = self.root_input.clone();
+        dag.seeded_inputs = self.seeded_inputs.clone();
+        dag.compress_outputs = self.compress_outputs;
+        dag.on_failure = self.on_failure.clone();
+        dag.file_env = self.file_env.clone();
+        dag.stall_timeout = self.stall_timeout;
+        dag.abort_on_stall = self.abort_on_stall;
+        dag.inputs_as_env = self.inputs_as_env;
+        dag.max_depth = self.max_depth;
+        dag.result_task = self.result_task.clone();
+        dag.resource_budget = self.resource_budget;
+        dag.saturation_callback = self.saturation_callback.clone();
+        dag.teardowns = self.teardowns.clone();
+        dag.output_transform = self.output_transform.clone();
+        dag.schedule_seed = self.schedule_seed;
+        dag.default_timeout = self.default_timeout;
+        dag.default_retry = self.default_retry.clone();
+        dag.scheduler_stats = self
+            .scheduler_stats
+            .as_ref()
+            .map(|_| SchedulerRecorder::new());
+        dag.type_drift = self
+            .type_drift
+            .as_ref()
+            .map(|drift| TypeDrift::new(drift.store().to_owned()));
+        dag.unconsumed_output = self.unconsumed_output;
+        dag
+    }
+
     /// Set the flag that indicates whether the task should continue to execute as much as possible.
     /// This means that even if an error occurs during the execution of a task, the subsequent independent tasks
     /// will continue to execute unless a dependency error occurs.
@@ -158,8 +632,460 @@ impl Dag {
         self
     }
 
-    /// Parse the content of the configuration file into a series of tasks and generate a dag.
+    /// Set the maximum time the whole dag is allowed to run. When it expires, the tasks that
+    /// have not started are cancelled, the running tasks are abandoned, and `start` returns
+    /// [`DagError::Timeout`].
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Fail the attempts of the tasks that run for longer than `timeout`, unless the task sets
+    /// a timeout of its own with `DefaultTask::set_timeout`, which takes precedence. Unlike
+    /// `set_timeout`, which limits the whole run, it limits each attempt of each task, and a
+    /// task that times out is retried according to its retry policy, once the attempt that
+    /// timed out returned, unless it takes too long, see `DefaultTask::set_timeout`.
+    ///
+    /// The tasks parsed from configuration files have no timeout of their own, so they all use
+    /// this one.
+    pub fn set_default_timeout(&mut self, timeout: Duration) {
+        self.default_timeout = Some(timeout);
+    }
+
+    /// Retry the tasks that fail according to `policy`, unless the task sets a policy of its
+    /// own with `DefaultTask::set_retry`, which then replaces this one as a whole. The tasks
+    /// parsed from configuration files have no policy of their own, so they all use this one.
+    /// A task may still choose which of its failures are retried, see
+    /// `DefaultTask::set_retry_if`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, RetryPolicy};
+    /// use std::time::Duration;
+    ///
+    /// let slow = DefaultTask::with_closure("slow", |_input, _env| {
+    ///     std::thread::sleep(Duration::from_millis(100));
+    ///     Output::empty()
+    /// });
+    /// let mut dag = Dag::with_tasks(vec![slow]);
+    /// dag.set_default_timeout(Duration::from_millis(10));
+    /// dag.set_default_retry(RetryPolicy::new(1));
+    /// assert!(!dag.start().unwrap());
+    /// ```
+    pub fn set_default_retry(&mut self, policy: RetryPolicy) {
+        self.default_retry = Some(policy);
+    }
+
+    /// Watch the run for stalls: if no task changes state for `window` while some tasks have
+    /// not finished, a warning is logged. Since a running task does not change state until it
+    /// ends, the window should be longer than the longest task.
+    ///
+    /// The watchdog runs along `start` and the other methods running the dag by themselves,
+    /// see `set_abort_on_stall` to abort a stalled run.
+    pub fn set_stall_timeout(&mut self, window: Duration) {
+        self.stall_timeout = Some(window);
+    }
+
+    /// Abort a stalled run with [`DagError::Stalled`], as if it had timed out, rather than only
+    /// logging a warning. See `set_stall_timeout`.
+    pub fn set_abort_on_stall(&mut self, abort: bool) {
+        self.abort_on_stall = abort;
+    }
+
+    /// Give a value to the tasks without predecessors, which otherwise receive an empty
+    /// [`Input`]. Every such task receives the same value, as the only content of its input,
+    /// so a pipeline can be run on data known at runtime only.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    /// let task = DefaultTask::with_closure("Double", |input, _env| {
+    ///     let value = input.get_iter().next().unwrap().get::<usize>().unwrap();
+    ///     Output::new(value * 2)
+    /// });
+    /// let mut dag = Dag::with_tasks(vec![task]);
+    /// dag.set_root_input(21usize);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 42);
+    /// ```
+    pub fn set_root_input<H: Send + Sync + 'static>(&mut self, value: H) {
+        self.root_input = Some(Content::new(value));
+    }
+
+    /// Give `input` to the named task as if its predecessors had produced it, e.g. to test a
+    /// task in the middle of the dag with controlled inputs.
+    ///
+    /// The seeded input overrides the outputs of the predecessors: the task neither waits for
+    /// them nor reads their outputs, so it may run before them or without them. Along
+    /// with `rerun_from`, which may be called on a dag that has not run yet, this runs a
+    /// subtree of the dag in isolation, without running the tasks upstream of it.
+    ///
+    /// Returns [`DagError::TaskNotFound`] if there is no such task.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Input, Output, Task, task::Content};
+    /// let fetch = DefaultTask::with_closure("Fetch", |_input, _env| panic!("no network"));
+    /// let mut count = DefaultTask::with_closure("Count", |input, _env| {
+    ///     Output::new(input.get_iter().next().unwrap().get::<String>().unwrap().len())
+    /// });
+    /// count.set_predecessors(&[&fetch]);
+    /// let mut dag = Dag::with_tasks(vec![fetch, count]);
+    /// let input = Input::new(vec![Content::new("hello".to_string())]);
+    /// dag.seed_input("Count", input).unwrap();
+    /// assert!(dag.rerun_from("Count").unwrap());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 5);
+    /// ```
+    pub fn seed_input(&mut self, name: &str, input: Input) -> Result<(), DagError> {
+        let id = self
+            .find_task_id(name)
+            .ok_or(DagError::TaskNotFound(name.to_string()))?;
+        self.seeded_inputs.insert(id, input);
+        Ok(())
+    }
+
+    /// Read the standard input to its end, and give it as a `String` to the tasks without
+    /// predecessors. See `set_root_input`.
+    pub fn set_root_input_from_stdin(&mut self) -> std::io::Result<()> {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut text)?;
+        self.set_root_input(text);
+        Ok(())
+    }
+
+    /// Give each task its own empty temporary directory, so that tasks running in parallel do
+    /// not collide on scratch files. The path of the directory is set in the environment
+    /// variables of the task as [`EnvVar::TASK_TMPDIR`], and the directory is removed once
+    /// the task finishes.
+    pub fn set_task_temp_dirs(&mut self, enabled: bool) {
+        self.temp_dirs = enabled;
+    }
+
+    /// Give the outputs of the predecessors of each task as JSON in its environment variables,
+    /// so that commands and scripts can read structured data produced by tasks written in
+    /// Rust. The output of a predecessor named `Fetch users` is held by the variable
+    /// `DAGRS_INPUT_FETCH_USERS`: the name is upper-cased, and every character other than an
+    /// ASCII letter or digit is replaced with `_`. If several predecessors end up with the
+    /// same variable, the first one declared wins.
+    ///
+    /// The variables are set in the [`EnvVar`] given to the actions, and exported to the
+    /// processes of commands and scripts. Only the outputs that [`Content::to_json`] supports
+    /// are given, and outputs larger than 64 KiB in JSON are left out with a warning, since
+    /// the environment of a process is limited in size. The lazy outputs that no task read
+    /// yet, see [`Output::lazy`], are left out as well, so that they are not computed for it.
+    pub fn set_inputs_as_env(&mut self, enabled: bool) {
+        self.inputs_as_env = enabled;
+    }
+
+    /// Keep the temporary directories of the tasks that fail, for debugging. Their paths are
+    /// logged. See `set_task_temp_dirs`.
+    pub fn set_keep_failed_temp_dirs(&mut self, keep: bool) {
+        self.keep_failed_temp_dirs = keep;
+    }
+
+    /// Drop the output of each task as soon as all its successors read it, to save memory in
+    /// large runs, e.g. long chains of tasks passing large values along.
+    ///
+    /// This is a deliberate trade-off: once the dag finished, the outputs read by all the
+    /// successors are gone, so `get_results` mostly returns the outputs of the last tasks, and
+    /// `rerun_from` runs the tasks without the dropped outputs in their inputs. The result of
+    /// the dag is kept, since the last task has no successor.
+    pub fn set_eager_output_drop(&mut self, enabled: bool) {
+        self.eager_output_drop = enabled;
+    }
+
+    /// Decide what happens to the outputs of the tasks without successors, which no task
+    /// consumes: they are kept by default, so that they can be read with `get_results` after
+    /// the run. The result of the dag is always kept, see [`UnconsumedOutput`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, UnconsumedOutput};
+    ///
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::new(1usize));
+    /// let b = DefaultTask::with_closure("b", |_input, _env| Output::new(2usize));
+    /// let mut dag = Dag::with_tasks(vec![a, b]);
+    /// dag.set_result_task("b");
+    /// dag.set_unconsumed_output_policy(UnconsumedOutput::Discard);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(dag.get_result_of::<usize>("a"), None);
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 2);
+    /// ```
+    pub fn set_unconsumed_output_policy(&mut self, policy: UnconsumedOutput) {
+        self.unconsumed_output = policy;
+    }
+
+    /// Convert the outputs of the predecessors of a task into the input type it declares with
+    /// `declares_input`, so that a task producing text can feed one expecting JSON, or the
+    /// other way round, without glue code. The declared input type acts as the format the
+    /// task prefers, and the declared output types of its predecessors may then differ from it
+    /// as long as they can be converted.
+    ///
+    /// The conversions cover `String`, `serde_json::Value`, `Vec<u8>`, `i64`, `f64` and the
+    /// lines produced by `CommandAction`, of which the standard output is converted: texts are
+    /// parsed into JSON values and numbers, JSON values other than strings are serialized into
+    /// texts, and so on. Floats are not converted into integers. An output that cannot be
+    /// converted, such as a text that is not a number, is passed as is and a warning is logged.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{CommandAction, Dag, DefaultTask, Output};
+    /// use serde_json::Value;
+    ///
+    /// let config = DefaultTask::with_action("config", CommandAction::new(r#"echo '{"port": 80}'"#));
+    /// let mut port = DefaultTask::with_closure("port", |input, _env| {
+    ///     let config = input.get_iter().next().unwrap().get::<Value>().unwrap();
+    ///     Output::new(config["port"].as_i64().unwrap())
+    /// });
+    /// port.declares_input::<Value>();
+    /// port.set_predecessors(&[&config]);
+    /// let mut dag = Dag::with_tasks(vec![config, port]);
+    /// dag.set_convert_inputs(true);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*dag.get_result::<i64>().unwrap(), 80);
+    /// ```
+    pub fn set_convert_inputs(&mut self, convert: bool) {
+        self.convert_inputs = convert;
+    }
+
+    /// Log a warning when a task that has successors succeeds with an empty output, and when
+    /// a task succeeds without reading the outputs of its predecessors, which are often
+    /// mistakes in the flow of data between the tasks. Off by default, since some tasks only
+    /// depend on others for their order.
+    ///
+    /// An input counts as read as soon as the action iterates over it or looks up the output
+    /// of a predecessor, whether or not it uses the values.
+    pub fn set_warn_on_empty_consumed(&mut self, enabled: bool) {
+        self.warn_on_empty_consumed = enabled;
+    }
+
+    /// Start the tasks in a random order derived from `seed` rather than in the order of the
+    /// execution sequence, to find tasks that rely on the order their siblings run in by
+    /// accident. Running with different seeds shuffles the tasks differently, and running again
+    /// with the seed of a failed run starts the tasks in the same order.
+    ///
+    /// The seed gives a random order of the tasks in which each task comes after its
+    /// predecessors, and each task waits for the ones before it to start before starting
+    /// itself, so that the order does not depend on the timing of the runtime. The tasks still
+    /// run concurrently once started, but a ready task may wait for a task placed before it
+    /// whose predecessors are slower, so the dag may take longer to run.
+    pub fn set_schedule_seed(&mut self, seed: u64) {
+        self.schedule_seed = Some(seed);
+    }
+
+    /// Record how many tasks are waiting for their predecessors, ready to start and running
+    /// whenever a task moves from one phase to the next, to be inspected with
+    /// `scheduler_stats` after a run. Nothing is recorded unless enabled.
+    pub fn set_record_scheduler_stats(&mut self, enabled: bool) {
+        self.scheduler_stats = enabled.then(SchedulerRecorder::new);
+    }
+
+    /// Record the output type of every task in the JSON file `store`, and log a warning when a
+    /// task outputs another type than in the previous run, e.g. a `String` where its
+    /// successors read a `usize`. The changes of the last run are given by `type_changes`.
+    ///
+    /// The types are compared by name, so a type renamed or moved to another module counts as
+    /// a change. The store is read when a run starts and written when it ends, keeping the
+    /// types recorded before for the tasks that produced no output. Nothing is recorded
+    /// unless enabled.
+    pub fn set_type_drift_detection(&mut self, store: impl Into<PathBuf>) {
+        self.type_drift = Some(TypeDrift::new(store.into()));
+    }
+
+    /// Retain the input given to each task once it ran, to be inspected with `get_task_input`.
+    /// The inputs hold the outputs of the predecessors, so this keeps them in memory as long as
+    /// the dag, uncompressed even if `set_compress_outputs` is enabled.
+    pub fn set_retain_inputs(&mut self, retain: bool) {
+        self.retain_inputs = retain;
+    }
+
+    /// Share a mutable state between the actions of the tasks, e.g. to aggregate metrics
+    /// across tasks. The actions get it from their environment with [`EnvVar::shared_state`].
+    ///
+    /// The actions of the tasks run concurrently, on several threads, so the state is behind
+    /// a mutex. To keep the tasks from waiting for each other, hold the lock briefly: compute
+    /// what to add outside of it, then lock the state only to merge it in. The lock is
+    /// poisoned if an action panics while holding it, which the other actions may recover
+    /// from with `lock().unwrap_or_else(PoisonError::into_inner)`. The state is shared with
+    /// the copies made by `clone_structure`, like the environment.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let tasks = (1..=3usize).map(|n| {
+    ///     DefaultTask::with_closure(&format!("task {}", n), move |_input, env| {
+    ///         let total = env.shared_state::<usize>().unwrap();
+    ///         *total.lock().unwrap() += n;
+    ///         Output::empty()
+    ///     })
+    /// });
+    /// let total = Arc::new(Mutex::new(0usize));
+    /// let mut dag = Dag::with_tasks(tasks.collect());
+    /// dag.set_shared_state(total.clone());
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*total.lock().unwrap(), 6);
+    /// ```
+    pub fn set_shared_state<T: Send + 'static>(&mut self, state: Arc<Mutex<T>>) {
+        self.shared_state = Some(Content::new(state));
+    }
+
+    /// Write the files of the scripts run by [`ScriptAction`](crate::ScriptAction)s to the given
+    /// directory rather than the system temporary directory, e.g. where the latter is not
+    /// writable. Each file is given a unique name, so scripts running in parallel do not collide.
+    pub fn set_script_temp_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.script_dir = Some(dir.into());
+    }
+
+    /// Keep the files of the scripts once they ran, for inspection, rather than removing them.
+    /// Their paths are logged. See `set_script_temp_dir`.
+    pub fn set_keep_script_files(&mut self, keep: bool) {
+        self.keep_script_files = keep;
+    }
+
+    /// Set a task to run once the dag finishes, if any of its tasks failed, e.g. to notify
+    /// someone and clean up. The task is not part of the graph: its predecessors are ignored,
+    /// and it runs after all the other tasks, whatever they depend on.
+    ///
+    /// Its input holds one [`TaskReport`] per failed task, giving its name and error, in the
+    /// order of the task names. The run is failed anyway, so the outcome of the handler does
+    /// not change the result of `start`: if the handler fails too, its error is only logged.
+    /// The handler is not run when the dag is interrupted by its timeout or a signal.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, TaskReport};
+    /// use std::sync::{Arc, Mutex};
+    /// let failing = DefaultTask::with_closure("Task 1", |_input, _env| {
+    ///     Output::error("disk full".to_string())
+    /// });
+    /// let failures = Arc::new(Mutex::new(Vec::new()));
+    /// let seen = failures.clone();
+    /// let handler = DefaultTask::with_closure("Notify", move |input, _env| {
+    ///     for failure in input.get_iter() {
+    ///         seen.lock().unwrap().push(failure.get::<TaskReport>().unwrap().clone());
+    ///     }
+    ///     Output::empty()
+    /// });
+    /// let mut dag = Dag::with_tasks(vec![failing]);
+    /// dag.set_on_failure_task(handler);
+    /// assert!(!dag.start().unwrap());
+    /// let failures = failures.lock().unwrap();
+    /// assert_eq!(failures[0].name, "Task 1");
+    /// assert_eq!(failures[0].error.as_deref(), Some("disk full"));
+    /// ```
+    pub fn set_on_failure_task(&mut self, task: impl Task + 'static) {
+        self.on_failure = Some(Arc::new(task));
+    }
+
+    /// Store the large text outputs of the tasks compressed, trading CPU for memory on
+    /// pipelines passing big outputs around. This is transparent to the actions: an output is
+    /// decompressed when a successor or the report first reads it, and the successors reading
+    /// it at the same time share the decompressed value.
+    ///
+    /// Only `String`, `Vec<String>` and command outputs of at least 16 KiB are compressed,
+    /// since compressing smaller outputs costs more than it saves.
+    pub fn set_compress_outputs(&mut self, compress: bool) {
+        self.compress_outputs = compress;
+    }
+
+    /// Limit the resources requested by the tasks running at the same time, in thousandths of
+    /// a core and in megabytes. A task only starts once its request, see
+    /// `Task::resource_request`, fits in what the running tasks leave of the budget; until then
+    /// it waits, even if its predecessors are done.
+    ///
+    /// The tasks that do not declare a request are not held back. A task requesting more than
+    /// the whole budget could never start, so the dag fails to start with
+    /// [`DagError::OverBudget`].
+    pub fn set_resource_budget(&mut self, cpu_millis: u32, memory_mb: u32) {
+        self.resource_budget = Some(ResourceRequest::new(cpu_millis, memory_mb));
+    }
+
+    /// Call `callback` with true when the resource budget of the dag becomes saturated, that
+    /// is when a task whose predecessors are done has to wait for resources, and with false
+    /// when no task waits anymore, e.g. to add capacity or to log the contention.
+    ///
+    /// The callback is only called on these transitions, not whenever a task takes or gives
+    /// back resources, and the calls alternate, starting with true. It is never called without
+    /// a budget, see `set_resource_budget`. The calls are serialized, and the tasks waiting
+    /// for resources wait for the callback to return, so it should be quick.
+    pub fn set_saturation_callback(&mut self, callback: impl Fn(bool) + Send + Sync + 'static) {
+        self.saturation_callback = Some(Arc::new(callback));
+    }
+
+    /// Limit the depth of the dag, that is the number of tasks along its longest chain of
+    /// dependencies, as a safety valve for generated dags. The depth is unbounded by default.
+    ///
+    /// The limit is checked whenever the dag is initialized, which is when it starts, so the
+    /// tasks added with `add_task` or `add_mapped_tasks` are checked too. A deeper dag fails
+    /// with [`DagError::TooDeep`], giving the names of the tasks along a longest chain.
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = Some(depth);
+    }
+
+    /// Make the output of the task with the given name the result of the dag, returned by
+    /// `get_result` and `start_and_get`, instead of the output of the last task. This removes
+    /// the ambiguity of dags with several terminal tasks, and lets the result come from a task
+    /// that has successors, whose output is then kept even with `set_eager_output_drop`.
+    ///
+    /// A value passed to [`Output::finish`] by a task that ended the run is still the result.
+    /// The task is looked up when the dag is initialized, which is when it starts: the dag
+    /// fails to start with [`DagError::TaskNotFound`] if there is no such task.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::new(1usize));
+    /// let b = DefaultTask::with_closure("b", |_input, _env| Output::new(2usize));
+    /// let mut dag = Dag::with_tasks(vec![a, b]);
+    /// dag.set_result_task("a");
+    /// assert_eq!(dag.start_and_get::<usize>().unwrap(), Some(1));
+    /// ```
+    pub fn set_result_task(&mut self, name: &str) {
+        self.result_task = Some(name.to_owned());
+    }
+
+    /// Require all tasks to be connected to each other, so that starting the dag fails with
+    /// [`DagError::Disconnected`] if some tasks do not contribute to the rest of the dag.
+    ///
+    /// Tasks are connected when one of them depends on the other, and connection is
+    /// transitive, regardless of the direction of the dependencies: a dag is connected if any
+    /// task can be reached from any other by following dependencies either way. Such a dag may
+    /// have several roots (tasks without predecessors) and several terminals (tasks without
+    /// successors). Otherwise it is made of several independent components, and the tasks
+    /// outside the largest one are reported, e.g. a forgotten isolated task.
+    pub fn set_require_connected(&mut self, require_connected: bool) {
+        self.require_connected = require_connected;
+    }
+
+    /// Log the dependencies found by [`Dag::redundant_edges`] as warnings when the dag starts.
+    ///
+    /// Disabled by default: finding them takes memory quadratic in the number of tasks, about
+    /// 300 MB for 50,000 tasks, on every start.
+    pub fn set_warn_redundant_edges(&mut self, warn_redundant_edges: bool) {
+        self.warn_redundant_edges = warn_redundant_edges;
+    }
 
+    /// Write the standard output and standard error of the tasks running commands or scripts to
+    /// `<task name>.out` and `<task name>.err` in the given directory, which is created if
+    /// missing. The output of the tasks is still captured as usual. Path separators in task
+    /// names are replaced with `_`.
+    pub fn set_log_dir(&mut self, dir: impl Into<PathBuf>) {
+        self.log_dir = Some(dir.into());
+    }
+
+    /// Make SIGINT and SIGTERM (Ctrl-C on Windows) cancel the run. When one of them is
+    /// received, no more tasks are started, the commands that are still running are killed,
+    /// and `start` returns [`DagError::Cancelled`].
+    ///
+    /// This is disabled by default: the signal handlers stay installed once the dag has run,
+    /// so these signals no longer terminate the process.
+    pub fn set_handle_signals(&mut self, handle_signals: bool) {
+        self.handle_signals = handle_signals;
+    }
+
+    /// Parse the content of the configuration file into a series of tasks and generate a dag.
     fn read_tasks(
         file: &str,
         parser: Box<dyn Parser>,
@@ -174,7 +1100,6 @@ impl Dag {
     }
 
     /// Parse the content of the configuration file into a series of tasks and generate a dag.
-
     fn read_tasks_from_str(
         content: &str,
         parser: Box<dyn Parser>,
@@ -192,37 +1117,116 @@ impl Dag {
     ///
     /// This operation will initialize `dagrs.rely_graph` if no error occurs.
     fn create_graph(&mut self) -> Result<(), DagError> {
-        let size = self.tasks.len();
-        self.rely_graph.set_graph_size(size);
+        self.rely_graph = self.build_graph()?;
+        Ok(())
+    }
 
-        // Add Node (create id - index mapping)
-        self.tasks
-            .iter()
-            .for_each(|(&n, _)| self.rely_graph.add_node(n));
+    /// Build a [`Graph`] from the dependencies of the tasks currently held by the dag.
+    fn build_graph(&self) -> Result<Graph, DagError> {
+        let mut graph = Graph::new();
+        graph.set_graph_size(self.tasks.len());
+
+        // Add Node (create id - index mapping), in the order the tasks were created in, so that
+        // the ties of the execution sequence are broken the same way in every process.
+        let mut ids: Vec<usize> = self.tasks.keys().copied().collect();
+        ids.sort_unstable();
+        ids.iter().for_each(|&n| graph.add_node(n));
 
         // Form Graph
-        for (&id, task) in self.tasks.iter() {
-            let index = self.rely_graph.find_index_by_id(&id).unwrap();
+        for id in ids {
+            let task = &self.tasks[&id];
+            let index = graph.find_index_by_id(&id).unwrap();
 
             for rely_task_id in task.precursors() {
                 // Rely task existence check
-                let rely_index = self
-                    .rely_graph
+                let rely_index = graph
                     .find_index_by_id(rely_task_id)
                     .ok_or(DagError::RelyTaskIllegal(task.name().to_string()))?;
 
-                self.rely_graph.add_edge(rely_index, index);
+                graph.add_edge(rely_index, index);
             }
         }
 
+        Ok(graph)
+    }
+
+    /// Compare the declared output type of every task with the declared input type of each of
+    /// its successors. Edges where either side is undeclared are skipped.
+    fn check_types(&self) -> Result<(), DagError> {
+        for consumer in self.tasks.values() {
+            let expected = match consumer.input_type() {
+                Some(expected) => expected,
+                None => continue,
+            };
+            for producer in consumer.precursors().iter().map(|id| &self.tasks[id]) {
+                match producer.output_type() {
+                    Some(found)
+                        if found != expected
+                            && !(self.convert_inputs && convertible(found, expected)) =>
+                    {
+                        return Err(DagError::TypeMismatch {
+                            producer: producer.name().to_string(),
+                            consumer: consumer.name().to_string(),
+                            expected: expected.name(),
+                            found: found.name(),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Check that the tasks form a single component, otherwise report the tasks outside the
+    /// largest component. Among components of the same size, the one holding the first task
+    /// name in alphabetical order is kept.
+    fn check_connected(&self) -> Result<(), DagError> {
+        let mut components: Vec<Vec<String>> = self
+            .rely_graph
+            .components()
+            .into_iter()
+            .map(|component| {
+                let mut names: Vec<String> = component
+                    .into_iter()
+                    .map(|index| {
+                        let id = self.rely_graph.find_id_by_index(index).unwrap();
+                        self.tasks[&id].name().to_string()
+                    })
+                    .collect();
+                names.sort();
+                names
+            })
+            .collect();
+        if components.len() <= 1 {
+            return Ok(());
+        }
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        let mut names: Vec<String> = components.into_iter().skip(1).flatten().collect();
+        names.sort();
+        Err(DagError::Disconnected(names))
+    }
+
     /// Initialize dags. The initialization process completes three actions:
     /// - Initialize the status of each task execution result.
     /// - Create a graph from task dependencies.
+    /// - Check the declared types along the edges of the graph.
+    /// - Check that the graph is connected, if required.
     /// - Generate task heart sequence according to topological sorting of graph.
     pub(crate) fn init(&mut self) -> Result<(), DagError> {
+        self.finish.reset();
+        self.step_position = None;
+        let env = Arc::make_mut(&mut self.env);
+        env.merge_defaults(&self.file_env);
+        if let Some(state) = &self.shared_state {
+            env.set_variable(EnvVar::SHARED_STATE, state.clone());
+        }
+        // The script settings may have changed since the previous run.
+        match &self.script_dir {
+            Some(dir) => env.set(EnvVar::SCRIPT_DIR, dir.clone()),
+            None => env.remove(EnvVar::SCRIPT_DIR),
+        }
+        env.set(EnvVar::KEEP_SCRIPTS, self.keep_script_files);
         self.execute_states.reserve(self.tasks.len());
         self.tasks.values().for_each(|task| {
             self.execute_states
@@ -230,8 +1234,44 @@ impl Dag {
         });
 
         self.create_graph()?;
+        self.check_types()?;
+        if self.require_connected {
+            self.check_connected()?;
+        }
+        if let Some(max) = self.max_depth {
+            self.check_depth(max)?;
+        }
+        if let Some(name) = &self.result_task {
+            if self.find_task_id(name).is_none() {
+                return Err(DagError::TaskNotFound(name.clone()));
+            }
+        }
+        if let Some(total) = self.resource_budget {
+            if let Some(task) = self
+                .tasks
+                .values()
+                .find(|task| !task.resource_request().fits(&total))
+            {
+                return Err(DagError::OverBudget(task.name().to_string()));
+            }
+            self.budget = Some(Arc::new(ResourceBudget::new(
+                total,
+                self.saturation_callback.clone(),
+            )));
+        }
+        if self.warn_redundant_edges && log_enabled!(Level::Warn) {
+            for (pre, succ) in self
+                .named_redundant_edges(&self.rely_graph)
+                .unwrap_or_default()
+            {
+                warn!(
+                    "Task[{}] depends on task[{}] both directly and indirectly",
+                    succ, pre
+                );
+            }
+        }
 
-        match self.rely_graph.topo_sort() {
+        match self.rely_graph.topo_sort(&self.weights(&self.rely_graph)) {
             Some(seq) => {
                 if seq.is_empty() {
                     return Err(DagError::EmptyJob);
@@ -247,122 +1287,1111 @@ impl Dag {
         }
     }
 
+    /// Check that no chain of dependencies holds more than `max` tasks.
+    fn check_depth(&self, max: usize) -> Result<(), DagError> {
+        let path = self.rely_graph.longest_path().ok_or(DagError::LoopGraph)?;
+        if path.len() <= max {
+            return Ok(());
+        }
+        let path = path
+            .into_iter()
+            .map(|index| {
+                let id = self.rely_graph.find_id_by_index(index).unwrap();
+                self.tasks[&id].name().to_string()
+            })
+            .collect();
+        Err(DagError::TooDeep { max, path })
+    }
+
     /// This function is used for the execution of a single dag.
+    ///
+    /// It blocks on a runtime of its own, so it must not be called from an async context: use
+    /// `run_async` there instead.
     pub fn start(&mut self) -> Result<bool, DagError> {
         // If the current continuable state is false, the task will start failing.
         if self.can_continue.load(Ordering::Acquire) {
-            self.init().map_or_else(Err, |_| {
-                Ok(tokio::runtime::Runtime::new()
-                    .unwrap()
-                    .block_on(async { self.run().await }))
-            })
+            if let Err(err) = self.init() {
+                self.notify_complete();
+                return Err(err);
+            }
+            self.block_on_run(self.exe_sequence.clone())
         } else {
             Ok(false)
         }
     }
 
-    /// Execute tasks sequentially according to the execution sequence given by
-    /// topological sorting, and cancel the execution of subsequent tasks if an
-    /// error is encountered during task execution.
-    pub(crate) async fn run(&self) -> bool {
-        debug!("[Start]{} -> [End]", {
-            self.exe_sequence
-                .iter()
-                .map(|id| self.tasks[id].name())
-                .collect::<Vec<&str>>()
-                .join(" -> ")
-        });
-
-        let handles = self
-            .exe_sequence
-            .iter()
-            .map(|id| (*id, self.execute_task(self.tasks[id].as_ref())))
-            .collect::<Vec<_>>();
-
-        // Wait for the status of each task to execute. If there is an error in the execution of a task,
-        // the engine will fail to execute and give up executing tasks that have not yet been executed.
-        for (tid, handle) in handles {
-            match handle.await {
-                Ok(succeed) => {
-                    if !succeed {
-                        self.handle_error(tid);
-                    }
+    /// Run the next task of the dag and report what happened to it, or return None once all
+    /// the tasks ran, to drive the dag one task at a time, e.g. from a test or from another
+    /// scheduler. The first call initializes the dag like `start`, and fails in the same way.
+    ///
+    /// The tasks run in the order of the execution sequence, in which each task comes after its
+    /// predecessors and the heaviest of the ready tasks come first, then the ones created first,
+    /// so the steps are deterministic. A task runs as it would with `start`, with its retries and middlewares,
+    /// and it is skipped if a predecessor failed, or after any failure unless the dag keeps
+    /// going. The hooks registered with `on_complete` are invoked when None is first returned.
+    ///
+    /// Running the dag otherwise, e.g. with `start` or `rerun_from`, ends the stepped run, and
+    /// the next call to `step` starts a new one, unless the dag cannot run anymore, in which
+    /// case it returns None like `start` returns false.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, TaskState};
+    ///
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::new(1usize));
+    /// let mut b = DefaultTask::with_closure("b", |input, _env| {
+    ///     Output::new(input.get_iter().next().unwrap().get::<usize>().unwrap() + 1)
+    /// });
+    /// b.set_predecessors(&[&a]);
+    /// let mut dag = Dag::with_tasks(vec![a, b]);
+    /// let step = dag.step().unwrap().unwrap();
+    /// assert_eq!((step.name.as_str(), step.state), ("a", TaskState::Succeeded));
+    /// assert_eq!(dag.task_state("b"), Some(TaskState::Pending));
+    /// assert_eq!(dag.step().unwrap().unwrap().name, "b");
+    /// assert!(dag.step().unwrap().is_none());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 2);
+    /// ```
+    pub fn step(&mut self) -> Result<Option<TaskReport>, DagError> {
+        let position = match self.step_position {
+            Some(position) => position,
+            None => {
+                if !self.can_continue.load(Ordering::Acquire) {
+                    return Ok(None);
                 }
-                Err(err) => {
-                    error!("Task execution encountered an unexpected error! {}", err);
-                    self.handle_error(tid);
+                if let Err(err) = self.init() {
+                    self.notify_complete();
+                    return Err(err);
+                }
+                *self.run_started.lock().unwrap() = Some(Instant::now());
+                0
+            }
+        };
+        self.step_position = Some(position + 1);
+        let id = match self.exe_sequence.get(position) {
+            Some(id) => *id,
+            None => {
+                if position == self.exe_sequence.len() {
+                    self.notify_complete();
                 }
+                return Ok(None);
+            }
+        };
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let task = self.tasks[&id].as_ref();
+        let succeed = runtime
+            .block_on(async { self.execute_task(task, None, false).await })
+            .unwrap_or(false);
+        runtime.shutdown_background();
+        if !succeed {
+            if self.finished() {
+                self.handle_errored_successor(&id, false);
+            } else {
+                self.handle_error(id);
             }
         }
+        let state = &self.execute_states[&id];
+        Ok(Some(TaskReport {
+            name: task.name().to_string(),
+            state: state.state(),
+            error: state.get_error(),
+            duration: state.duration(),
+            resources: state.resources(),
+        }))
+    }
 
-        if self.keep_going {
-            // when keep_going is true, the task will continue to execute as much as possible.
-            // So, the success is evaluated by keep_going_errored.
-            !self.keep_going_errored.load(Ordering::Relaxed)
-        } else {
-            self.can_continue
-                .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
-                .is_ok()
+    /// Execute the dag like `start`, but on the current thread rather than on a multi-threaded
+    /// runtime, for dags that do not need to run tasks in parallel, such as a chain of shell
+    /// commands run from a small program.
+    ///
+    /// The scheduler runs on the current thread, and the actions run one at a time on a single
+    /// helper thread, so that a blocking action does not block the scheduler, e.g. when the
+    /// timeout of the dag expires. Tasks that could run in parallel run one after the other
+    /// instead, so a graph of tasks that only depend on each other in a chain gives the same
+    /// results as with `start`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::new(1usize));
+    /// let mut b = DefaultTask::with_closure("b", |input, _env| {
+    ///     Output::new(input.get_iter().next().unwrap().get::<usize>().unwrap() + 1)
+    /// });
+    /// b.set_predecessors(&[&a]);
+    /// let mut dag = Dag::with_tasks(vec![a, b]);
+    /// assert!(dag.start_blocking_single_threaded().unwrap());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 2);
+    /// ```
+    pub fn start_blocking_single_threaded(&mut self) -> Result<bool, DagError> {
+        if !self.can_continue.load(Ordering::Acquire) {
+            return Ok(false);
         }
+        if let Err(err) = self.init() {
+            self.notify_complete();
+            return Err(err);
+        }
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .max_blocking_threads(1)
+            .build()
+            .unwrap();
+        self.block_on_run_with(runtime, self.exe_sequence.clone())
     }
 
-    /// Execute a given task asynchronously.
-    fn execute_task(&self, task: &dyn Task) -> JoinHandle<bool> {
-        let env = self.env.clone();
-        let task_id = task.id();
-        let task_name = task.name().to_string();
-        let execute_state = self.execute_states[&task_id].clone();
-        let task_out_degree = self.rely_graph.get_node_out_degree(&task_id);
-        let wait_for_input: Vec<Arc<ExecState>> = task
-            .precursors()
-            .iter()
-            .map(|id| self.execute_states[id].clone())
-            .collect();
-        let action = task.action();
-        let can_continue = self.can_continue.clone();
-
-        tokio::spawn(async move {
-            // Wait for the execution result of the predecessor task
-            let mut inputs = Vec::with_capacity(wait_for_input.len());
-            for wait_for in wait_for_input {
-                wait_for.semaphore().acquire().await.unwrap().forget();
-                // When the task execution result of the predecessor can be obtained, judge whether
+    /// Execute the dag like `start`, on the runtime of the caller rather than a new one.
+    ///
+    /// The tasks are spawned on the current tokio runtime, which must be able to run blocking
+    /// tasks, as multi-thread runtimes are. If the run is interrupted, by its timeout or a
+    /// signal, the tasks still running are left to finish in the background.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let task = DefaultTask::with_closure("answer", |_input, _env| Output::new(42usize));
+    ///     let mut dag = Dag::with_tasks(vec![task]);
+    ///     assert!(dag.run_async().await.unwrap());
+    ///     assert_eq!(*dag.get_result::<usize>().unwrap(), 42);
+    /// }
+    /// ```
+    pub async fn run_async(&mut self) -> Result<bool, DagError> {
+        if !self.can_continue.load(Ordering::Acquire) {
+            return Ok(false);
+        }
+        if let Err(err) = self.init() {
+            self.notify_complete();
+            return Err(err);
+        }
+        self.run_interruptible(self.exe_sequence.clone())
+            .await
+            .map_err(|err| self.interrupted(err))
+    }
+
+    /// Execute the given sequence of tasks on a new runtime, honoring the timeout of the dag and
+    /// the signals if they are handled.
+    fn block_on_run(&self, sequence: Vec<usize>) -> Result<bool, DagError> {
+        self.block_on_run_with(Runtime::new().unwrap(), sequence)
+    }
+
+    /// Execute the given sequence of tasks like `block_on_run`, on the given runtime.
+    fn block_on_run_with(&self, runtime: Runtime, sequence: Vec<usize>) -> Result<bool, DagError> {
+        if Handle::try_current().is_ok() {
+            warn!("The dag is started from within an async runtime, use `run_async` instead");
+        }
+        let res = runtime.block_on(self.run_interruptible(sequence));
+        // Do not wait for the tasks that are still running, which were abandoned because the
+        // run was interrupted or because they timed out.
+        runtime.shutdown_background();
+        res.map_err(|err| self.interrupted(err))
+    }
+
+    /// Report an interrupted run.
+    fn interrupted(&self, err: DagError) -> DagError {
+        error!("The dag was interrupted: {}", err);
+        self.notify_complete();
+        err
+    }
+
+    /// Execute the given sequence of tasks, unless the timeout expires or a handled signal is
+    /// received first. An interrupted run stops scheduling the remaining tasks, except for the
+    /// finalizers.
+    async fn run_interruptible(&self, sequence: Vec<usize>) -> Result<bool, DagError> {
+        let finalizers = sequence.clone();
+        let run = async {
+            let run = async {
+                match self.timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, self.run_sequence(sequence))
+                        .await
+                        .map_err(|_| DagError::Timeout),
+                    None => Ok(self.run_sequence(sequence).await),
+                }
+            };
+            match self.stall_timeout {
+                Some(window) => tokio::select! {
+                    res = run => res,
+                    err = self.watch_stalls(window) => Err(err),
+                },
+                None => run.await,
+            }
+        };
+        let res = if self.handle_signals {
+            // The signal handlers are installed before any task starts.
+            tokio::select! {
+                biased;
+                _ = signal::wait_for_signal() => Err(DagError::Cancelled),
+                res = run => res,
+            }
+        } else {
+            run.await
+        };
+        if let Err(err) = &res {
+            match err {
+                DagError::Cancelled => self.cancel(),
+                _ => self.can_continue.store(false, Ordering::Release),
+            }
+            self.run_finalizers(&finalizers).await;
+        }
+        res
+    }
+
+    /// Warn whenever no task changes state for `window` while some tasks have not finished.
+    /// Returns [`DagError::Stalled`] at the first stall if the run is to be aborted, and never
+    /// returns otherwise.
+    async fn watch_stalls(&self, window: Duration) -> DagError {
+        let period = (window / 4).max(Duration::from_millis(10));
+        let mut seen = self.events.len();
+        let mut last_progress = Instant::now();
+        loop {
+            tokio::time::sleep(period).await;
+            let count = self.events.len();
+            if count != seen {
+                seen = count;
+                last_progress = Instant::now();
+                continue;
+            }
+            if last_progress.elapsed() < window {
+                continue;
+            }
+            let unfinished: Vec<&str> = self
+                .execute_states
+                .iter()
+                .filter(|(_, state)| {
+                    matches!(state.state(), TaskState::Pending | TaskState::Running)
+                })
+                .map(|(id, _)| self.tasks[id].name())
+                .collect();
+            if unfinished.is_empty() {
+                continue;
+            }
+            warn!(
+                "No task progressed for {:?}, unfinished tasks: {:?}",
+                last_progress.elapsed(),
+                unfinished
+            );
+            if self.abort_on_stall {
+                return DagError::Stalled(window);
+            }
+            last_progress = Instant::now();
+        }
+    }
+
+    /// Stop starting new tasks, letting the running ones finish.
+    pub(crate) fn abandon(&self) {
+        self.can_continue.store(false, Ordering::Release);
+    }
+
+    /// Stop starting new tasks, kill the commands that are still running and fail the gates
+    /// that are waiting. Since signals are received by the whole process, the commands and
+    /// gates of all dags are stopped.
+    pub(crate) fn cancel(&self) {
+        self.can_continue.store(false, Ordering::Release);
+        kill_running_commands();
+        cancel_waiting_gates();
+    }
+
+    /// Re-run the given task and all its (direct or indirect) successors, reusing the outputs
+    /// that the other tasks produced in the previous run.
+    ///
+    /// This is meant to be called after `start`, typically once a failed task has been fixed.
+    /// The named task and its successors are reset to pending and executed again, while the
+    /// tasks upstream of it are not executed: their previous outputs are passed on as inputs.
+    /// If an upstream task did not succeed in the previous run, the tasks depending on it are
+    /// skipped again.
+    ///
+    /// If the dag has not run yet, no upstream task has succeeded, so only the tasks whose
+    /// inputs were given with `seed_input` and their successors can run.
+    ///
+    /// Returns [`DagError::TaskNotFound`] if there is no such task.
+    pub fn rerun_from(&mut self, name: &str) -> Result<bool, DagError> {
+        let id = self
+            .find_task_id(name)
+            .ok_or(DagError::TaskNotFound(name.to_string()))?;
+        if self.execute_states.is_empty() {
+            if let Err(err) = self.init() {
+                self.notify_complete();
+                return Err(err);
+            }
+        }
+
+        let rerun: HashSet<usize> = self
+            .rely_graph
+            .get_node_successors(&id)
+            .into_iter()
+            .map(|index| self.rely_graph.find_id_by_index(index).unwrap())
+            .collect();
+
+        for tid in rerun.iter() {
+            self.execute_states.insert(*tid, Arc::new(ExecState::new()));
+            // Make the retained outputs of upstream tasks available again.
+            for pre in self.tasks[tid].precursors() {
+                if !rerun.contains(pre) {
+                    self.execute_states[pre].semaphore().add_permits(1);
+                }
+            }
+        }
+
+        self.can_continue.store(true, Ordering::Release);
+        self.keep_going_errored.store(false, Ordering::Release);
+        self.finish.reset();
+        self.step_position = None;
+        let sequence = self
+            .exe_sequence
+            .iter()
+            .filter(|tid| rerun.contains(tid))
+            .copied()
+            .collect();
+        self.block_on_run(sequence)
+    }
+
+    /// Run only the tasks whose labels match the filter, along with everything they need.
+    ///
+    /// A task is executed if its labels match the filter, or if it is a (direct or indirect)
+    /// predecessor of such a task: the predecessors always run, whatever their labels, so
+    /// that the selected tasks receive their usual inputs. All the other tasks are not
+    /// executed and are marked as skipped.
+    ///
+    /// Returns [`DagError::EmptyJob`] if no task matches the filter.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, TaskState};
+    /// let build = DefaultTask::with_closure("build", |_input, _env| Output::empty());
+    /// let mut deploy = DefaultTask::with_closure("deploy", |_input, _env| Output::empty());
+    /// deploy.set_predecessors(&[&build]);
+    /// deploy.set_label("tier", "deploy");
+    /// let docs = DefaultTask::with_closure("docs", |_input, _env| Output::empty());
+    /// let mut dag = Dag::with_tasks(vec![build, deploy, docs]);
+    /// assert!(dag.run_subset(|labels| labels.matches("tier", "deploy")).unwrap());
+    /// assert_eq!(dag.task_state("build"), Some(TaskState::Succeeded));
+    /// assert_eq!(dag.task_state("docs"), Some(TaskState::Skipped));
+    /// ```
+    pub fn run_subset(&mut self, filter: impl Fn(&TaskLabels) -> bool) -> Result<bool, DagError> {
+        if !self.can_continue.load(Ordering::Acquire) {
+            return Ok(false);
+        }
+        if let Err(err) = self.init() {
+            self.notify_complete();
+            return Err(err);
+        }
+
+        let mut selected: HashSet<usize> = HashSet::new();
+        let mut stack: Vec<usize> = self
+            .tasks
+            .values()
+            .filter(|task| filter(&task.labels()))
+            .map(|task| task.id())
+            .collect();
+        if stack.is_empty() {
+            self.notify_complete();
+            return Err(DagError::EmptyJob);
+        }
+        while let Some(id) = stack.pop() {
+            if selected.insert(id) {
+                stack.extend(self.tasks[&id].precursors());
+            }
+        }
+
+        // The successors of a skipped task are never selected, so nothing waits on it.
+        let mut sequence = Vec::with_capacity(selected.len());
+        for id in self.exe_sequence.iter() {
+            if selected.contains(id) {
+                sequence.push(*id);
+            } else {
+                self.execute_states[id].set_state(TaskState::Skipped);
+                self.events
+                    .record(self.tasks[id].name(), RunEventKind::Skipped);
+            }
+        }
+        self.block_on_run(sequence)
+    }
+
+    /// Execute every task to check a pipeline, e.g. against a new environment, whatever the
+    /// failures, and return the report of what passed and what failed.
+    ///
+    /// The tasks still run in dependency order, but a failure neither stops the dag nor skips
+    /// its successors: as if every task were allowed to fail, the successors of a failed task
+    /// run with an empty input in place of its output. Unlike `set_allow_failure`, the failed
+    /// tasks keep the [`TaskState::Failed`] state, so the report is not successful if any task
+    /// failed. The failure handler is not run. Disabled tasks are skipped as usual.
+    ///
+    /// Returns an error if the dag cannot be started or is interrupted.
+    pub fn start_test_mode(&mut self) -> Result<RunReport, DagError> {
+        self.test_mode = true;
+        let result = self.start();
+        self.test_mode = false;
+        result.map(|_| self.run_report())
+    }
+
+    /// Start the dag like `start`, but give up and return [`DagError::Timeout`] if it does
+    /// not finish within the given time. This is a shortcut for `set_timeout` followed by `start`.
+    pub fn start_with_timeout(&mut self, timeout: Duration) -> Result<bool, DagError> {
+        self.set_timeout(timeout);
+        self.start()
+    }
+
+    /// Execute tasks sequentially according to the execution sequence given by
+    /// topological sorting, and cancel the execution of subsequent tasks if an
+    /// error is encountered during task execution.
+    pub(crate) async fn run(&self) -> bool {
+        self.run_sequence(self.exe_sequence.clone()).await
+    }
+
+    /// Execute the given tasks, which must be in topological order.
+    async fn run_sequence(&self, sequence: Vec<usize>) -> bool {
+        let (sequence, order) = match self.schedule_seed {
+            Some(seed) => {
+                let precursors = |id| self.tasks[&id].precursors().to_vec();
+                let sequence = seeded_order(&sequence, precursors, seed);
+                let order = ScheduleOrder::new(sequence.len());
+                (sequence, Some(order))
+            }
+            None => (sequence, None),
+        };
+        if let Some(recorder) = &self.scheduler_stats {
+            recorder.start(sequence.len());
+        }
+        self.progress.start(sequence.len());
+        if let Some(drift) = &self.type_drift {
+            drift.start();
+        }
+        *self.run_started.lock().unwrap() = Some(Instant::now());
+        debug!("[Start]{} -> [End]", {
+            sequence
+                .iter()
+                .map(|id| self.tasks[id].name())
+                .collect::<Vec<&str>>()
+                .join(" -> ")
+        });
+
+        let handles = sequence
+            .iter()
+            .enumerate()
+            .map(|(position, id)| {
+                let turn = order.as_ref().map(|order| order.turn(position));
+                (*id, self.execute_task(self.tasks[id].as_ref(), turn, false))
+            })
+            .collect::<Vec<_>>();
+
+        // Wait for the status of each task to execute. If there is an error in the execution of a task,
+        // the engine will fail to execute and give up executing tasks that have not yet been executed.
+        for (tid, handle) in handles {
+            let succeed = match handle.await {
+                Ok(succeed) => succeed,
+                Err(err) => {
+                    error!("Task execution encountered an unexpected error! {}", err);
+                    false
+                }
+            };
+            if succeed {
+                continue;
+            }
+            if self.finished() {
+                // The failure of a task still running when the dag was finished is ignored,
+                // only its successors are released so that they are skipped.
+                self.handle_errored_successor(&tid, false);
+            } else {
+                self.handle_error(tid);
+            }
+        }
+
+        let success = if self.finished() {
+            true
+        } else if self.keep_going {
+            // when keep_going is true, the task will continue to execute as much as possible.
+            // So, the success is evaluated by keep_going_errored.
+            !self.keep_going_errored.load(Ordering::Relaxed)
+        } else {
+            self.can_continue
+                .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        };
+        self.run_finalizers(&sequence).await;
+        if let Some(drift) = &self.type_drift {
+            drift.finish();
+        }
+        if !success {
+            self.run_on_failure().await;
+        }
+        self.notify_complete();
+        success
+    }
+
+    /// Whether a task ended the current run with [`Output::finish`].
+    fn finished(&self) -> bool {
+        self.finish.finished_by().is_some()
+    }
+
+    /// Execute the finalizers of the dag that did not run, see `run_finalizers`.
+    pub(crate) async fn finalize(&self) {
+        self.run_finalizers(&self.exe_sequence).await;
+    }
+
+    /// Execute the finalizers among the given tasks that did not run, one at a time in the
+    /// order of the dag, with an empty input. See `Task::run_on_cancel`.
+    ///
+    /// A finalizer is skipped if a task it depends on, directly or not, is still running, e.g.
+    /// an action abandoned when the run timed out, since it would clean up after a task that
+    /// did not end.
+    async fn run_finalizers(&self, sequence: &[usize]) {
+        for id in sequence {
+            let task = self.tasks[id].as_ref();
+            if !task.run_on_cancel()
+                || !task.enabled()
+                || !matches!(
+                    self.execute_states[id].state(),
+                    TaskState::Pending | TaskState::Skipped
+                )
+            {
+                continue;
+            }
+            if let Some(running) = self.running_upstream(*id) {
+                warn!(
+                    "Skipping finalizer [name: {}, id: {}]: task [name: {}] is still running",
+                    task.name(),
+                    id,
+                    running
+                );
+                continue;
+            }
+            debug!("Executing finalizer [name: {}, id: {}]", task.name(), id);
+            self.progress.add();
+            let _ = self.execute_task(task, None, true).await;
+        }
+    }
+
+    /// The name of a task that the given task depends on, directly or not, and that is still
+    /// running, if any.
+    fn running_upstream(&self, id: usize) -> Option<&str> {
+        let mut seen = HashSet::new();
+        let mut stack = self.tasks[&id].precursors().to_vec();
+        while let Some(pre) = stack.pop() {
+            if !seen.insert(pre) {
+                continue;
+            }
+            if self.execute_states[&pre].state() == TaskState::Running {
+                return Some(self.tasks[&pre].name());
+            }
+            stack.extend_from_slice(self.tasks[&pre].precursors());
+        }
+        None
+    }
+
+    /// Run the failure handler, if any, with the reports of the failed tasks.
+    async fn run_on_failure(&self) {
+        let handler = match &self.on_failure {
+            Some(handler) => handler.clone(),
+            None => return,
+        };
+        let failures = self
+            .run_report()
+            .tasks
+            .into_iter()
+            .filter(|task| task.state == TaskState::Failed)
+            .map(Content::new)
+            .collect();
+        let name = handler.name().to_string();
+        let action = handler.action();
+        let env = self.env.clone();
+        self.events.record(&name, RunEventKind::Started);
+        let out = tokio::task::spawn_blocking(move || {
+            panic::catch_unwind(AssertUnwindSafe(|| action.run(Input::new(failures), env)))
+        })
+        .await;
+        match out {
+            Ok(Ok(out)) if !out.is_err() => {
+                self.events
+                    .record_finished(&name, out.get_out().as_ref(), &self.env);
+            }
+            Ok(Ok(out)) => {
+                let err = self.env.redact(&out.get_err().unwrap_or_default());
+                error!("Failure handler [name: {}] failed\nerr: {}", name, err);
+                self.events.record_failed(&name, err);
+            }
+            _ => {
+                error!("Failure handler [name: {}] panicked", name);
+                self.events
+                    .record_failed(&name, "the task panicked".to_string());
+            }
+        }
+    }
+
+    /// Invoke the hooks registered by `on_complete` with the current run report.
+    fn notify_complete(&self) {
+        if self.on_complete.is_empty() {
+            return;
+        }
+        let report = self.run_report();
+        self.on_complete.iter().for_each(|hook| hook(&report));
+    }
+
+    /// Execute a given task asynchronously. With a turn, it waits for the turn once its
+    /// predecessors are done, see `set_schedule_seed`.
+    ///
+    /// A finalizer, see `run_finalizers`, runs at once with an empty input, even if the run was
+    /// stopped, and does not release its successors, which were already skipped.
+    fn execute_task(
+        &self,
+        task: &dyn Task,
+        turn: Option<Turn>,
+        finalizer: bool,
+    ) -> JoinHandle<bool> {
+        let env = self.env.clone();
+        let task_id = task.id();
+        let task_name = task.name().to_string();
+        let execute_state = self.execute_states[&task_id].clone();
+        let task_out_degree = self.rely_graph.get_node_out_degree(&task_id);
+        let seeded_input = match finalizer {
+            true => Some(Input::new(Vec::new())),
+            false => self.seeded_inputs.get(&task_id).cloned(),
+        };
+        // A task with a seeded input does not wait for its predecessors.
+        let wait_for_input: Vec<(usize, Arc<ExecState>)> = match seeded_input {
+            Some(_) => Vec::new(),
+            None => task
+                .precursors()
+                .iter()
+                .map(|id| (*id, self.execute_states[id].clone()))
+                .collect(),
+        };
+        let action = task.action();
+        let retry = task
+            .retry()
+            .or_else(|| self.default_retry.clone())
+            .map(|policy| match task.retry_filter() {
+                Some(filter) => policy.filtered_by(&filter),
+                None => policy,
+            });
+        let timeout = task.timeout().or(self.default_timeout);
+        let success_check = task.success_check();
+        let input_names: Option<HashMap<usize, String>> = self.inputs_as_env.then(|| {
+            task.precursors()
+                .iter()
+                .map(|id| (*id, self.tasks[id].name().to_string()))
+                .collect()
+        });
+        // The state of a failed task whose successors still run.
+        let tolerated = if task.allow_failure() {
+            Some(TaskState::FailedAllowed)
+        } else if self.test_mode {
+            Some(TaskState::Failed)
+        } else {
+            None
+        };
+        let group = task.labels().get(TaskGroup::LABEL).map(str::to_owned);
+        let cancelled_groups = self.cancelled_groups.clone();
+        // The setup of the group, if the task is a teardown.
+        let teardown_of = self.teardowns.get(&task_id).copied();
+        let budget = self.budget.clone();
+        let request = task.resource_request();
+        let affinity = task.thread_affinity();
+        let running = self.running.clone();
+        let ready = self.ready.clone();
+        let enabled = task.enabled();
+        let can_continue = self.can_continue.clone();
+        let finish = self.finish.clone();
+        let middlewares = self.middlewares.clone();
+        let log_dir = self.log_dir.clone();
+        let events = self.events.clone();
+        events.record(&task_name, RunEventKind::Queued);
+        let root_input = match task.precursors() {
+            [] => self.root_input.clone(),
+            _ => None,
+        };
+        let temp_dirs = self.temp_dirs;
+        let retain_inputs = self.retain_inputs;
+        let eager_output_drop = self.eager_output_drop;
+        let warn_on_empty_consumed = self.warn_on_empty_consumed;
+        let convert_to = task.input_type().filter(|_| self.convert_inputs);
+        let output_transform = self.output_transform.clone();
+        let type_drift = self.type_drift.clone();
+        let is_result = match &self.result_task {
+            Some(name) => name == task.name(),
+            None => self.exe_sequence.last() == Some(&task_id),
+        };
+        // The policy applied to the output, if it is not consumed.
+        let unconsumed = (task_out_degree == 0 && !is_result).then_some(self.unconsumed_output);
+        if eager_output_drop && self.result_task.as_deref() != Some(task.name()) {
+            execute_state.set_consumers(task_out_degree);
+        }
+        // The number of successors released once the task is done.
+        let task_out_degree = if finalizer { 0 } else { task_out_degree };
+        let keep_failed_temp_dirs = self.keep_failed_temp_dirs;
+        let compress_outputs = self.compress_outputs;
+        // The task is done for the statistics once the future is dropped, whatever its path.
+        let mut tracked = self
+            .scheduler_stats
+            .as_ref()
+            .map(|recorder| recorder.track());
+        let progress = self.progress.task();
+
+        tokio::spawn(async move {
+            let _progress = progress;
+            // Wait for the execution result of the predecessor task. The inputs are collected in
+            // the order the predecessors were declared in, whatever order they finish in.
+            let mut inputs = Vec::with_capacity(wait_for_input.len());
+            for (pre_id, wait_for) in wait_for_input {
+                wait_for.semaphore().acquire().await.unwrap().forget();
+                // When the task execution result of the predecessor can be obtained, judge whether
                 // the continuation flag is set to false, if it is set to false, cancel the specific
                 // execution logic of the task and return immediately.
-                if !can_continue.load(Ordering::Acquire) || !wait_for.success() {
+                let proceed = match teardown_of {
+                    // A teardown runs whatever happened to the members, as long as the setup
+                    // of its group succeeded.
+                    Some(setup) => setup != Some(pre_id) || wait_for.success(),
+                    None => can_continue.load(Ordering::Acquire) && wait_for.success(),
+                };
+                if !proceed {
+                    execute_state.mark_started();
+                    execute_state.set_state(TaskState::Skipped);
+                    events.record(&task_name, RunEventKind::Skipped);
+                    // Release the successors, which are skipped in turn if the dag was finished.
+                    execute_state.semaphore().add_permits(task_out_degree);
                     return true;
                 }
                 if let Some(content) = wait_for.get_output() {
-                    inputs.push(content);
+                    // The task declared the type of its input, so it reads it: a lazy input is
+                    // computed to be converted.
+                    let content = match convert_to {
+                        Some(to) => convert(&content, to).unwrap_or_else(|| {
+                            warn!(
+                                "Task [name: {}, id: {}] gets an input that cannot be converted into {}",
+                                task_name, task_id, to.name()
+                            );
+                            content
+                        }),
+                        None => content,
+                    };
+                    inputs.push((pre_id, content));
+                }
+                if eager_output_drop {
+                    wait_for.consume();
                 }
             }
+            if let Some(tracked) = &mut tracked {
+                tracked.enter(Phase::Ready);
+            }
+            let ready = ready.enter(task_id, &task_name);
+            if let Some(turn) = &turn {
+                turn.take().await;
+            }
+            if teardown_of.is_none()
+                && !finalizer
+                && group
+                    .as_ref()
+                    .is_some_and(|group| cancelled_groups.is_cancelled(group))
+            {
+                debug!(
+                    "Skipping cancelled task [name: {}, id: {}]",
+                    task_name, task_id
+                );
+                execute_state.mark_started();
+                execute_state.set_state(TaskState::Skipped);
+                events.record(&task_name, RunEventKind::Skipped);
+                execute_state.semaphore().add_permits(task_out_degree);
+                return true;
+            }
+            // A disabled task is regarded as a successful task without output, so that its
+            // successors can still be executed.
+            if !enabled {
+                debug!(
+                    "Skipping disabled task [name: {}, id: {}]",
+                    task_name, task_id
+                );
+                execute_state.set_output(Output::empty());
+                execute_state.exe_success();
+                execute_state.mark_started();
+                execute_state.set_state(TaskState::Skipped);
+                events.record(&task_name, RunEventKind::Skipped);
+                execute_state.semaphore().add_permits(task_out_degree);
+                return true;
+            }
             debug!("Executing task [name: {}, id: {}]", task_name, task_id);
-            // Concrete logical behavior for performing tasks.
-            panic::catch_unwind(AssertUnwindSafe(|| action.run(Input::new(inputs), env)))
-                .map_or_else(
-                    |_| {
+            execute_state.set_state(TaskState::Running);
+            events.record(&task_name, RunEventKind::Started);
+            // Let the next task start.
+            drop(turn);
+            // Concrete logical behavior for performing tasks. Actions are synchronous code, so
+            // they are run on the blocking thread pool to keep the scheduler responsive, which
+            // waits for the main thread of the engine if the task is bound to it.
+            let temp_dir = if temp_dirs {
+                match tempfile::Builder::new().prefix("dagrs-task-").tempdir() {
+                    Ok(dir) => Some(dir),
+                    Err(err) => {
+                        let err = format!("failed to create the temporary directory: {}", err);
                         error!("Execution failed [name: {}, id: {}]", task_name, task_id);
+                        events.record_failed(&task_name, err.clone());
+                        execute_state.set_error(err);
+                        execute_state.set_state(TaskState::Failed);
+                        finish.fail();
+                        return false;
+                    }
+                }
+            } else {
+                None
+            };
+            let mut task_vars = Vec::new();
+            if let Some(dir) = &temp_dir {
+                let path = dir.path().to_string_lossy().into_owned();
+                task_vars.push((EnvVar::TASK_TMPDIR.to_string(), path));
+            }
+            if let Some(names) = &input_names {
+                task_vars.extend(input_vars(&task_name, names, &inputs));
+            }
+            let action_env = if task_vars.is_empty() {
+                env.clone()
+            } else {
+                let mut task_env = (*env).clone();
+                for (name, value) in task_vars {
+                    task_env.set(&name, value);
+                }
+                Arc::new(task_env)
+            };
+            let input = match (seeded_input, root_input) {
+                (Some(input), _) => input,
+                (None, Some(content)) => Input::new(vec![content]),
+                (None, None) => Input::from_predecessors(inputs),
+            };
+            if retain_inputs {
+                execute_state.set_input(input.clone());
+            }
+            if let Some(budget) = &budget {
+                budget.acquire(request).await;
+            }
+            if let Some(tracked) = &mut tracked {
+                tracked.enter(Phase::Running);
+            }
+            let started = execute_state.mark_started();
+            drop(ready);
+            running.insert(task_id, &task_name);
+            let mut attempt = 0;
+            // The last attempt, if it timed out and may still be running.
+            let mut abandoned = None;
+            let out = loop {
+                let action = action.clone();
+                let action_env = action_env.clone();
+                let action_name = task_name.clone();
+                let middlewares = middlewares.clone();
+                let input = input.clone();
+                let usage_state = execute_state.clone();
+                let (running_tx, running_rx) = tokio::sync::oneshot::channel();
+                let mut run = tokio::task::spawn_blocking(move || {
+                    run_with_affinity(affinity, move || {
+                        let _ = running_tx.send(());
+                        // A panic in `before` fails the attempt like a panic of the action, and
+                        // only the middlewares whose `before` ran see its end.
+                        let mut entered = 0;
+                        let mut refused = None;
+                        for middleware in middlewares.iter() {
+                            let before = || middleware.before(&action_name, &input);
+                            match panic::catch_unwind(AssertUnwindSafe(before)) {
+                                Ok(()) => entered += 1,
+                                Err(payload) => {
+                                    refused = Some(payload);
+                                    break;
+                                }
+                            }
+                        }
+                        let out = match refused {
+                            Some(payload) => Err(payload),
+                            None => {
+                                // The processes spawned by the action record their usage on this
+                                // thread.
+                                take_usage();
+                                let out = panic::catch_unwind(AssertUnwindSafe(|| {
+                                    action.run(input, action_env)
+                                }));
+                                if let Some(usage) = take_usage() {
+                                    usage_state.add_resources(usage);
+                                }
+                                out
+                            }
+                        };
+                        let panicked = Output::error("the task panicked".to_string());
+                        let reported = out.as_ref().unwrap_or(&panicked);
+                        for middleware in middlewares[..entered].iter().rev() {
+                            middleware.after(&action_name, reported);
+                        }
+                        out
+                    })
+                });
+                let out = match timeout {
+                    Some(timeout) => {
+                        // The attempt may wait for a blocking thread, which does not count.
+                        let _ = running_rx.await;
+                        match tokio::time::timeout(timeout, &mut run).await {
+                            Ok(out) => out,
+                            Err(_) => {
+                                abandoned = Some(run);
+                                Ok(Ok(Output::error(format!(
+                                    "the task timed out after {:?}",
+                                    timeout
+                                ))))
+                            }
+                        }
+                    }
+                    None => run.await,
+                };
+                let out = match (out, &success_check) {
+                    (Ok(Ok(out)), Some(check)) if !out.is_err() && !check(&out) => {
+                        Ok(Ok(Output::error_with_exit_code(
+                            out.exit_code(),
+                            Some(Content::new(
+                                "the output of the task failed its success check".to_string(),
+                            )),
+                        )))
+                    }
+                    (out, _) => out,
+                };
+                let panicked = Output::error("the task panicked".to_string());
+                let failed = match &out {
+                    Ok(Ok(out)) if out.is_err() => out,
+                    Ok(Err(_)) => &panicked,
+                    _ => break out,
+                };
+                match &retry {
+                    Some(policy) if policy.should_retry(attempt, failed) => {
+                        // An attempt cannot be interrupted, so the next one waits for the
+                        // previous one to return, so that the attempts of a task never overlap.
+                        // It is given the timeout once more, after which the task fails.
+                        if let (Some(previous), Some(timeout)) = (abandoned.take(), timeout) {
+                            if tokio::time::timeout(timeout, previous).await.is_err() {
+                                warn!(
+                                    "Not retrying task [name: {}, id: {}], since its last attempt is still running",
+                                    task_name, task_id
+                                );
+                                break out;
+                            }
+                        }
+                        let delay = policy.delay(attempt);
+                        attempt += 1;
+                        warn!(
+                            "Retrying task [name: {}, id: {}] in {:?} ({}/{})",
+                            task_name,
+                            task_id,
+                            delay,
+                            attempt,
+                            policy.attempts()
+                        );
+                        tokio::time::sleep(delay).await;
+                        if !finalizer && !can_continue.load(Ordering::Acquire) {
+                            break out;
+                        }
+                    }
+                    _ => break out,
+                }
+            };
+            running.remove(task_id);
+            drop(tracked);
+            if let Some(budget) = &budget {
+                budget.release(request);
+            }
+            execute_state.set_duration(started.elapsed());
+            if let Some(dir) = temp_dir {
+                let failed = !matches!(&out, Ok(Ok(out)) if !out.is_err());
+                if failed && keep_failed_temp_dirs {
+                    let path = dir.keep();
+                    error!(
+                        "Kept the temporary directory of task [name: {}, id: {}]: {:?}",
+                        task_name, task_id, path
+                    );
+                }
+            }
+            match out {
+                Ok(Ok(out)) => {
+                    if let Some(dir) = log_dir {
+                        write_streams(&dir, &task_name, &out, &env);
+                    }
+                    let out = match &output_transform {
+                        Some(transform) => transform(&task_name, out),
+                        None => out,
+                    };
+                    // Store execution results
+                    if let (true, Some(state)) = (out.is_err(), tolerated) {
+                        let err = env.redact(&out.get_err().unwrap_or("".to_string()));
+                        warn!(
+                            "Execution failed, but the task is allowed to fail [name: {}, id: {}]\nerr: {}",
+                            task_name, task_id, err
+                        );
+                        events.record_failed(&task_name, err.clone());
+                        execute_state.set_error(err);
+                        allowed_failure(&execute_state, task_out_degree, state);
+                        if state == TaskState::Failed {
+                            finish.fail();
+                        }
+                        true
+                    } else if out.is_err() {
+                        let err = env.redact(&out.get_err().unwrap_or("".to_string()));
+                        error!(
+                            "Execution failed [name: {}, id: {}]\nerr: {}",
+                            task_name, task_id, err
+                        );
+                        events.record_failed(&task_name, err.clone());
+                        execute_state.set_error(err);
+                        execute_state.set_state(TaskState::Failed);
+                        finish.fail();
                         false
-                    },
-                    |out| {
-                        // Store execution results
-                        if out.is_err() {
-                            error!(
-                                "Execution failed [name: {}, id: {}]\nerr: {}",
-                                task_name,
-                                task_id,
-                                out.get_err().unwrap_or("".to_string())
+                    } else {
+                        if out.is_finish() && finish.finish(task_id, &can_continue) {
+                            debug!(
+                                "The dag was finished by [name: {}, id: {}]",
+                                task_name, task_id
                             );
-                            false
-                        } else {
-                            execute_state.set_output(out);
-                            execute_state.exe_success();
-                            execute_state.semaphore().add_permits(task_out_degree);
-                            debug!("Execution succeed [name: {}, id: {}]", task_name, task_id);
-                            true
                         }
-                    },
-                )
+                        if warn_on_empty_consumed {
+                            if task_out_degree > 0 && out.get_out().is_none() {
+                                warn!(
+                                    "Task [name: {}, id: {}] produced an empty output, but {} tasks depend on it",
+                                    task_name, task_id, task_out_degree
+                                );
+                            }
+                            if input.unread() {
+                                warn!(
+                                    "Task [name: {}, id: {}] did not read the outputs of its predecessors",
+                                    task_name, task_id
+                                );
+                            }
+                        }
+                        events.record_finished(&task_name, out.get_out().as_ref(), &env);
+                        if let (Some(drift), Some(content)) = (&type_drift, out.get_out()) {
+                            drift.record(&task_name, content.type_name());
+                        }
+                        let out = match unconsumed {
+                            Some(policy) => policy.apply(&task_name, out, &env),
+                            None => out,
+                        };
+                        let out = match compress_outputs {
+                            true => out.map_out(compress),
+                            false => out,
+                        };
+                        execute_state.set_output(out);
+                        execute_state.exe_success();
+                        execute_state.set_state(TaskState::Succeeded);
+                        execute_state.semaphore().add_permits(task_out_degree);
+                        debug!("Execution succeed [name: {}, id: {}]", task_name, task_id);
+                        true
+                    }
+                }
+                _ => {
+                    error!("Execution failed [name: {}, id: {}]", task_name, task_id);
+                    events.record_failed(&task_name, "the task panicked".to_string());
+                    execute_state.set_error("the task panicked".to_string());
+                    if let Some(state) = tolerated {
+                        allowed_failure(&execute_state, task_out_degree, state);
+                        if state == TaskState::Failed {
+                            finish.fail();
+                        }
+                        return true;
+                    }
+                    execute_state.set_state(TaskState::Failed);
+                    finish.fail();
+                    false
+                }
+            }
         })
     }
 
@@ -436,12 +2465,25 @@ impl Dag {
         }
     }
 
-    /// Get the final execution result.
+    /// Get the final execution result: the output of the last task, or of the task given to
+    /// `set_result_task`, or the value passed to [`Output::finish`] if a task ended the run.
+    ///
+    /// When the dag has several terminal tasks, see `terminal_tasks`, the last task is the one
+    /// that comes last in the execution sequence, in which the heaviest of the ready tasks
+    /// come first, then the ones created first. Use `set_result_task` to choose the task, or
+    /// `get_result_of` to read the output of a given terminal task instead.
     pub fn get_result<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
         if self.exe_sequence.is_empty() {
             None
         } else {
-            let last_id = self.exe_sequence.last().unwrap();
+            let result_task = self
+                .result_task
+                .as_deref()
+                .and_then(|name| self.find_task_id(name));
+            let finished_by = self.finish.finished_by().or(result_task);
+            let last_id = finished_by
+                .as_ref()
+                .unwrap_or_else(|| self.exe_sequence.last().unwrap());
             if let Some(content) = self.execute_states[last_id].get_output() {
                 content.into_inner()
             } else {
@@ -450,6 +2492,96 @@ impl Dag {
         }
     }
 
+    /// Run the dag with `start`, and get the output of its last task like `get_result`.
+    ///
+    /// When the dag has several terminal tasks, the output is the one of the terminal task that
+    /// comes last in the execution sequence, or the value passed to [`Output::finish`] if a task
+    /// ended the run. Use `get_results` after the run to read the other ones. Returns `Ok(None)`
+    /// if the dag failed, or if the output is not a `T`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::new(20usize));
+    /// let mut b = DefaultTask::with_closure("b", |input, _env| {
+    ///     let a = input.get_iter().next().unwrap().get::<usize>().unwrap();
+    ///     Output::new(a + 22)
+    /// });
+    /// b.set_predecessors(&[&a]);
+    /// let mut dag = Dag::with_tasks(vec![a, b]);
+    /// assert_eq!(dag.start_and_get::<usize>().unwrap(), Some(42));
+    /// ```
+    pub fn start_and_get<T: Clone + Send + Sync + 'static>(
+        &mut self,
+    ) -> Result<Option<T>, DagError> {
+        if !self.start()? {
+            return Ok(None);
+        }
+        Ok(self.get_result::<T>().map(|out| T::clone(&out)))
+    }
+
+    /// Get the output of the task with the given name, e.g. one of several terminal tasks, see
+    /// `terminal_tasks`. Returns None if there is no such task, if it produced no output, or if
+    /// the output is not a `T`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let build = DefaultTask::with_closure("build", |_input, _env| Output::empty());
+    /// let mut docs = DefaultTask::with_closure("docs", |_input, _env| Output::new("book".to_string()));
+    /// docs.set_predecessors(&[&build]);
+    /// let mut binary = DefaultTask::with_closure("binary", |_input, _env| Output::new(42usize));
+    /// binary.set_predecessors(&[&build]);
+    /// let mut dag = Dag::with_tasks(vec![build, docs, binary]);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(dag.terminal_tasks(), vec!["docs", "binary"]);
+    /// assert_eq!(*dag.get_result_of::<String>("docs").unwrap(), "book");
+    /// assert_eq!(*dag.get_result_of::<usize>("binary").unwrap(), 42);
+    /// ```
+    pub fn get_result_of<T: Send + Sync + 'static>(&self, name: &str) -> Option<Arc<T>> {
+        let id = self.find_task_id(name)?;
+        self.execute_states.get(&id)?.get_output()?.into_inner()
+    }
+
+    /// The outputs and errors of the tasks of the last run, by task name.
+    #[cfg(feature = "test-utils")]
+    pub(crate) fn task_outputs(&self) -> Vec<(String, Option<Content>, Option<String>)> {
+        self.tasks
+            .iter()
+            .map(|(id, task)| {
+                let state = self.execute_states.get(id);
+                (
+                    task.name().to_string(),
+                    state.and_then(|state| state.get_output()),
+                    state.and_then(|state| state.get_error()),
+                )
+            })
+            .collect()
+    }
+
+    /// The names of the terminal tasks of the dag, which no other task depends on, in the order
+    /// the tasks were created in.
+    pub fn terminal_tasks(&self) -> Vec<String> {
+        let precursors: HashSet<usize> = self
+            .tasks
+            .values()
+            .flat_map(|task| task.precursors().iter().copied())
+            .collect();
+        let mut terminals: Vec<(usize, &str)> = self
+            .tasks
+            .iter()
+            .filter(|(id, _)| !precursors.contains(id))
+            .map(|(&id, task)| (id, task.name()))
+            .collect();
+        terminals.sort_unstable();
+        terminals
+            .into_iter()
+            .map(|(_, name)| name.to_string())
+            .collect()
+    }
+
     /// Get the output of all tasks.
     pub fn get_results<T: Send + Sync + 'static>(&self) -> HashMap<usize, Option<Arc<T>>> {
         let hm = self
@@ -466,8 +2598,705 @@ impl Dag {
         hm
     }
 
+    /// Find the id of the task with the given name.
+    /// If several tasks share the name, any one of them may be returned.
+    fn find_task_id(&self, name: &str) -> Option<usize> {
+        self.tasks
+            .iter()
+            .find(|(_, task)| task.name() == name)
+            .map(|(&id, _)| id)
+    }
+
+    /// Get the resources used by the processes spawned by the task with the given name during
+    /// the last run, such as its commands or scripts. Returns None if there is no such task, if
+    /// it spawned no process, or if the usage cannot be measured on this platform, which is
+    /// the case outside of Unix.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{CommandAction, Dag, DefaultTask};
+    ///
+    /// let task = DefaultTask::with_action("list", CommandAction::new("ls"));
+    /// let mut dag = Dag::with_tasks(vec![task]);
+    /// assert!(dag.start().unwrap());
+    /// if cfg!(unix) {
+    ///     let usage = dag.get_task_resources("list").unwrap();
+    ///     assert!(usage.max_rss > 0);
+    /// }
+    /// ```
+    pub fn get_task_resources(&self, name: &str) -> Option<TaskResourceUsage> {
+        let id = self.find_task_id(name)?;
+        self.execute_states.get(&id)?.resources()
+    }
+
+    /// Get the execution state of the task with the given name.
+    /// Returns None if there is no such task or if the dag has not been initialized yet.
+    pub fn task_state(&self, name: &str) -> Option<TaskState> {
+        let id = self.find_task_id(name)?;
+        self.execute_states.get(&id).map(|state| state.state())
+    }
+
+    /// The input that was given to the task with the given name in the last run, if inputs are
+    /// retained with `set_retain_inputs` and the task started.
+    pub fn get_task_input(&self, name: &str) -> Option<Input> {
+        let id = self.find_task_id(name)?;
+        self.execute_states.get(&id)?.input()
+    }
+
+    /// The names of the tasks whose action is executing at the moment of the call, sorted.
+    pub fn running_tasks(&self) -> Vec<String> {
+        self.running.snapshot()
+    }
+
+    /// The tasks whose output type changed since the previous run in the last run, sorted by
+    /// task name, empty unless `set_type_drift_detection` is enabled.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let store = std::env::temp_dir().join("dagrs-doc-type-drift.json");
+    /// let _ = std::fs::remove_file(&store);
+    /// let run = |output: fn() -> Output| {
+    ///     let task = DefaultTask::with_closure("count", move |_input, _env| output());
+    ///     let mut dag = Dag::with_tasks(vec![task]);
+    ///     dag.set_type_drift_detection(&store);
+    ///     assert!(dag.start().unwrap());
+    ///     dag.type_changes()
+    /// };
+    /// assert!(run(|| Output::new(1usize)).is_empty());
+    /// let changes = run(|| Output::new("1".to_string()));
+    /// assert_eq!(changes[0].task, "count");
+    /// assert_eq!(changes[0].before, "usize");
+    /// # std::fs::remove_file(&store).unwrap();
+    /// ```
+    pub fn type_changes(&self) -> Vec<TypeChange> {
+        self.type_drift
+            .as_ref()
+            .map(|drift| drift.changes())
+            .unwrap_or_default()
+    }
+
+    /// The numbers of tasks in each phase of the scheduling over the course of the last run,
+    /// empty unless `set_record_scheduler_stats` is enabled.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::empty());
+    /// let mut b = DefaultTask::with_closure("b", |_input, _env| Output::empty());
+    /// b.set_predecessors(&[&a]);
+    /// let mut dag = Dag::with_tasks(vec![a, b]);
+    /// dag.set_record_scheduler_stats(true);
+    /// assert!(dag.start().unwrap());
+    /// let stats = dag.scheduler_stats();
+    /// assert_eq!(stats.samples()[0].pending, 2);
+    /// assert_eq!(stats.peak_running(), 1);
+    /// ```
+    pub fn scheduler_stats(&self) -> SchedulerStats {
+        self.scheduler_stats
+            .as_ref()
+            .map(|recorder| recorder.stats())
+            .unwrap_or_default()
+    }
+
+    /// A handle on the tasks of the dag whose action is executing, which can be sent to another
+    /// thread to poll them while the dag runs, unlike the dag itself.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    /// use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+    /// use std::{thread, time::Duration};
+    /// let release = Arc::new(AtomicBool::new(false));
+    /// let released = release.clone();
+    /// let task = DefaultTask::with_closure("Task 1", move |_input, _env| {
+    ///     while !released.load(Ordering::SeqCst) {
+    ///         thread::sleep(Duration::from_millis(1));
+    ///     }
+    ///     Output::empty()
+    /// });
+    /// let mut dag = Dag::with_tasks(vec![task]);
+    /// let running = dag.running_tasks_handle();
+    /// let poller = thread::spawn(move || loop {
+    ///     let names = running.snapshot();
+    ///     if !names.is_empty() {
+    ///         release.store(true, Ordering::SeqCst);
+    ///         return names;
+    ///     }
+    ///     thread::sleep(Duration::from_millis(1));
+    /// });
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(poller.join().unwrap(), vec!["Task 1"]);
+    /// assert!(dag.running_tasks().is_empty());
+    /// ```
+    pub fn running_tasks_handle(&self) -> RunningTasks {
+        self.running.clone()
+    }
+
+    /// The names of the tasks whose predecessors are done but which have not started yet at the
+    /// moment of the call, sorted: the frontier of the scheduler. They may wait for resources
+    /// of the budget of the dag, for their turn in a seeded schedule, or only for an instant.
+    /// See `ready_tasks_handle` to poll them while the dag runs.
+    pub fn ready_tasks(&self) -> Vec<String> {
+        self.ready.snapshot()
+    }
+
+    /// A handle on the tasks of the dag that are ready to start, which can be sent to another
+    /// thread to poll them while the dag runs, unlike the dag itself.
+    pub fn ready_tasks_handle(&self) -> ReadyTasks {
+        self.ready.clone()
+    }
+
+    /// The fraction of the tasks of the current or last run that are done, from 0 to 1, see
+    /// [`RunProgress`]. Each task counts the same, whatever its weight.
+    pub fn progress(&self) -> f32 {
+        self.progress.fraction()
+    }
+
+    /// A handle on the progress of the runs of the dag, which can be sent to another thread to
+    /// poll it while the dag runs, unlike the dag itself.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    /// use std::{thread, time::Duration};
+    ///
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::empty());
+    /// let mut b = DefaultTask::with_closure("b", |_input, _env| {
+    ///     thread::sleep(Duration::from_millis(50));
+    ///     Output::empty()
+    /// });
+    /// b.set_predecessors(&[&a]);
+    /// let mut dag = Dag::with_tasks(vec![a, b]);
+    /// let progress = dag.progress_handle();
+    /// let bar = thread::spawn(move || {
+    ///     while progress.fraction() < 1.0 {
+    ///         println!("{}/{} tasks done", progress.done(), progress.total());
+    ///         thread::sleep(Duration::from_millis(10));
+    ///     }
+    /// });
+    /// assert!(dag.start().unwrap());
+    /// bar.join().unwrap();
+    /// assert_eq!(dag.progress(), 1.0);
+    /// ```
+    pub fn progress_handle(&self) -> RunProgress {
+        self.progress.clone()
+    }
+
+    /// Draw the dag as a Mermaid flowchart, to be embedded in Markdown documentation.
+    ///
+    /// Each task is a node labelled with its name, and each dependency an edge from the
+    /// predecessor to the task. Once the dag has run, the nodes are styled by the state of
+    /// their task, e.g. failed tasks are drawn in red. Characters that Mermaid interprets in
+    /// labels, such as quotes, are escaped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, Task};
+    /// let a = DefaultTask::with_closure("Fetch \"data\"", |_input, _env| Output::empty());
+    /// let mut b = DefaultTask::with_closure("Report", |_input, _env| Output::empty());
+    /// b.set_predecessors(&[&a]);
+    /// let (a_id, b_id) = (a.id(), b.id());
+    /// let dag = Dag::with_tasks(vec![a, b]);
+    /// let chart = dag.to_mermaid();
+    /// assert!(chart.starts_with("graph TD\n"));
+    /// assert!(chart.contains(&format!("t{}[\"Fetch #quot;data#quot;\"]", a_id)));
+    /// assert!(chart.contains(&format!("t{} --> t{}", a_id, b_id)));
+    /// ```
+    pub fn to_mermaid(&self) -> String {
+        let mut ids: Vec<usize> = self.tasks.keys().copied().collect();
+        ids.sort_unstable();
+        let mut chart = String::from("graph TD\n");
+        for id in ids.iter() {
+            chart.push_str(&format!(
+                "    t{}[\"{}\"]\n",
+                id,
+                escape_mermaid(self.tasks[id].name())
+            ));
+        }
+        for id in ids.iter() {
+            for pre in self.tasks[id].precursors() {
+                chart.push_str(&format!("    t{} --> t{}\n", pre, id));
+            }
+        }
+
+        let styles = [
+            (TaskState::Pending, "pending", "fill:#eeeeee,stroke:#999999"),
+            (TaskState::Running, "running", "fill:#cce5ff,stroke:#004085"),
+            (
+                TaskState::Succeeded,
+                "succeeded",
+                "fill:#d4edda,stroke:#155724",
+            ),
+            (TaskState::Failed, "failed", "fill:#f8d7da,stroke:#721c24"),
+            (
+                TaskState::FailedAllowed,
+                "failedAllowed",
+                "fill:#fff3cd,stroke:#856404",
+            ),
+            (
+                TaskState::Skipped,
+                "skipped",
+                "fill:#ffffff,stroke:#999999,stroke-dasharray:4",
+            ),
+        ];
+        for (state, class, style) in styles {
+            let nodes: Vec<String> = ids
+                .iter()
+                .filter(|id| self.execute_states.get(id).map(|s| s.state()) == Some(state))
+                .map(|id| format!("t{}", id))
+                .collect();
+            if !nodes.is_empty() {
+                chart.push_str(&format!("    classDef {} {}\n", class, style));
+                chart.push_str(&format!("    class {} {}\n", nodes.join(","), class));
+            }
+        }
+        chart
+    }
+
+    /// The structure of the dag by task names, independent of the ids of the tasks and of
+    /// the order they were added in. Dependencies on tasks that are not in the dag are left
+    /// out. No task is executed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, Task, Topology};
+    /// let a = DefaultTask::with_closure("fetch", |_input, _env| Output::empty());
+    /// let mut b = DefaultTask::with_closure("report", |_input, _env| Output::empty());
+    /// b.set_predecessors(&[&a]);
+    /// let dag = Dag::with_tasks(vec![b, a]);
+    /// let expected = Topology::new(["report", "fetch"], [("fetch", "report")]);
+    /// let diff = expected.diff(&dag.topology());
+    /// assert!(diff.is_empty(), "the topology changed:\n{}", diff);
+    /// assert_eq!(dag.topology_fingerprint(), expected.fingerprint());
+    /// ```
+    pub fn topology(&self) -> Topology {
+        let tasks = self.tasks.values().map(|task| task.name());
+        let edges = self.tasks.values().flat_map(|task| {
+            task.precursors()
+                .iter()
+                .filter_map(|pre| self.tasks.get(pre))
+                .map(move |pre| (pre.name(), task.name()))
+        });
+        Topology::new(tasks, edges)
+    }
+
+    /// A stable hash of the structure of the dag, see [`Dag::topology`] and
+    /// [`Topology::fingerprint`]. It is the same for two dags with the same tasks and
+    /// dependencies by name, whatever the ids of their tasks and the order they were added in.
+    pub fn topology_fingerprint(&self) -> String {
+        self.topology().fingerprint()
+    }
+
+    /// Group the names of the tasks by dependency depth.
+    ///
+    /// The first level contains all tasks without predecessors, and each following level contains
+    /// the tasks whose predecessors have all appeared in earlier levels. Tasks of disconnected
+    /// components are layered independently, so their roots all share the first level. Names
+    /// within a level are sorted. No task is executed.
+    pub fn levels(&self) -> Result<Vec<Vec<String>>, DagError> {
+        let graph = self.build_graph()?;
+        let levels = graph.levels().ok_or(DagError::LoopGraph)?;
+        Ok(levels
+            .into_iter()
+            .map(|level| {
+                let mut names: Vec<String> = level
+                    .into_iter()
+                    .map(|index| {
+                        let id = graph.find_id_by_index(index).unwrap();
+                        self.tasks[&id].name().to_string()
+                    })
+                    .collect();
+                names.sort();
+                names
+            })
+            .collect())
+    }
+
+    /// Find the dependencies that are implied by other dependencies: if `a` runs before `b`
+    /// and `b` before `c`, then declaring that `a` runs before `c` is redundant. Returns the
+    /// redundant dependencies as `(predecessor, successor)` pairs of task names, sorted.
+    ///
+    /// Redundant dependencies are harmless, but they often hide the actual structure of a
+    /// pipeline or point to a mistake, so they can also be logged as warnings when the dag
+    /// starts, see [`Dag::set_warn_redundant_edges`].
+    pub fn redundant_edges(&self) -> Result<Vec<(String, String)>, DagError> {
+        self.named_redundant_edges(&self.build_graph()?)
+    }
+
+    /// The redundant dependencies of `graph`, by task name.
+    fn named_redundant_edges(&self, graph: &Graph) -> Result<Vec<(String, String)>, DagError> {
+        let edges = graph.redundant_edges().ok_or(DagError::LoopGraph)?;
+        let name = |index| {
+            let id = graph.find_id_by_index(index).unwrap();
+            self.tasks[&id].name().to_string()
+        };
+        let mut edges: Vec<(String, String)> =
+            edges.into_iter().map(|(v, w)| (name(v), name(w))).collect();
+        edges.sort();
+        Ok(edges)
+    }
+
+    /// The maximum number of tasks that could run simultaneously given the dependency structure,
+    /// that is the size of the widest level returned by [`Dag::levels`].
+    pub fn max_parallel_width(&self) -> Result<usize, DagError> {
+        Ok(self
+            .levels()?
+            .iter()
+            .map(|level| level.len())
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// The weight of each task of `graph`, by index.
+    fn weights(&self, graph: &Graph) -> Vec<f64> {
+        (0..self.tasks.len())
+            .map(|index| self.tasks[&graph.find_id_by_index(index).unwrap()].weight())
+            .collect()
+    }
+
+    /// The sum of the weights of all tasks.
+    pub fn total_weight(&self) -> f64 {
+        self.tasks.values().map(|task| task.weight()).sum()
+    }
+
+    /// The largest sum of the weights of the tasks along a chain of dependencies, which bounds
+    /// the cost of a run however many tasks are executed in parallel.
+    pub fn critical_path_weight(&self) -> Result<f64, DagError> {
+        let graph = self.build_graph()?;
+        graph
+            .critical_path_weight(&self.weights(&graph))
+            .ok_or(DagError::LoopGraph)
+    }
+
+    /// The sum of the weights of the tasks of each level returned by [`Dag::levels`].
+    pub fn level_weights(&self) -> Result<Vec<f64>, DagError> {
+        let graph = self.build_graph()?;
+        let levels = graph.levels().ok_or(DagError::LoopGraph)?;
+        let weights = self.weights(&graph);
+        Ok(levels
+            .into_iter()
+            .map(|level| level.into_iter().map(|index| weights[index]).sum())
+            .collect())
+    }
+
+    /// Register a middleware wrapping the action of every task. Middlewares compose in
+    /// registration order: the `before` methods are called in that order and the `after`
+    /// methods in the reverse order, so the first middleware registered is the outermost.
+    pub fn add_middleware(&mut self, middleware: impl TaskMiddleware + 'static) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    /// Set a function applied to the output of every task, along with the task's name, before
+    /// the output is stored and passed to the successors, e.g. to strip the trailing newlines
+    /// or the color codes of the outputs of commands. It is applied to errors as well, and
+    /// may turn an output into an error or the other way round. The logs written to the
+    /// directory given by `set_log_dir` hold the outputs before the transformation.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    /// use dagrs::task::Content;
+    ///
+    /// let task = DefaultTask::with_closure("echo", |_input, _env| Output::new("42\n".to_string()));
+    /// let mut dag = Dag::with_tasks(vec![task]);
+    /// dag.set_output_transform(|_name, out| match out {
+    ///     Output::Out(Some(content)) => match content.get::<String>() {
+    ///         Some(text) => Output::new(text.trim_end().to_string()),
+    ///         None => Output::Out(Some(content)),
+    ///     },
+    ///     out => out,
+    /// });
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*dag.get_result::<String>().unwrap(), "42");
+    /// ```
+    pub fn set_output_transform(
+        &mut self,
+        transform: impl Fn(&str, Output) -> Output + Send + Sync + 'static,
+    ) {
+        self.output_transform = Some(Arc::new(transform));
+    }
+
+    /// Register a hook that is invoked once with the run report when the dag finishes,
+    /// whether it succeeded or failed, including when it fails to initialize.
+    /// Hooks are invoked in registration order.
+    pub fn on_complete(&mut self, hook: impl Fn(&RunReport) + Send + Sync + 'static) {
+        self.on_complete.push(Arc::new(hook));
+    }
+
+    /// The timeline of the last run as one bar per task that ran or was skipped, sorted by
+    /// start, for drawing a Gantt chart that shows the parallelism of the run and the gaps
+    /// when the dag was idle. The tasks that were not reached by the run are left out.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, Task};
+    /// let a = DefaultTask::with_closure("a", |_input, _env| Output::empty());
+    /// let b = DefaultTask::with_closure("b", |_input, _env| Output::empty());
+    /// let mut c = DefaultTask::with_closure("c", |_input, _env| Output::empty());
+    /// c.set_predecessors(&[&a, &b]);
+    /// let mut dag = Dag::with_tasks(vec![a, b, c]);
+    /// assert!(dag.start().unwrap());
+    /// let bars = dag.gantt_data();
+    /// assert_eq!(bars.len(), 3);
+    /// assert_eq!(bars[2].task, "c");
+    /// assert!(bars[2].start >= bars[0].start + bars[0].duration);
+    /// ```
+    pub fn gantt_data(&self) -> Vec<GanttBar> {
+        let origin = match *self.run_started.lock().unwrap() {
+            Some(origin) => origin,
+            None => return Vec::new(),
+        };
+        let mut bars: Vec<GanttBar> = self
+            .exe_sequence
+            .iter()
+            .filter_map(|id| {
+                let execute_state = &self.execute_states[id];
+                let started = execute_state
+                    .started()
+                    .filter(|started| *started >= origin)?;
+                let state = execute_state.state();
+                let duration = match state {
+                    TaskState::Skipped => Duration::ZERO,
+                    _ => execute_state.duration().unwrap_or_default(),
+                };
+                Some(GanttBar {
+                    task: self.tasks[id].name().to_string(),
+                    start: started - origin,
+                    duration,
+                    lane: 0,
+                    state,
+                })
+            })
+            .collect();
+        assign_lanes(&mut bars);
+        bars
+    }
+
+    /// The events recorded while the dag ran, in chronological order. The events of later
+    /// runs, such as `rerun_from`, are appended.
+    pub fn event_log(&self) -> Vec<RunEvent> {
+        self.events.events()
+    }
+
+    /// Summarize the current execution state of the dag.
+    ///
+    /// The dag is regarded as successful when no task failed and no task is still pending.
+    pub fn run_report(&self) -> RunReport {
+        let mut reports: Vec<(Option<String>, TaskReport)> = self
+            .tasks
+            .iter()
+            .map(|(id, task)| {
+                let (state, error, duration, resources) = match self.execute_states.get(id) {
+                    Some(exec_state) => (
+                        exec_state.state(),
+                        exec_state.get_error(),
+                        exec_state.duration(),
+                        exec_state.resources(),
+                    ),
+                    None => (TaskState::Pending, None, None, None),
+                };
+                let group = task.labels().get(TaskGroup::LABEL).map(str::to_owned);
+                let report = TaskReport {
+                    name: task.name().to_string(),
+                    state,
+                    error,
+                    duration,
+                    resources,
+                };
+                (group, report)
+            })
+            .collect();
+        reports.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+        let mut members: BTreeMap<&str, Vec<&TaskReport>> = BTreeMap::new();
+        for (group, report) in reports.iter() {
+            if let Some(group) = group {
+                members.entry(group).or_default().push(report);
+            }
+        }
+        let groups = members
+            .into_iter()
+            .map(|(name, members)| {
+                GroupReport::new(name, self.cancelled_groups.is_cancelled(name), &members)
+            })
+            .collect();
+        let tasks: Vec<TaskReport> = reports.into_iter().map(|(_, report)| report).collect();
+        let success = !tasks.is_empty()
+            && tasks
+                .iter()
+                .all(|task| !matches!(task.state, TaskState::Failed | TaskState::Pending));
+        RunReport {
+            success,
+            tasks,
+            groups,
+        }
+    }
+
+    /// The `n` tasks that ran the longest in the last run, from the slowest, see
+    /// [`RunReport::slowest_tasks`].
+    pub fn slowest_tasks(&self, n: usize) -> Vec<(String, Duration)> {
+        self.run_report().slowest_tasks(n)
+    }
+
     /// Before the dag starts executing, set the dag's global environment variable.
     pub fn set_env(&mut self, env: EnvVar) {
         self.env = Arc::new(env);
     }
+
+    /// Load the variables of a `.env` file, see [`EnvVar::load_env_file`] for its syntax.
+    ///
+    /// The variables are layered under the ones given with `set_env`, whether it is called
+    /// before or after: they are only used when the environment of the dag does not set them.
+    /// When several files are loaded, the first file setting a variable wins.
+    pub fn load_env_file(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.file_env.load_env_file(path)
+    }
+}
+
+/// Convert an attribute of an action, see [`Complex::config`](crate::Complex::config), into a
+/// yaml value of the same type.
+#[cfg(feature = "yaml")]
+fn json_to_yaml(value: serde_json::Value) -> yaml_rust::Yaml {
+    use serde_json::Value;
+    use yaml_rust::Yaml;
+
+    match value {
+        Value::Null => Yaml::Null,
+        Value::Bool(value) => Yaml::Boolean(value),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => Yaml::Integer(value),
+            None => Yaml::Real(number.to_string()),
+        },
+        Value::String(value) => Yaml::String(value),
+        Value::Array(values) => Yaml::Array(values.into_iter().map(json_to_yaml).collect()),
+        Value::Object(map) => Yaml::Hash(
+            map.into_iter()
+                .map(|(key, value)| (Yaml::String(key), json_to_yaml(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Escape the characters that Mermaid interprets in a quoted label with entity codes.
+fn escape_mermaid(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("#quot;"),
+            '#' => escaped.push_str("#35;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The largest output given to a task as an environment variable, in bytes of JSON.
+const MAX_INPUT_VAR_SIZE: usize = 64 * 1024;
+
+/// The environment variables holding the outputs of the predecessors of a task in JSON, see
+/// `Dag::set_inputs_as_env`.
+fn input_vars(
+    task_name: &str,
+    names: &HashMap<usize, String>,
+    inputs: &[(usize, Content)],
+) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = Vec::new();
+    for (pre_id, content) in inputs {
+        let name = format!(
+            "{}{}",
+            EnvVar::INPUT_PREFIX,
+            names[pre_id]
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() {
+                    c.to_ascii_uppercase()
+                } else {
+                    '_'
+                })
+                .collect::<String>()
+        );
+        if vars.iter().any(|(var, _)| *var == name) || content.is_pending() {
+            continue;
+        }
+        let value = match content.to_json() {
+            Some(value) => value.to_string(),
+            None => continue,
+        };
+        if value.len() > MAX_INPUT_VAR_SIZE {
+            warn!(
+                "The output of task[{}] is too large to be given to task[{}] as {}",
+                names[pre_id], task_name, name
+            );
+            continue;
+        }
+        vars.push((name, value));
+    }
+    vars
+}
+
+/// Mark a task that is allowed to fail with the given state, and let its successors run as if
+/// it had produced an empty output.
+fn allowed_failure(execute_state: &ExecState, out_degree: usize, state: TaskState) {
+    execute_state.set_output(Output::empty());
+    execute_state.exe_success();
+    execute_state.set_state(state);
+    execute_state.semaphore().add_permits(out_degree);
+}
+
+/// Write the lines of the standard output and standard error held by the output of a command
+/// to the log directory. Outputs of other types are ignored.
+fn write_streams(dir: &Path, task_name: &str, out: &Output, env: &EnvVar) {
+    let content = match out {
+        Output::Out(Some(content))
+        | Output::OutWithExitCode(_, Some(content))
+        | Output::ErrWithExitCode(_, Some(content))
+            if !content.is_pending() =>
+        {
+            content
+        }
+        _ => return,
+    };
+    // Commands wrap their output into a nested content.
+    let streams = content.get::<(Vec<String>, Vec<String>)>().or_else(|| {
+        content
+            .get::<Content>()?
+            .get::<(Vec<String>, Vec<String>)>()
+    });
+    let (stdout, stderr) = match streams {
+        Some(streams) => streams,
+        None => return,
+    };
+    let file_name = task_name.replace(['/', '\\'], "_");
+    let res = fs::create_dir_all(dir).and_then(|_| {
+        for (lines, extension) in [(stdout, "out"), (stderr, "err")] {
+            let text: String = lines.iter().map(|line| env.redact(line) + "\n").collect();
+            fs::write(dir.join(format!("{}.{}", file_name, extension)), text)?;
+        }
+        Ok(())
+    });
+    if let Err(err) = res {
+        error!(
+            "Failed to write the output of task [{}] to {:?}: {}",
+            task_name, dir, err
+        );
+    }
+}
+
+impl Debug for Dag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dag")
+            .field("tasks", &self.tasks)
+            .field("rely_graph", &self.rely_graph)
+            .field("execute_states", &self.execute_states)
+            .field("env", &self.env)
+            .field("can_continue", &self.can_continue)
+            .field("keep_going", &self.keep_going)
+            .field("keep_going_errored", &self.keep_going_errored)
+            .field("exe_sequence", &self.exe_sequence)
+            .finish_non_exhaustive()
+    }
 }
@@ -0,0 +1,114 @@
+//! Type drift detection
+//!
+//! When enabled with `Dag::set_type_drift_detection`, the type of the output of every task is
+//! recorded in a small JSON file, mapping the name of each task to the name of its output
+//! type, and a warning is logged when a task outputs another type than it did in the
+//! previous run, e.g. a `String` where its successors read a `usize`. The changes of the last
+//! run are obtained with `Dag::type_changes`.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// A task whose output type differs from the one it had in the previous run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeChange {
+    /// The task's name.
+    pub task: String,
+    /// The name of the output type in the previous run.
+    pub before: String,
+    /// The name of the output type in the last run.
+    pub after: String,
+}
+
+#[derive(Debug, Default)]
+struct Types {
+    /// The output types recorded by the previous runs.
+    previous: HashMap<String, String>,
+    /// The output types of the current run.
+    current: HashMap<String, String>,
+    changes: Vec<TypeChange>,
+}
+
+/// Records the output types of the tasks in the store, see the module documentation.
+#[derive(Debug)]
+pub(crate) struct TypeDrift {
+    store: PathBuf,
+    types: Mutex<Types>,
+}
+
+impl TypeDrift {
+    pub(crate) fn new(store: PathBuf) -> Arc<Self> {
+        Arc::new(Self {
+            store,
+            types: Mutex::new(Types::default()),
+        })
+    }
+
+    pub(crate) fn store(&self) -> &Path {
+        &self.store
+    }
+
+    /// Start a run, reading the types recorded by the previous runs. A store that does not
+    /// exist yet holds no type.
+    pub(crate) fn start(&self) {
+        let previous = match fs::read_to_string(&self.store) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+                warn!("Ignoring the invalid type store {:?}: {}", self.store, err);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        *self.types.lock().unwrap() = Types {
+            previous,
+            ..Default::default()
+        };
+    }
+
+    /// Record the output type of a task, warning if it changed since the previous run.
+    pub(crate) fn record(&self, task: &str, type_name: &str) {
+        let mut types = self.types.lock().unwrap();
+        if let Some(before) = types.previous.get(task) {
+            if before != type_name {
+                warn!(
+                    "Task [name: {}] outputs {}, but it output {} in the previous run",
+                    task, type_name, before
+                );
+                let change = TypeChange {
+                    task: task.to_owned(),
+                    before: before.clone(),
+                    after: type_name.to_owned(),
+                };
+                types.changes.push(change);
+            }
+        }
+        types.current.insert(task.to_owned(), type_name.to_owned());
+    }
+
+    /// End the run, saving the types of the tasks that produced an output along with the
+    /// types recorded before for the other tasks.
+    pub(crate) fn finish(&self) {
+        let types = self.types.lock().unwrap();
+        let mut all = types.previous.clone();
+        all.extend(types.current.clone());
+        let saved = serde_json::to_string_pretty(&all)
+            .map_err(|err| err.to_string())
+            .and_then(|text| fs::write(&self.store, text).map_err(|err| err.to_string()));
+        if let Err(err) = saved {
+            warn!("Failed to save the type store {:?}: {}", self.store, err);
+        }
+    }
+
+    /// The type changes of the last run, sorted by task name.
+    pub(crate) fn changes(&self) -> Vec<TypeChange> {
+        let mut changes = self.types.lock().unwrap().changes.clone();
+        changes.sort_by(|a, b| a.task.cmp(&b.task));
+        changes
+    }
+}
@@ -0,0 +1,131 @@
+//! Event log
+//!
+//! While a [`Dag`](crate::Dag) runs, every change of the state of its tasks is recorded as a
+//! [`RunEvent`], in chronological order. Unlike the logs, the events are structured and kept
+//! after the run, so that they can be saved with `serde` and replayed later, e.g. to draw the
+//! timeline of the run. The log is obtained with `Dag::event_log`.
+//!
+//! The schema of an event serialized as JSON is:
+//!
+//! ```json
+//! {
+//!   "timestamp_ms": 1700000000000,
+//!   "task": "Task 1",
+//!   "kind": "finished",
+//!   "output": ["line 1", "line 2"],
+//!   "error": null
+//! }
+//! ```
+//!
+//! where `kind` is one of `queued`, `started`, `finished`, `failed` and `skipped`.
+
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{task::Content, EnvVar};
+
+/// A change of the state of a task during a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunEvent {
+    /// When the event happened, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// The name of the task.
+    pub task: String,
+    /// What happened to the task.
+    pub kind: RunEventKind,
+    /// The output of a finished task, if it can be represented in JSON.
+    pub output: Option<serde_json::Value>,
+    /// The error message of a failed task.
+    pub error: Option<String>,
+}
+
+/// The kinds of [`RunEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunEventKind {
+    /// The task was scheduled and waits for its predecessors.
+    Queued,
+    /// The action of the task started.
+    Started,
+    /// The action of the task succeeded.
+    Finished,
+    /// The action of the task failed or panicked.
+    Failed,
+    /// The task was skipped, because it is disabled or a predecessor did not succeed.
+    Skipped,
+}
+
+/// The events recorded during the runs of a dag.
+#[derive(Debug, Default)]
+pub(crate) struct EventLog(Mutex<Vec<RunEvent>>);
+
+impl EventLog {
+    /// Record an event without output nor error.
+    pub(crate) fn record(&self, task: &str, kind: RunEventKind) {
+        self.push(task, kind, None, None);
+    }
+
+    /// Record that a task finished with the given output. Secrets are redacted from it. A lazy
+    /// output that was not computed yet is left out.
+    pub(crate) fn record_finished(&self, task: &str, output: Option<&Content>, env: &EnvVar) {
+        let output = output
+            .filter(|content| !content.is_pending())
+            .and_then(Content::to_json)
+            .map(|value| redact(value, env));
+        self.push(task, RunEventKind::Finished, output, None);
+    }
+
+    /// Record that a task failed with the given error.
+    pub(crate) fn record_failed(&self, task: &str, error: String) {
+        self.push(task, RunEventKind::Failed, None, Some(error));
+    }
+
+    fn push(
+        &self,
+        task: &str,
+        kind: RunEventKind,
+        output: Option<serde_json::Value>,
+        error: Option<String>,
+    ) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        self.0.lock().unwrap().push(RunEvent {
+            timestamp_ms,
+            task: task.to_owned(),
+            kind,
+            output,
+            error,
+        });
+    }
+
+    /// A copy of the events recorded so far.
+    pub(crate) fn events(&self) -> Vec<RunEvent> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// The number of events recorded so far.
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+/// Redact the secrets from all the strings of a JSON value.
+fn redact(value: serde_json::Value, env: &EnvVar) -> serde_json::Value {
+    use serde_json::Value;
+
+    match value {
+        Value::String(text) => Value::String(env.redact(&text)),
+        Value::Array(values) => Value::Array(values.into_iter().map(|v| redact(v, env)).collect()),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, v)| (key, redact(v, env)))
+                .collect(),
+        ),
+        other => other,
+    }
+}
@@ -0,0 +1,59 @@
+//! Early end of a run, when a task returns [`Output::finish`](crate::Output::finish).
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Nothing ended the run yet.
+    #[default]
+    Open,
+    /// A task failed, so the run can no longer be finished.
+    Failed,
+    /// The task with the given id finished the run.
+    FinishedBy(usize),
+}
+
+/// Records whether a task failed or finished the run first.
+#[derive(Debug, Default)]
+pub(crate) struct FinishLatch(Mutex<State>);
+
+impl FinishLatch {
+    /// Record that a task failed, unless the run was already finished.
+    pub(crate) fn fail(&self) {
+        let mut state = self.0.lock().unwrap();
+        if *state == State::Open {
+            *state = State::Failed;
+        }
+    }
+
+    /// Finish the run on behalf of the given task, stopping the scheduling of the other tasks.
+    /// Returns false if a task failed or the run was stopped before.
+    pub(crate) fn finish(&self, id: usize, can_continue: &AtomicBool) -> bool {
+        let mut state = self.0.lock().unwrap();
+        if *state != State::Open
+            || can_continue
+                .compare_exchange(true, false, Ordering::SeqCst, Ordering::Relaxed)
+                .is_err()
+        {
+            return false;
+        }
+        *state = State::FinishedBy(id);
+        true
+    }
+
+    /// The task that finished the run, if any.
+    pub(crate) fn finished_by(&self) -> Option<usize> {
+        match *self.0.lock().unwrap() {
+            State::FinishedBy(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// Forget the outcome of the previous run.
+    pub(crate) fn reset(&self) {
+        *self.0.lock().unwrap() = State::Open;
+    }
+}
@@ -0,0 +1,46 @@
+//! Gantt charts
+//!
+//! `Dag::gantt_data` gives the timeline of the last run of a [`Dag`](crate::Dag) as one
+//! [`GanttBar`] per task, in a shape that charting tools can draw as is: parallel tasks are
+//! stacked on lanes, and the gaps in the lanes show when the dag was idle.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::TaskState;
+
+/// The bar of a task in the Gantt chart of a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GanttBar {
+    /// The task's name.
+    pub task: String,
+    /// When the action of the task started, since the start of the run.
+    pub start: Duration,
+    /// How long the action of the task ran. Skipped tasks are zero-duration markers placed
+    /// when they were skipped.
+    pub duration: Duration,
+    /// The lane of the bar, from 0. Every task running at the same time is on a lane of its
+    /// own, and the number of lanes is the largest number of tasks that ran at the same time.
+    pub lane: usize,
+    /// The final state of the task.
+    pub state: TaskState,
+}
+
+/// Sort the bars by start, and put each bar on the first lane that is free when it starts.
+pub(crate) fn assign_lanes(bars: &mut [GanttBar]) {
+    bars.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| a.task.cmp(&b.task)));
+    // When each lane becomes free.
+    let mut lanes: Vec<Duration> = Vec::new();
+    for bar in bars.iter_mut() {
+        let end = bar.start + bar.duration;
+        bar.lane = match lanes.iter().position(|free| *free <= bar.start) {
+            Some(lane) => lane,
+            None => {
+                lanes.push(end);
+                lanes.len() - 1
+            }
+        };
+        lanes[bar.lane] = end;
+    }
+}
@@ -23,6 +23,8 @@ task1->task2->task3->task4->task5->task6->task7->task8->task9
 
 */
 
+use std::{cmp::Ordering, collections::BinaryHeap};
+
 use bimap::BiMap;
 
 #[derive(Debug, Clone)]
@@ -101,25 +103,30 @@ impl Graph {
     ///
     /// 4. Just repeat step 2, 3 until no more zero degree nodes can be generated.
     ///    If all tasks have been executed, then it's a DAG, or there must be a loop in the graph.
-    pub(crate) fn topo_sort(&self) -> Option<Vec<usize>> {
+    ///
+    /// `weights` holds the weight of each node by index. When several nodes have zero in-degree,
+    /// the heaviest one is taken first, and the one with the lowest index among equally heavy
+    /// nodes.
+    pub(crate) fn topo_sort(&self, weights: &[f64]) -> Option<Vec<usize>> {
         let mut queue = self
             .in_degree
             .iter()
             .enumerate()
             .filter_map(|(index, &degree)| if degree == 0 { Some(index) } else { None })
-            .collect::<Vec<_>>();
+            .map(|index| Ready::new(index, weights))
+            .collect::<BinaryHeap<_>>();
 
         let mut in_degree = self.in_degree.clone();
 
         let mut sequence = Vec::with_capacity(self.size);
 
-        while let Some(v) = queue.pop() {
+        while let Some(Ready { index: v, .. }) = queue.pop() {
             sequence.push(v);
 
             for &index in self.adj[v].iter() {
                 in_degree[index] -= 1;
                 if in_degree[index] == 0 {
-                    queue.push(index)
+                    queue.push(Ready::new(index, weights))
                 }
             }
         }
@@ -131,6 +138,153 @@ impl Graph {
         }
     }
 
+    /// Group the nodes of the graph into levels by dependency depth.
+    ///
+    /// Level 0 holds every node with zero in-degree (the roots of all connected components),
+    /// level `n` holds the nodes whose predecessors all lie in levels `< n`, with at least one
+    /// of them in level `n - 1`. Nodes in the same level never depend on each other, so they may
+    /// run simultaneously. Returns None if the graph contains a loop.
+    pub(crate) fn levels(&self) -> Option<Vec<Vec<usize>>> {
+        let mut in_degree = self.in_degree.clone();
+        let mut current = in_degree
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &degree)| if degree == 0 { Some(index) } else { None })
+            .collect::<Vec<_>>();
+
+        let mut levels = Vec::new();
+        let mut visited = 0;
+
+        while !current.is_empty() {
+            visited += current.len();
+            let mut next = Vec::new();
+            for &v in current.iter() {
+                for &index in self.adj[v].iter() {
+                    in_degree[index] -= 1;
+                    if in_degree[index] == 0 {
+                        next.push(index);
+                    }
+                }
+            }
+            levels.push(current);
+            current = next;
+        }
+
+        if visited < self.size {
+            None
+        } else {
+            Some(levels)
+        }
+    }
+
+    /// The largest total weight of the nodes along a path of the graph, where `weights` holds the
+    /// weight of each node by index. Returns None if the graph contains a loop.
+    pub(crate) fn critical_path_weight(&self, weights: &[f64]) -> Option<f64> {
+        let order = self.topo_sort(weights)?;
+        // The heaviest path ending at each node.
+        let mut heaviest = weights.to_vec();
+        for v in order {
+            for &index in self.adj[v].iter() {
+                heaviest[index] = heaviest[index].max(heaviest[v] + weights[index]);
+            }
+        }
+        Some(heaviest.into_iter().fold(0.0, f64::max))
+    }
+
+    /// The indices of the nodes along a longest path of the graph, in order, that is a path
+    /// holding as many nodes as the depth of the graph. Returns None if the graph contains a
+    /// loop.
+    pub(crate) fn longest_path(&self) -> Option<Vec<usize>> {
+        let order = self.topo_sort(&vec![0.0; self.size])?;
+        // The number of nodes of the longest path ending at each node, and the node before it.
+        let mut length = vec![1; self.size];
+        let mut previous = vec![None; self.size];
+        for &v in order.iter() {
+            for &w in self.adj[v].iter() {
+                if length[v] + 1 > length[w] {
+                    length[w] = length[v] + 1;
+                    previous[w] = Some(v);
+                }
+            }
+        }
+        let mut path = Vec::new();
+        let mut node = (0..self.size).max_by_key(|&v| length[v]);
+        while let Some(v) = node {
+            path.push(v);
+            node = previous[v];
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Find the edges implied by other paths of the graph: an edge `v -> w` is redundant when
+    /// `w` can also be reached from `v` through another successor of `v`. These are the edges
+    /// removed by a transitive reduction. Returns the edges as pairs of indices, or None if the
+    /// graph contains a loop.
+    ///
+    /// The nodes reachable from each node are computed as bit sets in reverse topological
+    /// order, so this takes `O(n * e / 64)` time and `O(n * n / 64)` memory.
+    pub(crate) fn redundant_edges(&self) -> Option<Vec<(usize, usize)>> {
+        let order = self.topo_sort(&vec![0.0; self.size])?;
+        let words = self.size.div_ceil(64);
+        let contains = |set: &[u64], index: usize| set[index / 64] & (1 << (index % 64)) != 0;
+        // The nodes reachable from each node, excluding itself.
+        let mut reachable = vec![vec![0u64; words]; self.size];
+        let mut redundant = Vec::new();
+        for &v in order.iter().rev() {
+            // The nodes reachable from v through a path of at least two edges.
+            let mut indirect = vec![0u64; words];
+            for &w in self.adj[v].iter() {
+                indirect
+                    .iter_mut()
+                    .zip(reachable[w].iter())
+                    .for_each(|(word, reach)| *word |= reach);
+            }
+            let mut direct = self.adj[v].clone();
+            direct.sort_unstable();
+            direct.dedup();
+            for &w in direct.iter() {
+                if contains(&indirect, w) {
+                    redundant.push((v, w));
+                }
+                indirect[w / 64] |= 1 << (w % 64);
+            }
+            reachable[v] = indirect;
+        }
+        Some(redundant)
+    }
+
+    /// Split the nodes into the weakly connected components of the graph, that is the groups
+    /// of nodes linked by edges regardless of their direction.
+    pub(crate) fn components(&self) -> Vec<Vec<usize>> {
+        // Union-find over the node indices.
+        let mut parent: Vec<usize> = (0..self.size).collect();
+        fn find(parent: &mut [usize], mut v: usize) -> usize {
+            while parent[v] != v {
+                parent[v] = parent[parent[v]];
+                v = parent[v];
+            }
+            v
+        }
+        for v in 0..self.size {
+            for &w in self.adj[v].iter() {
+                let (rv, rw) = (find(&mut parent, v), find(&mut parent, w));
+                parent[rv.max(rw)] = rv.min(rw);
+            }
+        }
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        let mut component_of = vec![usize::MAX; self.size];
+        for v in 0..self.size {
+            let root = find(&mut parent, v);
+            if component_of[root] == usize::MAX {
+                component_of[root] = components.len();
+                components.push(Vec::new());
+            }
+            components[component_of[root]].push(v);
+        }
+        components
+    }
+
     /// Get the out degree of a node.
     pub(crate) fn get_node_out_degree(&self, id: &usize) -> usize {
         match self.nodes.get_by_left(id) {
@@ -190,3 +344,41 @@ impl Default for Graph {
         }
     }
 }
+
+/// A node with zero in-degree waiting in the queue of [`Graph::topo_sort`], ordered so that the
+/// heaviest node, then the one with the lowest index, is the greatest.
+struct Ready {
+    index: usize,
+    weight: f64,
+}
+
+impl Ready {
+    fn new(index: usize, weights: &[f64]) -> Self {
+        Self {
+            index,
+            weight: weights[index],
+        }
+    }
+}
+
+impl Ord for Ready {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.weight
+            .total_cmp(&other.weight)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Ready {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ready {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ready {}
@@ -0,0 +1,27 @@
+//! Cancellation of task groups
+//!
+//! [`GroupCanceller`] records the groups of a [`Dag`](crate::Dag) whose tasks must not start,
+//! see `Dag::cancel_group`. It is obtained with `Dag::group_canceller` before starting the dag,
+//! so that groups can be cancelled from another thread while the dag runs.
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+/// A shared handle cancelling the task groups of a dag, see [`TaskGroup`](crate::TaskGroup).
+#[derive(Debug, Clone, Default)]
+pub struct GroupCanceller(Arc<Mutex<BTreeSet<String>>>);
+
+impl GroupCanceller {
+    /// Cancel the group with the given name: its members that have not started yet are
+    /// skipped, along with their successors. The members already running are not interrupted.
+    pub fn cancel(&self, group: &str) {
+        self.0.lock().unwrap().insert(group.to_owned());
+    }
+
+    /// Whether the group with the given name was cancelled.
+    pub fn is_cancelled(&self, group: &str) -> bool {
+        self.0.lock().unwrap().contains(group)
+    }
+}
@@ -0,0 +1,74 @@
+//! Import of tasks from another dag
+//!
+//! `Dag::import_subgraph` copies a task of another [`Dag`](crate::Dag), along with all the
+//! tasks it transitively depends on, and [`NameCollision`] decides what happens to the copied
+//! tasks whose names are already taken.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::Task;
+
+use super::DagError;
+
+/// What to do with an imported task whose name is already taken by a task of the dag.
+///
+/// Tasks are matched by name only: two tasks with the same name but different actions or
+/// dependencies are considered the same task by [`NameCollision::Reuse`], and conflicting by
+/// the other strategies.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum NameCollision {
+    /// Fail with [`DagError::DuplicateTask`], importing nothing.
+    #[default]
+    Fail,
+    /// Keep the task of the dag instead of importing the task, and make the imported tasks
+    /// depend on it. The predecessors of the skipped task are not imported either, unless
+    /// another imported task depends on them.
+    Reuse,
+    /// Import the task under its name followed by the given suffix. If that name is taken as
+    /// well, fail with [`DagError::DuplicateTask`], importing nothing.
+    Rename(String),
+}
+
+/// The ids of the task `root` and of the tasks it transitively depends on, every task coming
+/// after its predecessors. The tasks for which `keep` returns false are listed, but their
+/// predecessors are not followed.
+pub(crate) fn ancestors(
+    tasks: &HashMap<usize, Box<dyn Task>>,
+    root: usize,
+    keep: impl Fn(&dyn Task) -> bool,
+) -> Result<Vec<usize>, DagError> {
+    let mut order = Vec::new();
+    let (mut done, mut visiting) = (HashSet::new(), HashSet::new());
+    // Each frame holds a task and the index of its next predecessor to follow.
+    let mut stack = vec![(root, 0)];
+    visiting.insert(root);
+    while let Some((id, next)) = stack.pop() {
+        let task = &tasks[&id];
+        let precursors = if keep(task.as_ref()) {
+            task.precursors()
+        } else {
+            &[]
+        };
+        match precursors.get(next) {
+            Some(pre) => {
+                stack.push((id, next + 1));
+                if done.contains(pre) {
+                    continue;
+                }
+                if !tasks.contains_key(pre) {
+                    return Err(DagError::RelyTaskIllegal(task.name().to_string()));
+                }
+                if !visiting.insert(*pre) {
+                    return Err(DagError::LoopGraph);
+                }
+                stack.push((*pre, 0));
+            }
+            None => {
+                visiting.remove(&id);
+                done.insert(id);
+                order.push(id);
+            }
+        }
+    }
+    Ok(order)
+}
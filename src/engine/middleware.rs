@@ -0,0 +1,47 @@
+//! Task middleware
+//!
+//! A [`TaskMiddleware`] wraps the execution of every task of a dag, so that cross-cutting logic
+//! such as preparing a working directory, timing or logging with context is written once
+//! instead of in each action. Middlewares are registered with [`Dag::add_middleware`].
+//!
+//! [`Dag::add_middleware`]: crate::Dag::add_middleware
+//!
+//! # Example
+//!
+//! ```rust
+//! use dagrs::{Dag, DefaultTask, Input, Output, TaskMiddleware};
+//!
+//! struct Trace;
+//!
+//! impl TaskMiddleware for Trace {
+//!     fn before(&self, task_name: &str, _input: &Input) {
+//!         println!("start {}", task_name);
+//!     }
+//!
+//!     fn after(&self, task_name: &str, output: &Output) {
+//!         println!("end {} (failed: {})", task_name, output.is_err());
+//!     }
+//! }
+//!
+//! let task = DefaultTask::with_closure("Simple Task", |_, _| Output::empty());
+//! let mut dag = Dag::with_tasks(vec![task]);
+//! dag.add_middleware(Trace);
+//! assert!(dag.start().unwrap());
+//! ```
+
+use crate::task::{Input, Output};
+
+/// Logic run around the action of every task.
+///
+/// `before` is called with the input of the task right before its action runs, and `after`
+/// with the output once the action returns. `after` is also called when the action fails or
+/// panics, a panic being reported as an error output, so it is suitable for cleanup. A panic
+/// in `before` fails the task the same way without running its action, and `after` is then
+/// only called for the middlewares whose `before` already ran.
+/// Both are called on the thread running the action, and do nothing by default.
+pub trait TaskMiddleware: Send + Sync {
+    /// Called before the action of the task runs.
+    fn before(&self, _task_name: &str, _input: &Input) {}
+    /// Called after the action of the task has run, whatever its result.
+    fn after(&self, _task_name: &str, _output: &Output) {}
+}
@@ -10,15 +10,52 @@
 //! the Dags are added to the Engine , executing each Dag in turn.
 
 pub use dag::Dag;
+pub use drift::TypeChange;
+pub use event::{RunEvent, RunEventKind};
+pub use gantt::GanttBar;
+pub use group::GroupCanceller;
+pub use import::NameCollision;
 use log::error;
+pub use middleware::TaskMiddleware;
+pub use notifier::WebhookNotifier;
+pub use progress::RunProgress;
+pub use report::{DurationChange, GroupReport, RunDiff, RunReport, StateChange, TaskReport};
+pub use running::{ReadyTasks, RunningTasks};
+pub use stats::{SchedulerSample, SchedulerStats};
 use thiserror::Error;
+pub use topology::{Topology, TopologyDiff};
+pub use unconsumed::UnconsumedOutput;
 
+mod budget;
 mod dag;
+mod drift;
+mod event;
+mod finish;
+mod gantt;
 mod graph;
+mod group;
+mod import;
+mod middleware;
+mod notifier;
+mod progress;
+mod report;
+mod running;
+mod schedule;
+mod signal;
+mod stats;
+mod topology;
+mod unconsumed;
 
 use crate::ParseError;
-use std::{collections::HashMap, sync::Arc};
-use tokio::runtime::Runtime;
+use graph::Graph;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{runtime::Runtime, sync::watch};
 
 /// The Engine. Manage multiple Dags.
 pub struct Engine {
@@ -29,6 +66,12 @@ pub struct Engine {
     /// A tokio runtime.
     /// In order to save computer resources, multiple Dags share one runtime.
     runtime: Runtime,
+    /// Whether SIGINT and SIGTERM cancel the running Dag.
+    handle_signals: bool,
+    /// Set once a signal cancelled the current run, so that no further Dag is started.
+    cancelled: Arc<watch::Sender<bool>>,
+    /// The pairs of Dags of which the first must succeed before the second runs.
+    dependencies: Vec<(String, String)>,
 }
 
 /// Errors that may be raised by building and running dag jobs.
@@ -47,46 +90,486 @@ pub enum DagError {
     /// There are no tasks in the job.
     #[error("There are no tasks in the job.")]
     EmptyJob,
+    /// The declared output type of a task differs from the declared input type of its successor.
+    #[error(
+        "Task[{consumer}] expects input of type {expected}, but task[{producer}] outputs {found}."
+    )]
+    TypeMismatch {
+        producer: String,
+        consumer: String,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// No task with the given name exists in the job.
+    #[error("Task[{0}] does not exist.")]
+    TaskNotFound(String),
+    /// A task with the given name already exists in the job.
+    #[error("Task[{0}] already exists.")]
+    DuplicateTask(String),
+    /// The job did not finish within the given time.
+    #[error("The job timed out.")]
+    Timeout,
+    /// The job is required to be connected, but these tasks are not connected to the rest of it.
+    #[error("Tasks {0:?} are not connected to the rest of the job.")]
+    Disconnected(Vec<String>),
+    /// The action of the task cannot be written to a configuration file.
+    #[error("The action of task[{0}] cannot be serialized.")]
+    Unserializable(String),
+    /// No task changed state for the given time while some tasks had not finished.
+    #[error("The job stalled: no task progressed for {0:?}.")]
+    Stalled(Duration),
+    /// The dependency chain of the job is longer than allowed. The path lists the names of
+    /// the tasks of a longest chain.
+    #[error("The job is deeper than {max} tasks: {}.", path.join(" -> "))]
+    TooDeep { max: usize, path: Vec<String> },
+    /// The task requests more resources than the whole budget of the job, so it could never start.
+    #[error("Task[{0}] requests more resources than the budget of the job.")]
+    OverBudget(String),
+    /// A Dag with this name was already added to the Engine.
+    #[error("Dag[{0}] already exists.")]
+    DuplicateDag(String),
+    /// No Dag with the given name was added to the Engine.
+    #[error("Dag[{0}] does not exist.")]
+    DagNotFound(String),
+    /// The job was interrupted by a signal.
+    #[error("The job was cancelled.")]
+    Cancelled,
+}
+
+/// The outcome of running the Dags of an [`Engine`] within a time budget. Each list holds
+/// the names of the Dags in the order they were added to the Engine.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadlineReport {
+    /// The Dags that ran successfully.
+    pub completed: Vec<String>,
+    /// The Dags that failed, or did not finish before the deadline.
+    pub failed: Vec<String>,
+    /// The Dags that were not started because the deadline had passed or a signal cancelled
+    /// the run.
+    pub not_started: Vec<String>,
 }
 
 impl Engine {
     /// Add a Dag to the Engine and assign a sequence number to the Dag.
-    /// It should be noted that different Dags should specify different names.
-    pub fn append_dag(&mut self, name: &str, mut dag: Dag) {
+    ///
+    /// Different Dags should specify different names: if a Dag with the same name was already
+    /// added, it is kept and [`DagError::DuplicateDag`] is returned, see `replace_dag` to
+    /// replace it. The error raised by checking the Dag, if any, is returned as well, and the
+    /// Dag is not added.
+    pub fn append_dag(&mut self, name: &str, mut dag: Dag) -> Result<(), DagError> {
+        if self.dags.contains_key(name) {
+            return Err(DagError::DuplicateDag(name.to_string()));
+        }
+        if let Err(err) = dag.init() {
+            error!("Some error occur: {}", err);
+            return Err(err);
+        }
+        self.dags.insert(name.to_string(), dag);
+        let len = self.sequence.len();
+        self.sequence.insert(len + 1, name.to_string());
+        Ok(())
+    }
+
+    /// Add a Dag to the Engine, replacing the Dag with the same name if there is one.
+    ///
+    /// The new Dag takes the sequence number of the Dag it replaces, so it runs at the same
+    /// point of `run_sequential`. If checking the new Dag raises an error, it is returned and
+    /// the previous Dag is kept.
+    pub fn replace_dag(&mut self, name: &str, mut dag: Dag) -> Result<(), DagError> {
         if !self.dags.contains_key(name) {
-            match dag.init() {
-                Ok(()) => {
-                    self.dags.insert(name.to_string(), dag);
-                    let len = self.sequence.len();
-                    self.sequence.insert(len + 1, name.to_string());
-                }
-                Err(err) => {
-                    error!("Some error occur: {}", err);
-                }
+            return self.append_dag(name, dag);
+        }
+        dag.init()?;
+        self.dags.insert(name.to_string(), dag);
+        Ok(())
+    }
+
+    /// Make the Dag `after` wait for the Dag `before` to succeed, so that the Dags of the Engine
+    /// form a dag of their own.
+    ///
+    /// `run_parallel` then runs a Dag once all the Dags it depends on succeeded, so only the
+    /// independent Dags run at the same time, and `run_sequential` runs the Dags in the order
+    /// they were added to the Engine as long as they come after the Dags they depend on. A Dag
+    /// does not run if a Dag it depends on failed or did not run, and is reported as failed.
+    ///
+    /// Returns [`DagError::DagNotFound`] if either Dag was not added to the Engine, and
+    /// [`DagError::LoopGraph`] if the dependency would close a cycle, in which case it is not
+    /// added.
+    pub fn add_dependency(&mut self, before: &str, after: &str) -> Result<(), DagError> {
+        for name in [before, after] {
+            if !self.dags.contains_key(name) {
+                return Err(DagError::DagNotFound(name.to_string()));
             }
         }
+        self.dependencies
+            .push((before.to_string(), after.to_string()));
+        if self.dependency_order().is_none() {
+            self.dependencies.pop();
+            return Err(DagError::LoopGraph);
+        }
+        Ok(())
+    }
+
+    /// The names of the Dags that must succeed before the given one runs.
+    fn prerequisites(&self, name: &str) -> Vec<&str> {
+        self.dependencies
+            .iter()
+            .filter(|(_, after)| after == name)
+            .map(|(before, _)| before.as_str())
+            .collect()
+    }
+
+    /// The names of the Dags sorted so that each comes after the Dags it depends on, and
+    /// otherwise in the order they were added to the Engine. None if the dependencies form a
+    /// cycle.
+    fn dependency_order(&self) -> Option<Vec<String>> {
+        let names = self.dag_names();
+        let mut graph = Graph::new();
+        graph.set_graph_size(names.len());
+        let index: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.as_str(), index))
+            .collect();
+        for index in 0..names.len() {
+            graph.add_node(index);
+        }
+        for (before, after) in &self.dependencies {
+            graph.add_edge(index[before.as_str()], index[after.as_str()]);
+        }
+        // The Dags added first are the heaviest, so that they are taken first.
+        let weights: Vec<f64> = (0..names.len()).map(|index| -(index as f64)).collect();
+        let order = graph.topo_sort(&weights)?;
+        Some(
+            order
+                .into_iter()
+                .map(|index| names[index].clone())
+                .collect(),
+        )
+    }
+
+    /// The names of the Dags in the order `run_sequential` runs them: the order they were
+    /// added to the Engine, except that each Dag comes after the Dags it depends on.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Engine, Output};
+    ///
+    /// let dag = || Dag::with_tasks(vec![DefaultTask::with_closure("a", |_, _| Output::empty())]);
+    /// let mut engine = Engine::default();
+    /// engine.append_dag("deploy", dag()).unwrap();
+    /// engine.append_dag("build", dag()).unwrap();
+    /// engine.add_dependency("build", "deploy").unwrap();
+    /// assert_eq!(engine.dag_names(), vec!["deploy", "build"]);
+    /// assert_eq!(engine.sequence_order(), vec!["build", "deploy"]);
+    /// ```
+    pub fn sequence_order(&self) -> Vec<String> {
+        // Cycles are rejected by `add_dependency`.
+        self.dependency_order().unwrap()
+    }
+
+    /// Make SIGINT and SIGTERM (Ctrl-C on Windows) cancel the running Dag, see
+    /// [`Dag::set_handle_signals`]. A cancelled Dag is reported as failed, and the Dags of
+    /// the same run that have not started yet are not run: they are reported as failed, or
+    /// as not started by the runs with a deadline.
+    pub fn set_handle_signals(&mut self, handle_signals: bool) {
+        self.handle_signals = handle_signals;
     }
 
     /// Given a Dag name, execute this Dag.
     /// Returns true if the given Dag executes successfully, otherwise false.
     pub fn run_dag(&mut self, name: &str) -> bool {
-        if let Some(dag) = self.dags.get(name) {
-            self.runtime.block_on(dag.run())
-        } else {
-            error!("No job named '{}'", name);
-            false
+        self.listening(|| self.run_until(name, None))
+    }
+
+    /// Call `run`, listening for the signals for its whole duration if they are handled, so
+    /// that a signal received between two Dags still cancels the run.
+    fn listening<R>(&self, run: impl FnOnce() -> R) -> R {
+        self.cancelled.send_replace(false);
+        let listener = self.handle_signals.then(|| {
+            // The signal handlers are installed before any task starts.
+            let _runtime = self.runtime.enter();
+            let mut signals = signal::Signals::install();
+            let cancelled = self.cancelled.clone();
+            self.runtime.spawn(async move {
+                signals.recv().await;
+                cancelled.send_replace(true);
+            })
+        });
+        let result = run();
+        if let Some(listener) = listener {
+            listener.abort();
         }
+        result
+    }
+
+    /// Whether a signal cancelled the current run.
+    fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// Execute the Dag with the given name, giving up once the deadline passes.
+    fn run_until(&self, name: &str, deadline: Option<Instant>) -> bool {
+        let dag = match self.dags.get(name) {
+            Some(dag) => dag,
+            None => {
+                error!("No job named '{}'", name);
+                return false;
+            }
+        };
+        let run = async {
+            match deadline {
+                Some(deadline) => {
+                    let deadline = tokio::time::Instant::from_std(deadline);
+                    match tokio::time::timeout_at(deadline, dag.run()).await {
+                        Ok(success) => success,
+                        Err(_) => {
+                            // The tasks that have not started yet are cancelled, except for
+                            // the finalizers.
+                            dag.abandon();
+                            dag.finalize().await;
+                            error!("Job '{}' did not finish before the deadline", name);
+                            false
+                        }
+                    }
+                }
+                None => dag.run().await,
+            }
+        };
+        if !self.handle_signals {
+            return self.runtime.block_on(run);
+        }
+        let mut cancelled = self.cancelled.subscribe();
+        if *cancelled.borrow_and_update() {
+            error!("Job '{}' not run, since the run was cancelled", name);
+            return false;
+        }
+        self.runtime.block_on(async {
+            tokio::select! {
+                biased;
+                _ = cancelled.changed() => {
+                    dag.cancel();
+                    dag.finalize().await;
+                    error!("Job '{}' cancelled by a signal", name);
+                    false
+                }
+                success = run => success,
+            }
+        })
+    }
+
+    /// The names of the Dags, in the order they were added to the Engine.
+    pub fn dag_names(&self) -> Vec<String> {
+        (1..self.sequence.len() + 1)
+            .map(|seq| self.sequence[&seq].clone())
+            .collect()
     }
 
     /// Execute all the Dags in the Engine in sequence according to the order numbers of the Dags in
     /// the sequence from small to large. The return value is the execution status of all tasks.
+    ///
+    /// The Dags that depend on other Dags run after them, see `add_dependency`, but the
+    /// statuses are still given in the order the Dags were added to the Engine.
     pub fn run_sequential(&mut self) -> Vec<bool> {
-        let mut res = Vec::with_capacity(self.sequence.len());
-        for seq in 1..self.sequence.len() + 1 {
-            let name = self.sequence.get(&seq).unwrap().clone();
-            res.push(self.run_dag(name.as_str()));
+        let results = self.listening(|| self.run_in_order(&self.dag_names(), None));
+        self.dag_names()
+            .iter()
+            .map(|name| results[name].unwrap_or(false))
+            .collect()
+    }
+
+    /// Execute the given Dags one after the other, following their dependencies, which must
+    /// be given as well. Each Dag is mapped to whether it succeeded, or None if it was not
+    /// started because the deadline had passed or a signal cancelled the run.
+    fn run_in_order(
+        &self,
+        names: &[String],
+        deadline: Option<Instant>,
+    ) -> HashMap<String, Option<bool>> {
+        let order = self.sequence_order();
+        let mut results: HashMap<String, Option<bool>> = HashMap::with_capacity(names.len());
+        for name in order.into_iter().filter(|name| names.contains(name)) {
+            let result = if let Some(pre) = self
+                .prerequisites(&name)
+                .into_iter()
+                .find(|pre| results[*pre] != Some(true))
+            {
+                error!(
+                    "Job '{}' not run, since job '{}' did not succeed",
+                    name, pre
+                );
+                Some(false)
+            } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                None
+            } else if self.is_cancelled() {
+                error!("Job '{}' not run, since the run was cancelled", name);
+                None
+            } else {
+                Some(self.run_until(&name, deadline))
+            };
+            results.insert(name, result);
+        }
+        results
+    }
+
+    /// Execute all the Dags in the Engine at the same time. The return value is the execution
+    /// status of each Dag, in the order the Dags were added to the Engine.
+    ///
+    /// A Dag that depends on other Dags only starts once they succeeded, see `add_dependency`.
+    pub fn run_parallel(&mut self) -> Vec<bool> {
+        self.listening(|| self.run_concurrently(&self.dag_names(), None))
+    }
+
+    /// Execute the Dags whose names match `filter` in sequence like `run_sequential`, e.g. to
+    /// only run the nightly pipelines. The Dags they depend on, see `add_dependency`, are run
+    /// as well, whether they match or not.
+    ///
+    /// The return value maps the name of each Dag that was run to whether it succeeded, in
+    /// the order the Dags were added to the Engine.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Engine, Output};
+    ///
+    /// let dag = || Dag::with_tasks(vec![DefaultTask::with_closure("a", |_, _| Output::empty())]);
+    /// let mut engine = Engine::default();
+    /// for name in ["build", "nightly-bench", "nightly-fuzz"] {
+    ///     engine.append_dag(name, dag()).unwrap();
+    /// }
+    /// let results = engine.run_matching(|name| name.starts_with("nightly-"));
+    /// assert_eq!(
+    ///     results,
+    ///     vec![("nightly-bench".to_string(), true), ("nightly-fuzz".to_string(), true)]
+    /// );
+    /// ```
+    pub fn run_matching(&mut self, filter: impl Fn(&str) -> bool) -> Vec<(String, bool)> {
+        let names = self.matching(filter);
+        let results = self.listening(|| self.run_in_order(&names, None));
+        names
+            .into_iter()
+            .map(|name| {
+                let success = results[&name].unwrap_or(false);
+                (name, success)
+            })
+            .collect()
+    }
+
+    /// Execute the Dags whose names match `filter` at the same time like `run_parallel`,
+    /// along with the Dags they depend on. The return value is the same as for
+    /// `run_matching`.
+    pub fn run_matching_parallel(&mut self, filter: impl Fn(&str) -> bool) -> Vec<(String, bool)> {
+        let names = self.matching(filter);
+        let results = self.listening(|| self.run_concurrently(&names, None));
+        names.into_iter().zip(results).collect()
+    }
+
+    /// The names of the Dags that match `filter` and of the Dags they transitively depend
+    /// on, in the order they were added to the Engine.
+    fn matching(&self, filter: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut selected: HashSet<String> = HashSet::new();
+        let mut stack: Vec<String> = self
+            .dag_names()
+            .into_iter()
+            .filter(|name| filter(name))
+            .collect();
+        while let Some(name) = stack.pop() {
+            if !selected.contains(&name) {
+                stack.extend(self.prerequisites(&name).into_iter().map(str::to_string));
+                selected.insert(name);
+            }
+        }
+        self.dag_names()
+            .into_iter()
+            .filter(|name| selected.contains(name))
+            .collect()
+    }
+
+    /// Execute the given Dags at the same time, each once the Dags it depends on succeeded,
+    /// which must be given as well. The return value is the execution status of each Dag.
+    fn run_concurrently(&self, names: &[String], deadline: Option<Instant>) -> Vec<bool> {
+        let results: Mutex<HashMap<&str, bool>> = Mutex::new(HashMap::new());
+        let finished = Condvar::new();
+        let (results, finished) = (&results, &finished);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = names
+                .iter()
+                .map(|name| {
+                    scope.spawn(move || {
+                        let prerequisites = self.prerequisites(name);
+                        let mut done = results.lock().unwrap();
+                        let failed = loop {
+                            let failed = prerequisites
+                                .iter()
+                                .find(|pre| done.get(*pre) == Some(&false));
+                            if failed.is_some()
+                                || prerequisites.iter().all(|pre| done.contains_key(pre))
+                            {
+                                break failed;
+                            }
+                            done = finished.wait(done).unwrap();
+                        };
+                        drop(done);
+                        let success = match failed {
+                            Some(pre) => {
+                                error!(
+                                    "Job '{}' not run, since job '{}' did not succeed",
+                                    name, pre
+                                );
+                                false
+                            }
+                            None => panic::catch_unwind(AssertUnwindSafe(|| {
+                                self.run_until(name, deadline)
+                            }))
+                            .unwrap_or(false),
+                        };
+                        results.lock().unwrap().insert(name, success);
+                        finished.notify_all();
+                        success
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or(false))
+                .collect()
+        })
+    }
+
+    /// Execute the Dags in sequence like `run_sequential`, within the given time budget.
+    ///
+    /// Once the budget is spent, no more Dags are started. The Dag running at that moment
+    /// is given the remaining time only: when it runs out, its tasks that have not started
+    /// are cancelled and it is reported as failed, while its running tasks are abandoned.
+    pub fn run_sequential_with_deadline(&mut self, budget: Duration) -> DeadlineReport {
+        let deadline = Instant::now() + budget;
+        let mut results = self.listening(|| self.run_in_order(&self.dag_names(), Some(deadline)));
+        let mut report = DeadlineReport::default();
+        for name in self.dag_names() {
+            match results.remove(&name).flatten() {
+                None => report.not_started.push(name),
+                Some(true) => report.completed.push(name),
+                Some(false) => report.failed.push(name),
+            }
+        }
+        report
+    }
+
+    /// Execute all the Dags at the same time like `run_parallel`, within the given time
+    /// budget. The Dags that do not finish in time are reported as failed, see
+    /// `run_sequential_with_deadline`.
+    pub fn run_parallel_with_deadline(&mut self, budget: Duration) -> DeadlineReport {
+        let deadline = Instant::now() + budget;
+        let names = self.dag_names();
+        let results = self.listening(|| self.run_concurrently(&names, Some(deadline)));
+        let mut report = DeadlineReport::default();
+        for (name, success) in names.into_iter().zip(results) {
+            if success {
+                report.completed.push(name);
+            } else {
+                report.failed.push(name);
+            }
         }
-        res
+        report
     }
 
     /// Given the name of the Dag, get the execution result of the specified Dag.
@@ -101,6 +584,9 @@ impl Default for Engine {
             dags: HashMap::new(),
             runtime: Runtime::new().unwrap(),
             sequence: HashMap::new(),
+            handle_signals: false,
+            cancelled: Arc::new(watch::channel(false).0),
+            dependencies: Vec::new(),
         }
     }
 }
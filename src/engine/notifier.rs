@@ -0,0 +1,94 @@
+//! Completion notifications
+//!
+//! [`WebhookNotifier`] posts the [`RunReport`] of a finished dag to a webhook as JSON, e.g.
+//! to notify a chat channel when a pipeline completes. The request is sent by the `curl`
+//! command, which must be available on the machine at run time: dagrs does not depend on an
+//! HTTP client, and a missing `curl` is logged as an error when notifying.
+//!
+//! The request is given up after the timeout of the notifier, 10 seconds by default, so that
+//! an unresponsive webhook does not block the end of the run.
+//!
+//! # Example
+//!
+//! ```rust
+//! use dagrs::{Dag, DefaultTask, Output, WebhookNotifier};
+//!
+//! let task = DefaultTask::with_closure("Simple Task", |_, _| Output::empty());
+//! let mut dag = Dag::with_tasks(vec![task]);
+//! let notifier = WebhookNotifier::new("http://localhost:8080/hook");
+//! dag.on_complete(move |report| notifier.notify(report));
+//! ```
+
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use log::{debug, error};
+
+use super::RunReport;
+
+/// Posts run reports to a webhook url.
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    url: String,
+    timeout: Duration,
+}
+
+impl WebhookNotifier {
+    /// Create a notifier that posts to the given url.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Set how long a notification may take, connecting included, before it is given up.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Post the report as JSON to the webhook.
+    /// Failures are logged rather than returned, since the dag has already finished.
+    pub fn notify(&self, report: &RunReport) {
+        let timeout = format!("{:.3}", self.timeout.as_secs_f64());
+        let child = Command::new("curl")
+            .args(["-sS", "-X", "POST", "-H", "Content-Type: application/json"])
+            .args(["--connect-timeout", &timeout, "--max-time", &timeout])
+            .args(["--data-binary", "@-", &self.url])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                error!(
+                    "Failed to notify webhook [{}]: the curl command was not found",
+                    self.url
+                );
+                return;
+            }
+            Err(err) => {
+                error!("Failed to notify webhook [{}]: {}", self.url, err);
+                return;
+            }
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Err(err) = stdin.write_all(report.to_json().as_bytes()) {
+                error!("Failed to notify webhook [{}]: {}", self.url, err);
+            }
+        }
+        match child.wait_with_output() {
+            Ok(out) if out.status.success() => debug!("Notified webhook [{}]", self.url),
+            Ok(out) => error!(
+                "Failed to notify webhook [{}]: {}",
+                self.url,
+                String::from_utf8_lossy(&out.stderr)
+            ),
+            Err(err) => error!("Failed to notify webhook [{}]: {}", self.url, err),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! Run progress
+//!
+//! [`RunProgress`] counts the tasks of a [`Dag`](crate::Dag) that are done in the current run,
+//! so that a progress bar can poll it from another thread while the dag runs. It is obtained
+//! with `Dag::progress_handle` before starting the dag.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+#[derive(Debug, Default)]
+struct Counts {
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+/// A shared view of the progress of the current run of a dag.
+///
+/// Every task counts the same, whatever its weight: a task is done once it succeeded, failed
+/// or was skipped.
+#[derive(Debug, Clone, Default)]
+pub struct RunProgress(Arc<Counts>);
+
+impl RunProgress {
+    /// The number of tasks of the run that are done.
+    pub fn done(&self) -> usize {
+        self.0.done.load(Ordering::Acquire)
+    }
+
+    /// The number of tasks of the run, which is 0 before the first run.
+    pub fn total(&self) -> usize {
+        self.0.total.load(Ordering::Acquire)
+    }
+
+    /// The fraction of the tasks of the run that are done, from 0 to 1. It is 0 before the
+    /// first run.
+    pub fn fraction(&self) -> f32 {
+        match self.total() {
+            0 => 0.0,
+            total => (self.done().min(total) as f64 / total as f64) as f32,
+        }
+    }
+
+    /// Start counting a run of `total` tasks.
+    pub(crate) fn start(&self, total: usize) {
+        self.0.done.store(0, Ordering::Release);
+        self.0.total.store(total, Ordering::Release);
+    }
+
+    /// Count one more task in the run, e.g. a finalizer run once the other tasks are done.
+    pub(crate) fn add(&self) {
+        self.0.total.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// A task of the run, which is done once dropped.
+    pub(crate) fn task(&self) -> ProgressTask {
+        ProgressTask(self.clone())
+    }
+}
+
+/// A task counted by [`RunProgress`], which is done once dropped.
+#[derive(Debug)]
+pub(crate) struct ProgressTask(RunProgress);
+
+impl Drop for ProgressTask {
+    fn drop(&mut self) {
+        self.0 .0.done.fetch_add(1, Ordering::AcqRel);
+    }
+}
@@ -0,0 +1,258 @@
+//! Run report
+//!
+//! [`RunReport`] summarizes the execution of a [`Dag`](crate::Dag): whether the whole dag succeeded,
+//! and the final state of every task together with the error message of the failed ones.
+//! A report can be obtained with `Dag::run_report` at any time, and it is passed to the hooks
+//! registered with `Dag::on_complete` once the dag finishes.
+//!
+//! Two reports can be compared with [`RunReport::diff`], e.g. to detect regressions between the
+//! run of yesterday and the one of today.
+//!
+//! # Example
+//!
+//! ```rust
+//! use dagrs::{RunReport, TaskReport, TaskState};
+//!
+//! let report = |state| RunReport {
+//!     success: state == TaskState::Succeeded,
+//!     tasks: vec![TaskReport {
+//!         name: "build".to_string(),
+//!         state,
+//!         error: None,
+//!         duration: None,
+//!         resources: None,
+//!     }],
+//!     groups: Vec::new(),
+//! };
+//! let diff = report(TaskState::Succeeded).diff(&report(TaskState::Failed));
+//! assert_eq!(diff.state_changes.len(), 1);
+//! println!("{}", diff);
+//! ```
+
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{task::TaskResourceUsage, TaskState};
+
+/// The summary of a dag run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Whether every task of the dag completed without error.
+    pub success: bool,
+    /// The report of each task, sorted by task name.
+    pub tasks: Vec<TaskReport>,
+    /// The report of each task group, sorted by group name.
+    #[serde(default)]
+    pub groups: Vec<GroupReport>,
+}
+
+/// The summary of the execution of a single task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskReport {
+    /// The task's name.
+    pub name: String,
+    /// The final state of the task.
+    pub state: TaskState,
+    /// The error message, if the task failed.
+    pub error: Option<String>,
+    /// How long the action of the task ran, if it has run.
+    #[serde(default)]
+    pub duration: Option<Duration>,
+    /// The resources used by the processes the task spawned, if it spawned any and they
+    /// could be measured, see `Dag::get_task_resources`.
+    #[serde(default)]
+    pub resources: Option<TaskResourceUsage>,
+}
+
+/// The summary of the execution of a task group, see [`TaskGroup`](crate::TaskGroup).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupReport {
+    /// The group's name.
+    pub name: String,
+    /// The state of the group as a whole: failed if a member failed, else running or pending
+    /// if a member is, else skipped if all members were skipped, else succeeded, or failed but
+    /// allowed if a member did.
+    pub state: TaskState,
+    /// Whether the group was cancelled.
+    pub cancelled: bool,
+    /// The names of the members, sorted.
+    pub tasks: Vec<String>,
+}
+
+impl GroupReport {
+    /// Summarize the group from the reports of its members.
+    pub(crate) fn new(name: &str, cancelled: bool, members: &[&TaskReport]) -> Self {
+        let any = |state| members.iter().any(|task| task.state == state);
+        let state = if any(TaskState::Failed) {
+            TaskState::Failed
+        } else if any(TaskState::Running) {
+            TaskState::Running
+        } else if any(TaskState::Pending) {
+            TaskState::Pending
+        } else if members.iter().all(|task| task.state == TaskState::Skipped) {
+            TaskState::Skipped
+        } else if any(TaskState::FailedAllowed) {
+            TaskState::FailedAllowed
+        } else {
+            TaskState::Succeeded
+        };
+        Self {
+            name: name.to_owned(),
+            state,
+            cancelled,
+            tasks: members.iter().map(|task| task.name.clone()).collect(),
+        }
+    }
+}
+
+/// The differences between two run reports, from a former run to a latter one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunDiff {
+    /// The tasks whose final state differs, sorted by task name.
+    pub state_changes: Vec<StateChange>,
+    /// The tasks that ran slower beyond the threshold, sorted by task name.
+    pub regressions: Vec<DurationChange>,
+    /// The tasks only found in the latter run.
+    pub added: Vec<String>,
+    /// The tasks only found in the former run.
+    pub removed: Vec<String>,
+}
+
+/// A task whose final state differs between two runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateChange {
+    /// The task's name.
+    pub name: String,
+    /// The state in the former run.
+    pub before: TaskState,
+    /// The state in the latter run.
+    pub after: TaskState,
+}
+
+/// A task whose duration differs between two runs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DurationChange {
+    /// The task's name.
+    pub name: String,
+    /// The duration in the former run.
+    pub before: Duration,
+    /// The duration in the latter run.
+    pub after: Duration,
+}
+
+impl RunReport {
+    /// Find the report of the task with the given name.
+    pub fn task(&self, name: &str) -> Option<&TaskReport> {
+        self.tasks.iter().find(|task| task.name == name)
+    }
+
+    /// Find the report of the task group with the given name.
+    pub fn group(&self, name: &str) -> Option<&GroupReport> {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    /// The `n` tasks that ran the longest, with their durations, from the slowest. Tasks of
+    /// equal duration are sorted by name, and tasks that did not run are left out.
+    pub fn slowest_tasks(&self, n: usize) -> Vec<(String, Duration)> {
+        let mut timed: Vec<(String, Duration)> = self
+            .tasks
+            .iter()
+            .filter_map(|task| Some((task.name.clone(), task.duration?)))
+            .collect();
+        timed.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+        timed.truncate(n);
+        timed
+    }
+
+    /// Serialize the report into a JSON string.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Compare this report with the report of a latter run. A task is considered to have
+    /// regressed if it ran more than 20% slower, see [`RunReport::diff_with_threshold`].
+    pub fn diff(&self, other: &RunReport) -> RunDiff {
+        self.diff_with_threshold(other, 0.2)
+    }
+
+    /// Compare this report with the report of a latter run. A task is considered to have
+    /// regressed if its duration grew by more than the fraction `threshold` of its former
+    /// duration. Tasks are matched by name, and tasks without a duration in either run are
+    /// not compared by duration.
+    pub fn diff_with_threshold(&self, other: &RunReport, threshold: f64) -> RunDiff {
+        let mut diff = RunDiff::default();
+        for before in self.tasks.iter() {
+            let after = match other.task(&before.name) {
+                Some(after) => after,
+                None => {
+                    diff.removed.push(before.name.clone());
+                    continue;
+                }
+            };
+            if before.state != after.state {
+                diff.state_changes.push(StateChange {
+                    name: before.name.clone(),
+                    before: before.state,
+                    after: after.state,
+                });
+            }
+            if let (Some(former), Some(latter)) = (before.duration, after.duration) {
+                if latter.as_secs_f64() > former.as_secs_f64() * (1.0 + threshold) {
+                    diff.regressions.push(DurationChange {
+                        name: before.name.clone(),
+                        before: former,
+                        after: latter,
+                    });
+                }
+            }
+        }
+        diff.added = other
+            .tasks
+            .iter()
+            .filter(|after| self.task(&after.name).is_none())
+            .map(|after| after.name.clone())
+            .collect();
+        diff
+    }
+}
+
+impl RunDiff {
+    /// Whether the two runs show no difference.
+    pub fn is_empty(&self) -> bool {
+        self.state_changes.is_empty()
+            && self.regressions.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+    }
+}
+
+/// A human-readable summary of the differences, one line per task.
+impl fmt::Display for RunDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No difference");
+        }
+        for change in self.state_changes.iter() {
+            writeln!(
+                f,
+                "{}: {:?} -> {:?}",
+                change.name, change.before, change.after
+            )?;
+        }
+        for change in self.regressions.iter() {
+            writeln!(
+                f,
+                "{}: slower, {:?} -> {:?}",
+                change.name, change.before, change.after
+            )?;
+        }
+        for name in self.added.iter() {
+            writeln!(f, "{}: added", name)?;
+        }
+        for name in self.removed.iter() {
+            writeln!(f, "{}: removed", name)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+//! Running tasks
+//!
+//! [`RunningTasks`] tracks the tasks of a [`Dag`](crate::Dag) whose action is executing, so that
+//! a dashboard can poll them from another thread while the dag runs. It is obtained with
+//! `Dag::running_tasks_handle` before starting the dag, and [`ReadyTasks`] the tasks ready to
+//! start, obtained with `Dag::ready_tasks_handle`.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// A shared view of the tasks currently executing in a dag.
+///
+/// The set is updated under a lock held only to insert or remove a task, so reading it never
+/// delays the scheduler for long, and each snapshot is consistent: a task either is in it or
+/// is not.
+#[derive(Debug, Clone, Default)]
+pub struct RunningTasks(Arc<Mutex<BTreeMap<usize, String>>>);
+
+impl RunningTasks {
+    /// The names of the tasks executing at the moment of the call, sorted.
+    pub fn snapshot(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.0.lock().unwrap().values().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Record that the action of a task started.
+    pub(crate) fn insert(&self, id: usize, name: &str) {
+        self.0.lock().unwrap().insert(id, name.to_owned());
+    }
+
+    /// Record that the action of a task ended.
+    pub(crate) fn remove(&self, id: usize) {
+        self.0.lock().unwrap().remove(&id);
+    }
+}
+
+/// A shared view of the tasks of a dag whose predecessors are done but which have not started
+/// yet, e.g. because they wait for resources of the budget of the dag. It is obtained with
+/// `Dag::ready_tasks_handle` before starting the dag.
+#[derive(Debug, Clone, Default)]
+pub struct ReadyTasks(RunningTasks);
+
+impl ReadyTasks {
+    /// The names of the tasks ready at the moment of the call, sorted.
+    pub fn snapshot(&self) -> Vec<String> {
+        self.0.snapshot()
+    }
+
+    /// Record that a task is ready, until the returned guard is dropped when it starts or is
+    /// skipped.
+    pub(crate) fn enter(&self, id: usize, name: &str) -> ReadyGuard {
+        self.0.insert(id, name);
+        ReadyGuard {
+            tasks: self.clone(),
+            id,
+        }
+    }
+}
+
+/// A task recorded by [`ReadyTasks`], which is no longer ready once dropped.
+#[derive(Debug)]
+pub(crate) struct ReadyGuard {
+    tasks: ReadyTasks,
+    id: usize,
+}
+
+impl Drop for ReadyGuard {
+    fn drop(&mut self) {
+        self.tasks.0.remove(self.id);
+    }
+}
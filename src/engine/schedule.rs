@@ -0,0 +1,133 @@
+//! Seeded scheduling order of the ready tasks, see `Dag::set_schedule_seed`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::Notify;
+
+/// The SplitMix64 generator: tiny, and good enough to shuffle tasks.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Order the tasks of `sequence` so that each one comes after its predecessors, picking the
+/// next task among the ready ones at random. `precursors` gives the predecessors of each task,
+/// of which only the ones in `sequence` are taken into account.
+///
+/// The order only depends on the seed and on the order the tasks were created in, given by
+/// their ids, not on the order of `sequence`, which varies from one dag to its copies.
+pub(crate) fn seeded_order(
+    sequence: &[usize],
+    precursors: impl Fn(usize) -> Vec<usize>,
+    seed: u64,
+) -> Vec<usize> {
+    let members: HashSet<usize> = sequence.iter().copied().collect();
+    let mut in_degree = HashMap::with_capacity(sequence.len());
+    let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &id in sequence {
+        let pres: Vec<usize> = precursors(id)
+            .into_iter()
+            .filter(|pre| members.contains(pre))
+            .collect();
+        in_degree.insert(id, pres.len());
+        for pre in pres {
+            successors.entry(pre).or_default().push(id);
+        }
+    }
+    let mut rng = SplitMix64(seed);
+    let mut ready: Vec<usize> = sequence
+        .iter()
+        .copied()
+        .filter(|id| in_degree[id] == 0)
+        .collect();
+    ready.sort_unstable();
+    let mut order = Vec::with_capacity(sequence.len());
+    while !ready.is_empty() {
+        let next = ready.remove((rng.next() % ready.len() as u64) as usize);
+        order.push(next);
+        for succ in successors.get(&next).into_iter().flatten() {
+            let degree = in_degree.get_mut(succ).unwrap();
+            *degree -= 1;
+            if *degree == 0 {
+                let at = ready.binary_search(succ).unwrap_or_else(|at| at);
+                ready.insert(at, *succ);
+            }
+        }
+    }
+    order
+}
+
+/// Lets the tasks of a run start one after the other, in the order of their positions.
+#[derive(Debug)]
+pub(crate) struct ScheduleOrder {
+    /// The first position that has not had its turn yet, and which positions had it.
+    state: Mutex<(usize, Vec<bool>)>,
+    /// Notified whenever a position had its turn.
+    passed: Notify,
+}
+
+impl ScheduleOrder {
+    pub(crate) fn new(len: usize) -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new((0, vec![false; len])),
+            passed: Notify::new(),
+        })
+    }
+
+    /// The turn of the task at `position`. It is given up when dropped, if it was not taken.
+    pub(crate) fn turn(self: &Arc<Self>, position: usize) -> Turn {
+        Turn {
+            order: self.clone(),
+            position,
+        }
+    }
+
+    fn pass(&self, position: usize) {
+        let mut state = self.state.lock().unwrap();
+        let (next, passed) = &mut *state;
+        passed[position] = true;
+        while *next < passed.len() && passed[*next] {
+            *next += 1;
+        }
+        drop(state);
+        self.passed.notify_waiters();
+    }
+}
+
+/// The turn of a task to start, see [`ScheduleOrder`].
+#[derive(Debug)]
+pub(crate) struct Turn {
+    order: Arc<ScheduleOrder>,
+    position: usize,
+}
+
+impl Turn {
+    /// Wait until all the tasks at the previous positions started or gave up their turn. The
+    /// turn is passed on to the next position once dropped.
+    pub(crate) async fn take(&self) {
+        loop {
+            // Created before checking, so that no turn passed in between is missed.
+            let passed = self.order.passed.notified();
+            if self.order.state.lock().unwrap().0 == self.position {
+                return;
+            }
+            passed.await;
+        }
+    }
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.order.pass(self.position);
+    }
+}
@@ -0,0 +1,71 @@
+//! Signal handling
+//!
+//! When enabled, SIGINT and SIGTERM (Ctrl-C on Windows) cancel the running dag instead of
+//! terminating the process, so that the commands it spawned can be killed rather than orphaned.
+
+/// Wait until the process receives SIGINT or SIGTERM, or Ctrl-C on Windows.
+pub(crate) async fn wait_for_signal() {
+    Signals::install().recv().await
+}
+
+/// The handlers of the signals that cancel a run.
+///
+/// The handlers are installed by `install` and stay installed for the rest of the life of
+/// the process, so these signals no longer terminate it by default. From then on commands are
+/// started in their own process group, so that the processes they spawn are killed with them.
+/// The signals received after `install` are not lost, even when nobody waits for them yet.
+#[cfg(unix)]
+pub(crate) struct Signals(Option<(tokio::signal::unix::Signal, tokio::signal::unix::Signal)>);
+
+#[cfg(unix)]
+impl Signals {
+    /// Install the handlers. Must be called from within a tokio runtime.
+    pub(crate) fn install() -> Self {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match (
+            signal(SignalKind::interrupt()),
+            signal(SignalKind::terminate()),
+        ) {
+            (Ok(interrupt), Ok(terminate)) => {
+                crate::task::isolate_commands();
+                Self(Some((interrupt, terminate)))
+            }
+            _ => {
+                log::error!("Failed to install the signal handlers");
+                Self(None)
+            }
+        }
+    }
+
+    /// Wait until the process receives SIGINT or SIGTERM.
+    pub(crate) async fn recv(&mut self) {
+        match &mut self.0 {
+            Some((interrupt, terminate)) => tokio::select! {
+                _ = interrupt.recv() => {},
+                _ = terminate.recv() => {},
+            },
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// The handler of Ctrl-C.
+#[cfg(not(unix))]
+pub(crate) struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    /// Install the handler. Must be called from within a tokio runtime.
+    pub(crate) fn install() -> Self {
+        Self
+    }
+
+    /// Wait until the process receives Ctrl-C.
+    pub(crate) async fn recv(&mut self) {
+        if tokio::signal::ctrl_c().await.is_err() {
+            log::error!("Failed to install the signal handler");
+            std::future::pending::<()>().await;
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Scheduler statistics
+//!
+//! [`SchedulerStats`] tells how many tasks of a [`Dag`](crate::Dag) were waiting for their
+//! predecessors, ready but waiting to start, and running, over the course of a run. It is
+//! recorded when enabled with `Dag::set_record_scheduler_stats`, and obtained with
+//! `Dag::scheduler_stats` after the run.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The numbers of tasks in each phase of the scheduling at some point of a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedulerSample {
+    /// The time since the run started.
+    pub at: Duration,
+    /// The tasks waiting for their predecessors to finish.
+    pub pending: usize,
+    /// The tasks whose predecessors finished, waiting to start, e.g. for resources of the
+    /// budget of the dag or for their turn in a seeded schedule.
+    pub ready: usize,
+    /// The tasks whose action is running.
+    pub running: usize,
+}
+
+/// The numbers of tasks in each phase of the scheduling over the course of a run, sampled
+/// whenever a task moves from one phase to the next.
+///
+/// Many ready tasks mean that the tasks wait for the limits on concurrency, which may be
+/// raised, while many pending tasks with few running ones mean that the dependencies between
+/// the tasks are the bottleneck.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchedulerStats {
+    samples: Vec<SchedulerSample>,
+}
+
+impl SchedulerStats {
+    /// The samples, in the order they were taken.
+    pub fn samples(&self) -> &[SchedulerSample] {
+        &self.samples
+    }
+
+    /// The largest number of tasks that were ready but waiting to start at the same time.
+    pub fn peak_ready(&self) -> usize {
+        self.samples.iter().map(|s| s.ready).max().unwrap_or(0)
+    }
+
+    /// The largest number of tasks that were running at the same time.
+    pub fn peak_running(&self) -> usize {
+        self.samples.iter().map(|s| s.running).max().unwrap_or(0)
+    }
+}
+
+/// A phase of the scheduling of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Phase {
+    Pending,
+    Ready,
+    Running,
+    Done,
+}
+
+#[derive(Debug)]
+struct Recording {
+    started: Instant,
+    current: SchedulerSample,
+    samples: Vec<SchedulerSample>,
+}
+
+/// Records the samples of the current run.
+#[derive(Debug)]
+pub(crate) struct SchedulerRecorder(Mutex<Recording>);
+
+impl SchedulerRecorder {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self(Mutex::new(Recording {
+            started: Instant::now(),
+            current: SchedulerSample {
+                at: Duration::ZERO,
+                pending: 0,
+                ready: 0,
+                running: 0,
+            },
+            samples: Vec::new(),
+        })))
+    }
+
+    /// Start recording a run of `tasks` tasks, all pending, dropping the previous samples.
+    pub(crate) fn start(&self, tasks: usize) {
+        let mut recording = self.0.lock().unwrap();
+        recording.started = Instant::now();
+        recording.current = SchedulerSample {
+            at: Duration::ZERO,
+            pending: tasks,
+            ready: 0,
+            running: 0,
+        };
+        recording.samples = vec![recording.current];
+    }
+
+    /// Follow a pending task through its phases.
+    pub(crate) fn track(self: &Arc<Self>) -> TrackedTask {
+        TrackedTask {
+            recorder: self.clone(),
+            phase: Phase::Pending,
+        }
+    }
+
+    pub(crate) fn stats(&self) -> SchedulerStats {
+        SchedulerStats {
+            samples: self.0.lock().unwrap().samples.clone(),
+        }
+    }
+
+    fn transition(&self, from: Phase, to: Phase) {
+        let mut recording = self.0.lock().unwrap();
+        let at = recording.started.elapsed();
+        let current = &mut recording.current;
+        for (phase, delta) in [(from, -1), (to, 1)] {
+            let count = match phase {
+                Phase::Pending => &mut current.pending,
+                Phase::Ready => &mut current.ready,
+                Phase::Running => &mut current.running,
+                Phase::Done => continue,
+            };
+            *count = count.saturating_add_signed(delta);
+        }
+        current.at = at;
+        let sample = *current;
+        recording.samples.push(sample);
+    }
+}
+
+/// A task followed by a [`SchedulerRecorder`]. The task is done once dropped.
+#[derive(Debug)]
+pub(crate) struct TrackedTask {
+    recorder: Arc<SchedulerRecorder>,
+    phase: Phase,
+}
+
+impl TrackedTask {
+    pub(crate) fn enter(&mut self, phase: Phase) {
+        if phase != self.phase {
+            self.recorder.transition(self.phase, phase);
+            self.phase = phase;
+        }
+    }
+}
+
+impl Drop for TrackedTask {
+    fn drop(&mut self) {
+        self.enter(Phase::Done);
+    }
+}
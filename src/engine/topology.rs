@@ -0,0 +1,178 @@
+//! Dag topology
+//!
+//! [`Topology`] is the structure of a [`Dag`](crate::Dag) in terms of task names: its tasks
+//! and the dependencies between them, independent of the ids of the tasks and of the order
+//! they were added in. It is obtained with `Dag::topology`, and summed up by a stable
+//! fingerprint, so that tests can check that a pipeline generator still builds the expected
+//! graph, and show what changed with [`Topology::diff`] when it does not.
+
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+/// The tasks of a dag and the dependencies between them, by name.
+///
+/// Tasks are identified by name only, so two tasks with the same name are listed twice, and
+/// the dependencies from or to either of them are not told apart.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Topology {
+    /// The names of the tasks, sorted.
+    pub tasks: Vec<String>,
+    /// The dependencies as `(predecessor, successor)` pairs of task names, sorted.
+    pub edges: Vec<(String, String)>,
+}
+
+/// The differences between two topologies, from an expected one to an actual one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopologyDiff {
+    /// The tasks only found in the actual topology, sorted.
+    pub added_tasks: Vec<String>,
+    /// The tasks only found in the expected topology, sorted.
+    pub removed_tasks: Vec<String>,
+    /// The dependencies only found in the actual topology, sorted.
+    pub added_edges: Vec<(String, String)>,
+    /// The dependencies only found in the expected topology, sorted.
+    pub removed_edges: Vec<(String, String)>,
+}
+
+impl Topology {
+    /// Build a topology from task names and `(predecessor, successor)` pairs of task names,
+    /// e.g. the expected structure of a dag, in any order.
+    pub fn new<S: Into<String>>(
+        tasks: impl IntoIterator<Item = S>,
+        edges: impl IntoIterator<Item = (S, S)>,
+    ) -> Self {
+        let mut tasks: Vec<String> = tasks.into_iter().map(Into::into).collect();
+        let mut edges: Vec<(String, String)> = edges
+            .into_iter()
+            .map(|(pre, post)| (pre.into(), post.into()))
+            .collect();
+        tasks.sort();
+        edges.sort();
+        Self { tasks, edges }
+    }
+
+    /// A stable hash of the topology, as 16 hexadecimal digits. Two topologies have the same
+    /// fingerprint if they are equal, and the fingerprint does not change between runs,
+    /// platforms or versions of dagrs, so it may be kept as a golden value.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = Fnv1a::default();
+        hasher.write_usize(self.tasks.len());
+        for task in self.tasks.iter() {
+            hasher.write_str(task);
+        }
+        hasher.write_usize(self.edges.len());
+        for (pre, post) in self.edges.iter() {
+            hasher.write_str(pre);
+            hasher.write_str(post);
+        }
+        format!("{:016x}", hasher.0)
+    }
+
+    /// Compare this expected topology with an actual one.
+    pub fn diff(&self, actual: &Topology) -> TopologyDiff {
+        TopologyDiff {
+            added_tasks: difference(&actual.tasks, &self.tasks),
+            removed_tasks: difference(&self.tasks, &actual.tasks),
+            added_edges: difference(&actual.edges, &self.edges),
+            removed_edges: difference(&self.edges, &actual.edges),
+        }
+    }
+}
+
+/// The topology as text, one line per task and then one line per dependency.
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in self.tasks.iter() {
+            writeln!(f, "task {}", name)?;
+        }
+        for (pre, post) in self.edges.iter() {
+            writeln!(f, "edge {} -> {}", pre, post)?;
+        }
+        Ok(())
+    }
+}
+
+impl TopologyDiff {
+    /// Whether the two topologies are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added_tasks.is_empty()
+            && self.removed_tasks.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+/// A human-readable summary of the differences, one line per task or dependency, prefixed
+/// with `+` if it was added and `-` if it was removed.
+impl fmt::Display for TopologyDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No difference");
+        }
+        for name in self.added_tasks.iter() {
+            writeln!(f, "+ task {}", name)?;
+        }
+        for name in self.removed_tasks.iter() {
+            writeln!(f, "- task {}", name)?;
+        }
+        for (pre, post) in self.added_edges.iter() {
+            writeln!(f, "+ edge {} -> {}", pre, post)?;
+        }
+        for (pre, post) in self.removed_edges.iter() {
+            writeln!(f, "- edge {} -> {}", pre, post)?;
+        }
+        Ok(())
+    }
+}
+
+/// The items of `from` that are not in `other`, sorted, counting repeated items as many
+/// times as they are repeated.
+fn difference<T: Ord + Clone>(from: &[T], other: &[T]) -> Vec<T> {
+    let mut counts: BTreeMap<&T, usize> = BTreeMap::new();
+    for item in other {
+        *counts.entry(item).or_default() += 1;
+    }
+    let mut missing: Vec<T> = from
+        .iter()
+        .filter(|item| match counts.get_mut(item) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .cloned()
+        .collect();
+    missing.sort();
+    missing
+}
+
+/// The 64-bit FNV-1a hash, whose values are fixed by its specification unlike those of the
+/// hashers of the standard library.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write(&(value as u64).to_le_bytes());
+    }
+
+    /// Write the length of the string before it, so that names cannot run into each other.
+    fn write_str(&mut self, value: &str) {
+        self.write_usize(value.len());
+        self.write(value.as_bytes());
+    }
+}
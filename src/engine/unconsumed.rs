@@ -0,0 +1,52 @@
+//! Unconsumed outputs
+//!
+//! The outputs of the tasks without successors flow nowhere: [`UnconsumedOutput`] decides
+//! whether they are kept once the tasks finish, see `Dag::set_unconsumed_output_policy`.
+
+use log::info;
+
+use crate::{EnvVar, Output};
+
+/// What happens to the output of a task that no task consumes, because it has no successor.
+///
+/// The output that is the result of the dag, see `Dag::get_result`, and the values passed to
+/// [`Output::finish`] are always kept, whatever the policy. Either way, the output is recorded
+/// in the event log of the dag beforehand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnconsumedOutput {
+    /// Keep the output, so that it can be read after the run with `Dag::get_results`.
+    #[default]
+    Keep,
+    /// Drop the output as soon as the task finishes, to save memory.
+    Discard,
+    /// Log the output at the info level, in JSON if it can be represented in JSON and by the
+    /// name of its type otherwise, then drop it like [`UnconsumedOutput::Discard`].
+    Log,
+}
+
+impl UnconsumedOutput {
+    /// Apply the policy to the output of the given task, which is not consumed.
+    pub(crate) fn apply(self, task: &str, out: Output, env: &EnvVar) -> Output {
+        if self == UnconsumedOutput::Keep || out.is_finish() {
+            return out;
+        }
+        if self == UnconsumedOutput::Log {
+            if let Some(content) = out.get_out() {
+                // A lazy output is not computed only to be logged.
+                let json = match content.is_pending() {
+                    true => None,
+                    false => content.to_json(),
+                };
+                let value = match json {
+                    Some(value) => env.redact(&value.to_string()),
+                    None => format!("a value of type {}", content.type_name()),
+                };
+                info!(
+                    "Task [name: {}] output {}, which no task consumes",
+                    task, value
+                );
+            }
+        }
+        Output::empty()
+    }
+}
@@ -8,16 +8,28 @@ extern crate yaml_rust;
 
 #[cfg(feature = "derive")]
 pub use derive::*;
-pub use engine::{Dag, DagError, Engine};
+pub use engine::{
+    Dag, DagError, DeadlineReport, DurationChange, Engine, GanttBar, GroupCanceller, GroupReport,
+    NameCollision, ReadyTasks, RunDiff, RunEvent, RunEventKind, RunProgress, RunReport,
+    RunningTasks, SchedulerSample, SchedulerStats, StateChange, TaskMiddleware, TaskReport,
+    Topology, TopologyDiff, TypeChange, UnconsumedOutput, WebhookNotifier,
+};
+#[cfg(feature = "wasm")]
+pub use task::WasmAction;
 pub use task::{
-    alloc_id, Action, CommandAction, Complex, DefaultTask, Input, Output, Simple, Task,
+    alloc_id, Action, AssertAction, CommandAction, Complex, DataType, DefaultTask, Gate,
+    GateAction, Input, Output, OutputFormat, ResourceRequest, RetryPolicy, RunningError,
+    ScriptAction, Simple, StreamFlush, SuccessCheck, Task, TaskGroup, TaskLabels,
+    TaskResourceUsage, TaskState, ThreadAffinity,
 };
 pub use utils::{EnvVar, ParseError, Parser};
 #[cfg(feature = "yaml")]
-pub use yaml::{FileContentError, FileNotFound, YamlParser, YamlTask, YamlTaskError};
+pub use yaml::{FileContentError, FileNotFound, TaskConfig, YamlParser, YamlTask, YamlTaskError};
 
 pub mod engine;
 pub mod task;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 pub mod utils;
 #[cfg(feature = "yaml")]
 pub mod yaml;
@@ -1,5 +1,5 @@
-use crate::{EnvVar, Input, Output};
-use std::sync::Arc;
+use crate::{AssertAction, EnvVar, Input, Output};
+use std::{fmt::Debug, sync::Arc};
 
 /// The type of closure that performs logic.
 /// # [`Simple`]
@@ -52,6 +52,13 @@ pub type Simple = dyn Fn(Input, Arc<EnvVar>) -> Output + Send + Sync;
 /// ```
 pub trait Complex {
     fn run(&self, input: Input, env: Arc<EnvVar>) -> Output;
+
+    /// The attributes describing this action in a yaml configuration file, such as
+    /// `("cmd", json!("echo a"))`, or None if it cannot be written to one. The values are
+    /// written with their types, e.g. `json!(false)` as a yaml boolean.
+    fn config(&self) -> Option<Vec<(&'static str, serde_json::Value)>> {
+        None
+    }
 }
 
 /// Task specific behavior
@@ -69,6 +76,24 @@ pub enum Action {
 }
 
 impl Action {
+    /// An action asserting that `predicate` holds for the outputs of the predecessor tasks,
+    /// see [`AssertAction`].
+    pub fn assert<T: Debug + 'static>(
+        predicate: impl Fn(&T) -> bool + Send + Sync + 'static,
+        message: &str,
+    ) -> Self {
+        Self::Structure(Arc::new(AssertAction::new(predicate, message)))
+    }
+
+    /// The attributes describing this action in a yaml configuration file, see
+    /// [`Complex::config`]. Closures cannot be described.
+    pub fn config(&self) -> Option<Vec<(&'static str, serde_json::Value)>> {
+        match self {
+            Self::Closure(_) => None,
+            Self::Structure(structure) => structure.config(),
+        }
+    }
+
     pub fn run(&self, input: Input, env: Arc<EnvVar>) -> Output {
         match self {
             Self::Closure(closure) => closure(input, env),
@@ -0,0 +1,63 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Sender},
+        Mutex, OnceLock,
+    },
+    thread,
+};
+
+/// The thread the action of a task runs on, see `Task::thread_affinity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThreadAffinity {
+    /// Any thread of the blocking thread pool of the runtime.
+    #[default]
+    Any,
+    /// The main thread of the engine: a single dedicated thread, the same for every task and
+    /// every dag of the process for as long as it runs, for the actions bound to libraries
+    /// that must always be called from the same thread.
+    ///
+    /// The actions of such tasks are serialized: they run one at a time, in the order the
+    /// tasks become ready to run, while the other tasks keep running on the thread pool.
+    Main,
+}
+
+/// The type of the jobs run on the main thread.
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The sender of the jobs to the main thread, which is started by the first job.
+static MAIN_THREAD: OnceLock<Mutex<Sender<Job>>> = OnceLock::new();
+
+/// Run `job` on the thread given by `affinity`, blocking the current thread until it returns,
+/// and resume its panic if it panics.
+pub(crate) fn run_with_affinity<R: Send + 'static>(
+    affinity: ThreadAffinity,
+    job: impl FnOnce() -> R + Send + 'static,
+) -> R {
+    if affinity == ThreadAffinity::Any {
+        return job();
+    }
+    let sender = MAIN_THREAD.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("dagrs-main".to_owned())
+            .spawn(move || {
+                for job in receiver {
+                    job();
+                }
+            })
+            .expect("failed to spawn the main thread of the engine");
+        Mutex::new(sender)
+    });
+    let (result, received) = mpsc::sync_channel(1);
+    // The panics are caught so that the thread survives them.
+    let job: Job = Box::new(move || {
+        let out = panic::catch_unwind(AssertUnwindSafe(job));
+        let _ = result.send(out);
+    });
+    sender.lock().unwrap().send(job).unwrap();
+    match received.recv().unwrap() {
+        Ok(out) => out,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{Complex, EnvVar, Input, Output};
+use std::{fmt::Debug, sync::Arc};
+
+/// [`AssertAction`] is a specific implementation of [`Complex`], used to check the outputs of
+/// the predecessor tasks.
+///
+/// The predicate is evaluated on every input of the task. If it does not hold for one of
+/// them, or an input is not of type `T`, the action fails with the message followed by the
+/// value. Otherwise the input is passed through when there is exactly one, so that the
+/// assertion can be inserted between two tasks, and the output is empty when there are more.
+/// Whether a failed assertion aborts the dag depends on the dag, like any failure.
+///
+/// # Example
+///
+/// ```rust
+/// use dagrs::{AssertAction, Dag, DefaultTask, Output};
+///
+/// let count = DefaultTask::with_closure("Count", |_, _| Output::new(0usize));
+/// let assertion = AssertAction::new(|count: &usize| *count > 0, "no items");
+/// let mut check = DefaultTask::with_action("Check", assertion);
+/// check.set_predecessors(&[&count]);
+/// assert!(!Dag::with_tasks(vec![count, check]).start().unwrap());
+/// ```
+pub struct AssertAction<T> {
+    predicate: Box<dyn Fn(&T) -> bool + Send + Sync>,
+    message: String,
+}
+
+impl<T> AssertAction<T> {
+    /// Assert that `predicate` holds for the inputs, failing with `message` otherwise.
+    pub fn new(predicate: impl Fn(&T) -> bool + Send + Sync + 'static, message: &str) -> Self {
+        Self {
+            predicate: Box::new(predicate),
+            message: message.to_owned(),
+        }
+    }
+}
+
+impl<T: Debug + 'static> Complex for AssertAction<T> {
+    fn run(&self, input: Input, _env: Arc<EnvVar>) -> Output {
+        let mut count = 0;
+        for content in input.get_iter() {
+            count += 1;
+            match content.get::<T>() {
+                Some(value) if (self.predicate)(value) => {}
+                Some(value) => return Output::error(format!("{}: {:?}", self.message, value)),
+                None => {
+                    return Output::error(format!(
+                        "{}: the input is not of type {}",
+                        self.message,
+                        std::any::type_name::<T>()
+                    ))
+                }
+            }
+        }
+        match count {
+            0 => Output::error(format!("{}: there is no input", self.message)),
+            1 => Output::Out(input.get_iter().next().cloned()),
+            _ => Output::empty(),
+        }
+    }
+}
@@ -1,12 +1,294 @@
 use crate::{Complex, EnvVar, Input, Output};
-use std::process::Command;
-use std::sync::Arc;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{self, Child, Command, Stdio};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread::JoinHandle;
 
-use crate::task::Content;
+use crate::task::{Content, DataType};
+
+use super::resource_usage::{record_usage, TaskResourceUsage};
+
+use super::stream::{OutputStream, StreamFlush};
+use thiserror::Error;
+
+/// The errors of the commands and scripts that could not run, as opposed to the ones that ran
+/// and failed. They are held by the output of the failed action, see [`Output::running_error`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RunningError {
+    /// The program, or the interpreter of a script, is not installed or not on the `PATH`.
+    /// Its exit code is the error code of the operating system, or 127 when the shell reported
+    /// it.
+    #[error("the program {0:?} was not found, is it installed and on the PATH?")]
+    InterpreterNotFound(String),
+}
+
+/// The process ids of the commands that are currently running, so that they can be killed when
+/// a dag is cancelled, along with whether they lead their own process group.
+static RUNNING: Mutex<Vec<(u32, bool)>> = Mutex::new(Vec::new());
+
+/// Whether new commands are started in their own process group.
+static ISOLATED: AtomicBool = AtomicBool::new(false);
+
+/// Start the commands in their own process group from now on, so that killing a command also
+/// kills the processes it spawned. They then no longer receive the signals sent by the
+/// terminal, which is only desirable once the signals are handled by the process.
+pub(crate) fn isolate_commands() {
+    ISOLATED.store(true, Ordering::Release);
+}
+
+/// Forget a command that exited, before it is reaped: until then its process id cannot be
+/// reused, so [`kill_running_commands`] never kills another process.
+fn forget_command(pid: u32) {
+    RUNNING
+        .lock()
+        .unwrap()
+        .retain(|(running, _)| *running != pid);
+}
+
+/// Kill all the commands that are currently running.
+pub(crate) fn kill_running_commands() {
+    for (pid, group) in RUNNING.lock().unwrap().drain(..) {
+        log::debug!("Killing command [pid: {}]", pid);
+        kill(pid, group);
+    }
+}
+
+#[cfg(unix)]
+fn kill(pid: u32, group: bool) {
+    let pid = if group {
+        -(pid as libc::pid_t)
+    } else {
+        pid as libc::pid_t
+    };
+    // SAFETY: sending a signal has no memory safety requirements.
+    unsafe {
+        libc::kill(pid, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill(pid: u32, _group: bool) {
+    let _ = Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .output();
+}
+
+/// Read a pipe until its end on its own thread, `buffer` bytes at a time if given.
+fn read_all(mut pipe: impl Read + Send + 'static, buffer: Option<usize>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut read = Vec::new();
+        let size = match buffer {
+            Some(size) => size.max(1),
+            None => {
+                let _ = pipe.read_to_end(&mut read);
+                return read;
+            }
+        };
+        let mut chunk = vec![0; size];
+        loop {
+            match pipe.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => read.extend_from_slice(&chunk[..n]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+        read
+    })
+}
+
+/// The bytes read from a pipe by [`read_all`].
+fn join(pipe: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    pipe.map(|pipe| pipe.join().unwrap_or_default())
+        .unwrap_or_default()
+}
+
+/// Wait for the child to exit and collect its output, like `Child::wait_with_output`, but
+/// record the resources it used, and forget the child once it exited. `streamed` reads its
+/// standard output, if it is streamed, and the pipes are otherwise read `buffer` bytes at a
+/// time if given.
+#[cfg(unix)]
+fn wait_with_usage(
+    mut child: Child,
+    streamed: Option<JoinHandle<Vec<u8>>>,
+    buffer: Option<usize>,
+) -> io::Result<process::Output> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| read_all(pipe, buffer))
+        .or(streamed);
+    let stderr = child.stderr.take().map(|pipe| read_all(pipe, buffer));
+    let pid = child.id() as libc::pid_t;
+    // Wait for the child to exit without reaping it, so that it can be forgotten while its
+    // process id is still reserved.
+    loop {
+        // SAFETY: siginfo_t is a plain C struct, for which zeroes are valid.
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        // SAFETY: the pointer is valid for the duration of the call.
+        let waited = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                pid as libc::id_t,
+                &mut info,
+                libc::WEXITED | libc::WNOWAIT,
+            )
+        };
+        if waited == 0 {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            forget_command(pid as u32);
+            return Err(err);
+        }
+    }
+    forget_command(pid as u32);
+    let mut status = 0;
+    // SAFETY: rusage is a plain C struct, for which zeroes are valid.
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: the pointers are valid for the duration of the call, and the child is not
+        // waited for anywhere else.
+        if unsafe { libc::wait4(pid, &mut status, 0, &mut usage) } == pid {
+            break;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+    let time = |time: libc::timeval| {
+        std::time::Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+    };
+    // The peak resident set size is given in kilobytes on Linux, and in bytes on macOS.
+    let rss_unit = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    record_usage(TaskResourceUsage {
+        cpu_time: time(usage.ru_utime) + time(usage.ru_stime),
+        max_rss: usage.ru_maxrss as u64 * rss_unit,
+    });
+    Ok(process::Output {
+        status: process::ExitStatus::from_raw(status),
+        stdout: join(stdout),
+        stderr: join(stderr),
+    })
+}
+
+/// Wait for the child to exit and collect its output, and forget the child once it exited.
+/// The resources it used are not measured on this platform.
+#[cfg(not(unix))]
+fn wait_with_usage(
+    mut child: Child,
+    streamed: Option<JoinHandle<Vec<u8>>>,
+    buffer: Option<usize>,
+) -> io::Result<process::Output> {
+    let stdout = child
+        .stdout
+        .take()
+        .map(|pipe| read_all(pipe, buffer))
+        .or(streamed);
+    let stderr = child.stderr.take().map(|pipe| read_all(pipe, buffer));
+    // The child is still owned, so its process id is not reused before it is forgotten.
+    let status = child.wait();
+    forget_command(child.id());
+    let status = status?;
+    Ok(process::Output {
+        status,
+        stdout: join(stdout),
+        stderr: join(stderr),
+    })
+}
+
+/// How the standard output of a successful command is turned into the output of the action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// The lines of the standard output and standard error, as a `(Vec<String>, Vec<String>)`.
+    #[default]
+    Lines,
+    /// The standard output parsed as an `i64`.
+    Int,
+    /// The standard output parsed as an `f64`.
+    Float,
+    /// The standard output parsed as a [`serde_json::Value`].
+    Json,
+}
+
+impl OutputFormat {
+    /// The type of the output produced in this format.
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Self::Lines => DataType::of::<Content>(),
+            Self::Int => DataType::of::<i64>(),
+            Self::Float => DataType::of::<f64>(),
+            Self::Json => DataType::of::<serde_json::Value>(),
+        }
+    }
+
+    /// The value of the `output_type` attribute selecting this format in a yaml configuration
+    /// file, or None for the default format.
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Self::Lines => None,
+            Self::Int => Some("int"),
+            Self::Float => Some("float"),
+            Self::Json => Some("json"),
+        }
+    }
+
+    /// Parse the standard output, ignoring the surrounding whitespace.
+    fn parse(&self, stdout: &[String]) -> Output {
+        let text = stdout.join("\n");
+        let text = text.trim();
+        let parsed = match self {
+            Self::Lines => unreachable!(),
+            Self::Int => text
+                .parse::<i64>()
+                .map(Output::new)
+                .map_err(|e| e.to_string()),
+            Self::Float => text
+                .parse::<f64>()
+                .map(Output::new)
+                .map_err(|e| e.to_string()),
+            Self::Json => serde_json::from_str::<serde_json::Value>(text)
+                .map(Output::new)
+                .map_err(|e| e.to_string()),
+        };
+        parsed.unwrap_or_else(|err| {
+            Output::error(format!(
+                "failed to parse the output {:?} as {:?}: {}",
+                text, self, err
+            ))
+        })
+    }
+}
 
 /// [`CommandAction`] is a specific implementation of [`Complex`], used to execute operating system commands.
+///
+/// By default the command line is run by the system shell, `sh -c` or `powershell -Command`,
+/// so it may use pipes, redirections and variables. With `set_shell(false)`, it is instead split
+/// into a program and its arguments following the quoting rules of the shell, and the program
+/// is run directly: `echo "hello world"` then gives `hello world` to `echo` as one argument,
+/// whatever the platform, and no other character is interpreted.
+///
+/// The command succeeds if it exits with one of its success codes, only 0 by default, and its
+/// output holds the code it exited with, see [`Output::exit_code`].
+///
+/// The lines of its standard output can also be followed while it runs, with `set_stream`.
 pub struct CommandAction {
     command: String,
+    format: OutputFormat,
+    shell: bool,
+    success_codes: Vec<i32>,
+    nice: Option<i32>,
+    capture_buffer: Option<usize>,
+    stream: Option<OutputStream>,
+    stream_flush: StreamFlush,
 }
 
 impl CommandAction {
@@ -14,59 +296,271 @@ impl CommandAction {
     pub fn new(cmd: &str) -> Self {
         Self {
             command: cmd.to_owned(),
+            format: OutputFormat::Lines,
+            shell: true,
+            success_codes: vec![0],
+            nice: None,
+            capture_buffer: None,
+            stream: None,
+            stream_flush: StreamFlush::Lines,
         }
     }
+
+    /// Whether the command line is run by the system shell, true by default. Otherwise it is
+    /// split into words and run directly, see [`CommandAction`].
+    pub fn set_shell(&mut self, shell: bool) {
+        self.shell = shell;
+    }
+
+    /// Set how the standard output of the command is turned into the output of the action.
+    /// If it cannot be parsed in the given format, the action fails.
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.format = format;
+    }
+
+    /// Set the exit codes for which the command succeeds, `[0]` by default, e.g. `[0, 1]` for
+    /// `grep`, which exits with 1 when nothing matches. The command fails with any other code.
+    pub fn set_success_codes(&mut self, codes: &[i32]) {
+        self.success_codes = codes.to_vec();
+    }
+
+    /// Run the command at the given nice value, from -20 for the highest priority to 19 for
+    /// the lowest, instead of the priority of the current process, e.g. so that background
+    /// tasks do not slow down interactive work.
+    ///
+    /// This is only supported on Unix, and does nothing on the other platforms. Raising the
+    /// priority above the one of the current process usually requires privileges: without
+    /// them, the command fails to start.
+    pub fn set_nice(&mut self, level: i32) {
+        self.nice = Some(level);
+    }
+
+    /// Read the standard output and standard error of the command `bytes` at a time, instead
+    /// of letting the buffers grow with the output, e.g. small buffers for commands that print
+    /// little and large ones for commands that print a lot. The output is the same whatever
+    /// the size, which only affects the number of reads. A size of 0 is taken as 1.
+    pub fn set_capture_buffer(&mut self, bytes: usize) {
+        self.capture_buffer = Some(bytes);
+    }
+
+    /// Pass the lines of the standard output of the command to `observer` while it runs, e.g.
+    /// to forward them to a log. The lines are still part of the output of the action.
+    pub fn set_stream(&mut self, observer: impl Fn(&[String]) + Send + Sync + 'static) {
+        self.stream = Some(OutputStream::new(observer));
+    }
+
+    /// Set how often the streamed lines are passed on, every line by default. Batching them
+    /// saves calls to the observer when the command writes many lines.
+    pub fn set_stream_flush(&mut self, flush: StreamFlush) {
+        self.stream_flush = flush;
+    }
 }
 
 impl Complex for CommandAction {
-    fn run(&self, input: Input, _env: Arc<EnvVar>) -> Output {
+    fn run(&self, input: Input, env: Arc<EnvVar>) -> Output {
         let mut args = Vec::new();
-        let mut cmd = if cfg!(target_os = "windows") {
+        let words;
+        let mut cmd = if !self.shell {
+            words = match shell_words::split(&self.command) {
+                Ok(words) if !words.is_empty() => words,
+                Ok(_) => return Output::error("the command is empty".to_string()),
+                Err(err) => {
+                    return Output::error(format!(
+                        "failed to split the command {:?}: {}",
+                        self.command, err
+                    ))
+                }
+            };
+            args.extend(words[1..].iter().map(String::as_str));
+            Command::new(&words[0])
+        } else if cfg!(target_os = "windows") {
             args.push("-Command");
+            args.push(&self.command);
             Command::new("powershell")
         } else {
             args.push("-c");
+            args.push(&self.command);
             Command::new("sh")
         };
-        args.push(&self.command);
 
         input.get_iter().for_each(|input| {
             if let Some(inp) = input.get::<String>() {
                 args.push(inp)
+            } else if let Some(path) = input.path().and_then(Path::to_str) {
+                args.push(path)
             }
         });
 
-        log::debug!("cmd: {:?}, args: {:?}", cmd.get_program(), args);
-        let (code, out) = match cmd.args(args).output() {
-            Ok(o) => (0, o),
-            Err(e) => {
-                return Output::error_with_exit_code(
-                    e.raw_os_error(),
-                    Some(Content::new(e.to_string())),
-                )
-            }
-        };
-        let stdout: Vec<String> = {
-            let out = String::from_utf8(out.stdout).unwrap_or("".to_string());
-            if cfg!(target_os = "windows") {
-                out.rsplit_terminator("\r\n").map(str::to_string).collect()
-            } else {
-                out.split_terminator('\n').map(str::to_string).collect()
-            }
-        };
-        let stderr: Vec<String> = {
-            let out = String::from_utf8(out.stderr).unwrap_or("".to_string());
-            if cfg!(target_os = "windows") {
-                out.rsplit_terminator("\r\n").map(str::to_string).collect()
-            } else {
-                out.split_terminator('\n').map(str::to_string).collect()
-            }
-        };
-        let output = Content::new((stdout, stderr));
-        if out.status.success() {
-            Output::new(output)
+        log::debug!(
+            "cmd: {:?}, args: {:?}",
+            cmd.get_program(),
+            args.iter().map(|arg| env.redact(arg)).collect::<Vec<_>>()
+        );
+        cmd.args(args);
+        cmd.envs(env.exported());
+        set_nice(&mut cmd, self.nice);
+        let stream = self
+            .stream
+            .as_ref()
+            .map(|stream| (stream, self.stream_flush));
+        execute(
+            cmd,
+            self.format,
+            &self.success_codes,
+            stream,
+            self.capture_buffer,
+        )
+    }
+
+    fn config(&self) -> Option<Vec<(&'static str, serde_json::Value)>> {
+        let mut attrs = vec![("cmd", self.command.clone().into())];
+        if !self.shell {
+            attrs.push(("shell", false.into()));
+        }
+        if let Some(name) = self.format.name() {
+            attrs.push(("output_type", name.into()));
+        }
+        if let Some(codes) = success_codes_attr(&self.success_codes) {
+            attrs.push(("success_codes", codes));
+        }
+        Some(attrs)
+    }
+}
+
+/// The value of the `success_codes` attribute of an action, as a list of codes, or None for
+/// the default codes.
+pub(crate) fn success_codes_attr(codes: &[i32]) -> Option<serde_json::Value> {
+    if codes == [0] {
+        return None;
+    }
+    Some(codes.into())
+}
+
+/// Run the command to completion, capturing its standard output and standard error line by line,
+/// and turn them into an output of the given format.
+/// The command succeeds if it exits with one of `success_codes`, and the output holds its code.
+/// If a stream is given, the lines of the standard output are passed to it as they come.
+/// The outputs are read `capture_buffer` bytes at a time if given.
+/// The command is killed if the dag is cancelled in the meantime.
+pub(crate) fn execute(
+    mut cmd: Command,
+    format: OutputFormat,
+    success_codes: &[i32],
+    stream: Option<(&OutputStream, StreamFlush)>,
+    capture_buffer: Option<usize>,
+) -> Output {
+    let group = ISOLATED.load(Ordering::Acquire);
+    #[cfg(unix)]
+    if group {
+        std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    }
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let out = child.and_then(|mut child| {
+        let pid = child.id();
+        RUNNING.lock().unwrap().push((pid, group));
+        // The streamed standard output is read on its own thread, while the standard error
+        // is read along with the exit status.
+        let streamed = stream.and_then(|(stream, flush)| {
+            let stdout = child.stdout.take()?;
+            let stream = stream.clone();
+            Some(std::thread::spawn(move || {
+                stream.forward(stdout, flush, capture_buffer)
+            }))
+        });
+        wait_with_usage(child, streamed, capture_buffer)
+    });
+    let out = match out {
+        Ok(o) => o,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Output::error_with_exit_code(
+                e.raw_os_error(),
+                Some(Content::new(RunningError::InterpreterNotFound(program))),
+            )
+        }
+        Err(e) => {
+            return Output::error_with_exit_code(
+                e.raw_os_error(),
+                Some(Content::new(e.to_string())),
+            )
+        }
+    };
+    let stdout: Vec<String> = {
+        let out = String::from_utf8(out.stdout).unwrap_or("".to_string());
+        if cfg!(target_os = "windows") {
+            out.rsplit_terminator("\r\n").map(str::to_string).collect()
+        } else {
+            out.split_terminator('\n').map(str::to_string).collect()
+        }
+    };
+    let stderr: Vec<String> = {
+        let out = String::from_utf8(out.stderr).unwrap_or("".to_string());
+        if cfg!(target_os = "windows") {
+            out.rsplit_terminator("\r\n").map(str::to_string).collect()
         } else {
-            Output::error_with_exit_code(Some(code), Some(output))
+            out.split_terminator('\n').map(str::to_string).collect()
+        }
+    };
+    // A command killed by a signal has no exit code, and always fails.
+    let code = match out.status.code() {
+        Some(code) if success_codes.contains(&code) => code,
+        code => {
+            let err = match code {
+                Some(NOT_FOUND) => missing_program(&stderr),
+                _ => None,
+            };
+            let err = match err {
+                Some(program) => Content::new(RunningError::InterpreterNotFound(program)),
+                None => Content::new((stdout, stderr)),
+            };
+            return Output::error_with_exit_code(code, Some(err));
+        }
+    };
+    if format != OutputFormat::Lines {
+        return match format.parse(&stdout) {
+            Output::Out(out) => Output::with_exit_code(code, out),
+            err => err,
+        };
+    }
+    let output = Content::new((stdout, stderr));
+    Output::with_exit_code(code, Some(Content::new(output)))
+}
+
+/// Run the command at the given nice value, if any. Does nothing on the platforms other than
+/// Unix.
+#[allow(unused_variables)]
+pub(crate) fn set_nice(cmd: &mut Command, nice: Option<i32>) {
+    #[cfg(unix)]
+    if let Some(level) = nice {
+        use std::os::unix::process::CommandExt;
+        // SAFETY: setpriority is async-signal-safe, and only changes the priority of the
+        // child process.
+        unsafe {
+            cmd.pre_exec(move || {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, level) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
         }
     }
 }
+
+/// The exit code of the shells when a command is not found.
+const NOT_FOUND: i32 = 127;
+
+/// The program a shell failed to find, from the message it wrote to the standard error, such
+/// as `sh: 1: python3: not found` or `bash: line 1: python3: command not found`.
+fn missing_program(stderr: &[String]) -> Option<String> {
+    stderr.iter().rev().find_map(|line| {
+        let line = line
+            .strip_suffix(": command not found")
+            .or_else(|| line.strip_suffix(": not found"))?;
+        let program = line.rsplit(": ").next()?.trim();
+        (!program.is_empty()).then(|| program.to_owned())
+    })
+}
@@ -0,0 +1,113 @@
+//! Compressed storage of large outputs.
+//!
+//! When `Dag::set_compress_outputs` is enabled, the text outputs of the tasks are stored
+//! compressed with zstd once they finish, and decompressed when a successor or the report
+//! first reads them. Only outputs of at least [`COMPRESSION_THRESHOLD`] bytes are compressed:
+//! below that, the time spent compressing outweighs the memory saved (see the
+//! `compress_outputs` benchmark).
+
+use std::any::type_name;
+
+use super::Content;
+
+/// The size in bytes from which an output is compressed.
+pub(crate) const COMPRESSION_THRESHOLD: usize = 16 * 1024;
+
+/// The compression level passed to zstd, favoring speed over ratio.
+const LEVEL: i32 = 1;
+
+/// The type of a compressed output, so that it can be restored as it was.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    /// A `String`.
+    Text,
+    /// A `Vec<String>`.
+    Lines,
+    /// The `(stdout, stderr)` lines produced by `CommandAction`.
+    Streams,
+}
+
+/// An output stored compressed. `nested` is set if the original value was wrapped in another
+/// [`Content`], as the outputs of commands are.
+#[derive(Debug)]
+struct Compressed {
+    kind: Kind,
+    nested: bool,
+    data: Vec<u8>,
+}
+
+/// Compress the content if it is a large enough text. Other contents are returned as is.
+pub(crate) fn compress(content: Content) -> Content {
+    if content.is_pending() {
+        return content;
+    }
+    let (inner, nested) = match content.get::<Content>() {
+        Some(inner) => (inner, true),
+        None => (&content, false),
+    };
+    let (kind, bytes) = if let Some(text) = inner.get::<String>() {
+        if text.len() < COMPRESSION_THRESHOLD {
+            return content;
+        }
+        (Kind::Text, text.as_bytes().to_vec())
+    } else if let Some(lines) = inner.get::<Vec<String>>() {
+        if size(lines) < COMPRESSION_THRESHOLD {
+            return content;
+        }
+        (Kind::Lines, serde_json::to_vec(lines).unwrap())
+    } else if let Some(streams) = inner.get::<(Vec<String>, Vec<String>)>() {
+        if size(&streams.0) + size(&streams.1) < COMPRESSION_THRESHOLD {
+            return content;
+        }
+        (Kind::Streams, serde_json::to_vec(streams).unwrap())
+    } else {
+        return content;
+    };
+    match zstd::encode_all(bytes.as_slice(), LEVEL) {
+        Ok(data) => Content::new(Compressed { kind, nested, data }),
+        Err(_) => content,
+    }
+}
+
+/// Restore a content stored by [`compress`], lazily: the data is only decompressed the first
+/// time the returned content is read. Other contents are returned as is.
+pub(crate) fn decompress(content: Content) -> Content {
+    if content.is_pending() {
+        return content;
+    }
+    let type_name = match content.get::<Compressed>() {
+        Some(compressed) if compressed.nested => type_name::<Content>(),
+        Some(compressed) => match compressed.kind {
+            Kind::Text => type_name::<String>(),
+            Kind::Lines => type_name::<Vec<String>>(),
+            Kind::Streams => type_name::<(Vec<String>, Vec<String>)>(),
+        },
+        None => return content,
+    };
+    Content::deferred(type_name, move || {
+        restore(content.get::<Compressed>().unwrap())
+    })
+}
+
+/// Decompress the data of a compressed content.
+fn restore(compressed: &Compressed) -> Content {
+    // The data was produced by `compress`, so it is valid.
+    let bytes = zstd::decode_all(compressed.data.as_slice()).unwrap();
+    let inner = match compressed.kind {
+        Kind::Text => Content::new(String::from_utf8(bytes).unwrap()),
+        Kind::Lines => Content::new(serde_json::from_slice::<Vec<String>>(&bytes).unwrap()),
+        Kind::Streams => {
+            Content::new(serde_json::from_slice::<(Vec<String>, Vec<String>)>(&bytes).unwrap())
+        }
+    };
+    if compressed.nested {
+        Content::new(inner)
+    } else {
+        inner
+    }
+}
+
+/// The number of bytes of text in the lines.
+fn size(lines: &[String]) -> usize {
+    lines.iter().map(String::len).sum()
+}
@@ -0,0 +1,132 @@
+//! Conversion of the outputs of the tasks into the types their successors expect.
+//!
+//! When `Dag::set_convert_inputs` is enabled, the output of a predecessor is converted into
+//! the input type declared by the task before the task receives it. The conversions cover the
+//! common representations of the outputs: text, JSON, bytes, integers, floats and the lines
+//! produced by `CommandAction`.
+
+use super::{Content, DataType};
+use serde_json::Value;
+
+/// A representation of the outputs that can be converted into the other ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// A `String`.
+    Text,
+    /// A [`serde_json::Value`].
+    Json,
+    /// A `Vec<u8>`.
+    Bytes,
+    /// An `i64`.
+    Int,
+    /// An `f64`.
+    Float,
+    /// The `(stdout, stderr)` lines produced by `CommandAction`, possibly nested in a
+    /// [`Content`]. Only the standard output is converted.
+    Lines,
+}
+
+impl Format {
+    /// The format of the values of the given type, if it is one of the formats.
+    fn of(data_type: DataType) -> Option<Self> {
+        let formats = [
+            (DataType::of::<String>(), Self::Text),
+            (DataType::of::<Value>(), Self::Json),
+            (DataType::of::<Vec<u8>>(), Self::Bytes),
+            (DataType::of::<i64>(), Self::Int),
+            (DataType::of::<f64>(), Self::Float),
+            (DataType::of::<Content>(), Self::Lines),
+            (DataType::of::<(Vec<String>, Vec<String>)>(), Self::Lines),
+        ];
+        formats
+            .into_iter()
+            .find(|(ty, _)| *ty == data_type)
+            .map(|(_, format)| format)
+    }
+}
+
+/// Whether the values of type `from` can be converted into `to`. Some values may still fail
+/// to convert, such as a text that is not a number.
+///
+/// Every format converts into the other ones, except that floats do not convert into
+/// integers, and nothing converts into the lines of a command.
+pub(crate) fn convertible(from: DataType, to: DataType) -> bool {
+    match (Format::of(from), Format::of(to)) {
+        (_, Some(Format::Lines)) | (Some(Format::Float), Some(Format::Int)) => false,
+        (Some(_), Some(_)) => true,
+        _ => false,
+    }
+}
+
+/// Convert the content into the given type. Returns the content as is if it already has the
+/// type, and None if it cannot be converted.
+pub(crate) fn convert(content: &Content, to: DataType) -> Option<Content> {
+    let to = Format::of(to)?;
+    let converted = match to {
+        Format::Text => Content::new(text(content)?),
+        Format::Json => Content::new(json(content)?),
+        Format::Bytes => match content.get::<Vec<u8>>() {
+            Some(_) => content.clone(),
+            None => Content::new(text(content)?.into_bytes()),
+        },
+        Format::Int => match (content.get::<i64>(), content.get::<Value>()) {
+            (Some(_), _) => content.clone(),
+            (_, Some(value)) => Content::new(value.as_i64()?),
+            _ => Content::new(text(content)?.trim().parse::<i64>().ok()?),
+        },
+        Format::Float => match (
+            content.get::<f64>(),
+            content.get::<i64>(),
+            content.get::<Value>(),
+        ) {
+            (Some(_), _, _) => content.clone(),
+            (_, Some(int), _) => Content::new(*int as f64),
+            (_, _, Some(value)) => Content::new(value.as_f64()?),
+            _ => Content::new(text(content)?.trim().parse::<f64>().ok()?),
+        },
+        Format::Lines => content.clone(),
+    };
+    Some(converted)
+}
+
+/// The standard output of a command, if the content holds its lines.
+fn stdout(content: &Content) -> Option<&Vec<String>> {
+    let inner = content.get::<Content>().unwrap_or(content);
+    inner
+        .get::<(Vec<String>, Vec<String>)>()
+        .map(|(stdout, _)| stdout)
+}
+
+/// The content as a text. JSON values other than strings are serialized.
+fn text(content: &Content) -> Option<String> {
+    if let Some(text) = content.get::<String>() {
+        return Some(text.clone());
+    }
+    if let Some(value) = content.get::<Value>() {
+        return Some(match value {
+            Value::String(text) => text.clone(),
+            value => value.to_string(),
+        });
+    }
+    if let Some(bytes) = content.get::<Vec<u8>>() {
+        return String::from_utf8(bytes.clone()).ok();
+    }
+    if let Some(int) = content.get::<i64>() {
+        return Some(int.to_string());
+    }
+    if let Some(float) = content.get::<f64>() {
+        return Some(float.to_string());
+    }
+    stdout(content).map(|lines| lines.join("\n"))
+}
+
+/// The content as a JSON value. Texts are parsed.
+fn json(content: &Content) -> Option<Value> {
+    if let Some(value) = content.get::<Value>() {
+        return Some(value.clone());
+    }
+    if content.get::<i64>().is_some() || content.get::<f64>().is_some() {
+        return content.to_json();
+    }
+    serde_json::from_str(&text(content)?).ok()
+}
@@ -1,6 +1,14 @@
-use super::{Action, Complex, Task, ID_ALLOCATOR};
+use super::{
+    Action, Complex, DataType, ResourceRequest, RetryPolicy, SuccessCheck, Task, TaskLabels,
+    ThreadAffinity, ID_ALLOCATOR,
+};
 use crate::{EnvVar, Input, Output};
-use std::sync::Arc;
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
 /// Common task types
 ///
@@ -68,6 +76,32 @@ pub struct DefaultTask {
     precursors: Vec<usize>,
     /// Perform specific actions.
     action: Action,
+    /// Whether the action of the task is executed when the dag runs.
+    enabled: bool,
+    /// The declared type of the output.
+    output_type: Option<DataType>,
+    /// The declared type of the inputs.
+    input_type: Option<DataType>,
+    /// The estimated cost of the task.
+    weight: f64,
+    /// Metadata used to select tasks.
+    labels: TaskLabels,
+    /// How the task is retried when it fails.
+    retry: Option<RetryPolicy>,
+    /// Which failures of the task are retried, whatever the policy in effect.
+    retry_filter: Option<RetryPolicy>,
+    /// Whether the failure of the task is tolerated.
+    allow_failure: bool,
+    /// Whether the task runs when the run of the dag is cancelled or fails.
+    run_on_cancel: bool,
+    /// The resources the task needs while it runs.
+    resource_request: ResourceRequest,
+    /// The thread the action runs on.
+    thread_affinity: ThreadAffinity,
+    /// How long the action may run.
+    timeout: Option<Duration>,
+    /// Whether a non-error output of the action is a success.
+    success_check: Option<Arc<SuccessCheck>>,
 }
 
 impl DefaultTask {
@@ -79,6 +113,19 @@ impl DefaultTask {
             action: Action::Closure(Arc::new(action)),
             name: name.to_owned(),
             precursors: Vec::new(),
+            enabled: true,
+            output_type: None,
+            input_type: None,
+            weight: 1.0,
+            labels: TaskLabels::default(),
+            retry: None,
+            retry_filter: None,
+            allow_failure: false,
+            run_on_cancel: false,
+            resource_request: ResourceRequest::default(),
+            thread_affinity: ThreadAffinity::Any,
+            timeout: None,
+            success_check: None,
         }
     }
     /// Create a task, give the task name, and provide a specific type that implements the [`Complex`] trait as the specific
@@ -95,6 +142,19 @@ impl DefaultTask {
             action: Action::Structure(action),
             name: name.to_owned(),
             precursors: Vec::new(),
+            enabled: true,
+            output_type: None,
+            input_type: None,
+            weight: 1.0,
+            labels: TaskLabels::default(),
+            retry: None,
+            retry_filter: None,
+            allow_failure: false,
+            run_on_cancel: false,
+            resource_request: ResourceRequest::default(),
+            thread_affinity: ThreadAffinity::Any,
+            timeout: None,
+            success_check: None,
         }
     }
 
@@ -116,6 +176,19 @@ impl DefaultTask {
             action: Action::Closure(action),
             name: name.to_owned(),
             precursors: Vec::new(),
+            enabled: true,
+            output_type: None,
+            input_type: None,
+            weight: 1.0,
+            labels: TaskLabels::default(),
+            retry: None,
+            retry_filter: None,
+            allow_failure: false,
+            run_on_cancel: false,
+            resource_request: ResourceRequest::default(),
+            thread_affinity: ThreadAffinity::Any,
+            timeout: None,
+            success_check: None,
         }
     }
 
@@ -160,6 +233,248 @@ impl DefaultTask {
     pub fn set_action(&mut self, action: impl Complex + Send + Sync + 'static) {
         self.action = Action::Structure(Arc::new(action))
     }
+
+    /// Enable or disable the task. A disabled task is skipped when the dag runs, and its
+    /// successors receive no input from it.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Declare that the task outputs values of type `T`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DagError, DefaultTask, Output};
+    /// let mut t1 = DefaultTask::with_closure("Task 1", |_input, _env| Output::new(1usize));
+    /// t1.declares_output::<usize>();
+    /// let mut t2 = DefaultTask::with_closure("Task 2", |_input, _env| Output::empty());
+    /// t2.declares_input::<String>();
+    /// t2.set_predecessors(&[&t1]);
+    /// let res = Dag::with_tasks(vec![t1, t2]).start();
+    /// assert!(matches!(res, Err(DagError::TypeMismatch { .. })));
+    /// ```
+    pub fn declares_output<T: 'static>(&mut self) {
+        self.output_type = Some(DataType::of::<T>());
+    }
+
+    /// Declare that the task expects values of type `T` from its predecessors.
+    pub fn declares_input<T: 'static>(&mut self) {
+        self.input_type = Some(DataType::of::<T>());
+    }
+
+    /// Copy the id, name, dependencies, action and settings of any task. The action is shared
+    /// with the original task.
+    pub(crate) fn copy_of(task: &dyn Task) -> Self {
+        DefaultTask {
+            id: task.id(),
+            action: task.action(),
+            name: task.name().to_owned(),
+            precursors: task.precursors().to_vec(),
+            enabled: task.enabled(),
+            output_type: task.output_type(),
+            input_type: task.input_type(),
+            weight: task.weight(),
+            labels: task.labels(),
+            retry: task.retry(),
+            retry_filter: task.retry_filter(),
+            allow_failure: task.allow_failure(),
+            run_on_cancel: task.run_on_cancel(),
+            resource_request: task.resource_request(),
+            thread_affinity: task.thread_affinity(),
+            timeout: task.timeout(),
+            success_check: task.success_check(),
+        }
+    }
+
+    /// Copy a task like `copy_of`, under another id and name and with other predecessors.
+    pub(crate) fn copy_as(
+        task: &dyn Task,
+        id: usize,
+        name: String,
+        precursors: Vec<usize>,
+    ) -> Self {
+        DefaultTask {
+            id,
+            name,
+            precursors,
+            ..Self::copy_of(task)
+        }
+    }
+
+    /// Set the estimated cost of the task, 1.0 by default.
+    pub fn set_weight(&mut self, weight: f64) {
+        self.weight = weight;
+    }
+
+    /// Attach a label to the task, replacing the previous value of the same key.
+    pub fn set_label(&mut self, key: &str, value: &str) {
+        self.labels.insert(key, value);
+    }
+
+    /// Retry the task when it fails, see [`RetryPolicy`]. The condition given by
+    /// `set_retry_if` or `set_retry_on_codes` is kept, whichever is called first.
+    pub fn set_retry(&mut self, policy: RetryPolicy) {
+        self.retry = Some(policy);
+    }
+
+    /// Only retry the failures of the task for which `condition` returns true, e.g. to retry
+    /// transient errors but not the other ones. It has no effect unless retries are set with
+    /// `set_retry` or `Dag::set_default_retry`, and it replaces the condition of the policy.
+    pub fn set_retry_if(&mut self, condition: impl Fn(&Output) -> bool + Send + Sync + 'static) {
+        self.retry_filter
+            .get_or_insert_with(RetryPolicy::default)
+            .set_condition(condition);
+    }
+
+    /// Only accept the outputs of the action for which `check` returns true, e.g. to fail a
+    /// script whose output lacks a token. `check` is called with every output that is not an
+    /// error: when it returns false, the attempt fails as if the action had returned an error,
+    /// so it may be retried, and the output is dropped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, TaskState};
+    ///
+    /// let mut probe = DefaultTask::with_closure("probe", |_input, _env| Output::new(0usize));
+    /// probe.set_success_check(|out| !matches!(out, Output::Out(Some(c)) if c.get::<usize>() == Some(&0)));
+    /// let mut dag = Dag::with_tasks(vec![probe]);
+    /// assert!(!dag.start().unwrap());
+    /// assert_eq!(dag.task_state("probe"), Some(TaskState::Failed));
+    /// ```
+    pub fn set_success_check(&mut self, check: impl Fn(&Output) -> bool + Send + Sync + 'static) {
+        self.success_check = Some(Arc::new(check));
+    }
+
+    /// Fail an attempt of the task that runs for longer than `timeout`, overriding the default
+    /// timeout of the dag, see `Dag::set_default_timeout`. The time an attempt waits for a
+    /// thread to run on does not count.
+    ///
+    /// The failure is retried like any other. The action cannot be interrupted, so it is left
+    /// to finish in the background and its output is ignored, but a retry only starts once it
+    /// returned, so that the attempts never overlap: if it is still running after `timeout`
+    /// once more, the task fails without being retried. The middlewares see the end of the last attempt, and its resources are added
+    /// to the task, only once it returns, possibly after the task failed.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Only retry the failures of the task whose process exited with one of `codes`, e.g. for
+    /// a [`CommandAction`](crate::CommandAction) running a tool that exits with 75
+    /// (`EX_TEMPFAIL`) on transient failures, and fail immediately otherwise. It has no effect
+    /// unless retries are set with `set_retry` or `Dag::set_default_retry`, and it takes
+    /// precedence over the condition given by `set_retry_if`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{CommandAction, Dag, DefaultTask, RetryPolicy, TaskState};
+    ///
+    /// let mut task = DefaultTask::with_action("fetch", CommandAction::new("exit 2"));
+    /// task.set_retry(RetryPolicy::new(3));
+    /// task.set_retry_on_codes(&[75]);
+    /// let mut dag = Dag::with_tasks(vec![task]);
+    /// assert!(!dag.start().unwrap());
+    /// assert_eq!(dag.task_state("fetch"), Some(TaskState::Failed));
+    /// ```
+    pub fn set_retry_on_codes(&mut self, codes: &[i32]) {
+        self.retry_filter
+            .get_or_insert_with(RetryPolicy::default)
+            .set_retry_on_codes(codes);
+    }
+
+    /// Allow the task to fail without failing the dag, e.g. for a best-effort lint. If it
+    /// fails, it is reported as [`TaskState::FailedAllowed`](crate::TaskState::FailedAllowed)
+    /// and its successors still run, receiving no input from it.
+    pub fn set_allow_failure(&mut self, allow: bool) {
+        self.allow_failure = allow;
+    }
+
+    /// Make the task a finalizer, e.g. to release a lock or delete temporary resources: when
+    /// the run of the dag is cancelled, times out or fails before the task ran, it is still
+    /// executed before the run returns, with an empty input. See `Task::run_on_cancel`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, Task, TaskState};
+    ///
+    /// let build = DefaultTask::with_closure("build", |_input, _env| Output::error("no".to_string()));
+    /// let mut unlock = DefaultTask::with_closure("unlock", |_input, _env| Output::empty());
+    /// unlock.set_predecessors(&[&build]);
+    /// unlock.set_run_on_cancel(true);
+    /// let mut dag = Dag::with_tasks(vec![build, unlock]);
+    /// assert!(!dag.start().unwrap());
+    /// assert_eq!(dag.task_state("unlock"), Some(TaskState::Succeeded));
+    /// ```
+    pub fn set_run_on_cancel(&mut self, run: bool) {
+        self.run_on_cancel = run;
+    }
+
+    /// Declare the resources the task needs while it runs, in thousandths of a core and in
+    /// megabytes. See `Dag::set_resource_budget`.
+    pub fn set_resource_request(&mut self, cpu_millis: u32, memory_mb: u32) {
+        self.resource_request = ResourceRequest::new(cpu_millis, memory_mb);
+    }
+
+    /// Set the thread the action of the task runs on, e.g. [`ThreadAffinity::Main`] for an
+    /// action calling a library that must always be called from the same thread.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, ThreadAffinity};
+    ///
+    /// let mut task = DefaultTask::with_closure("gui", |_input, _env| {
+    ///     Output::new(std::thread::current().name().map(str::to_owned))
+    /// });
+    /// task.set_thread_affinity(ThreadAffinity::Main);
+    /// let mut dag = Dag::with_tasks(vec![task]);
+    /// assert!(dag.start().unwrap());
+    /// let thread = dag.get_result::<Option<String>>().unwrap();
+    /// assert_eq!(thread.as_deref(), Some("dagrs-main"));
+    /// ```
+    pub fn set_thread_affinity(&mut self, affinity: ThreadAffinity) {
+        self.thread_affinity = affinity;
+    }
+
+    /// Run the action of the task directly with the given input and environment, without a
+    /// dag, e.g. to unit test it.
+    ///
+    /// As in a dag, a panic of the action is turned into an error output, and the task is
+    /// retried according to its [`RetryPolicy`], sleeping on the current thread between the
+    /// attempts. The other settings of the task, such as `enabled` or `allow_failure`, only
+    /// concern dags and are ignored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{DefaultTask, EnvVar, Input, Output};
+    /// use dagrs::task::Content;
+    /// use std::sync::Arc;
+    ///
+    /// let task = DefaultTask::with_closure("double", |input, _env| {
+    ///     let value = input.get_iter().next().unwrap().get::<usize>().unwrap();
+    ///     Output::new(value * 2)
+    /// });
+    /// let out = task.run_isolated(Input::new(vec![Content::new(21usize)]), Arc::new(EnvVar::new()));
+    /// assert!(matches!(out, Output::Out(Some(c)) if c.get::<usize>() == Some(&42)));
+    /// ```
+    pub fn run_isolated(&self, input: Input, env: Arc<EnvVar>) -> Output {
+        let retry = self.retry.clone().map(|policy| match &self.retry_filter {
+            Some(filter) => policy.filtered_by(filter),
+            None => policy,
+        });
+        let mut attempt = 0;
+        loop {
+            let out = panic::catch_unwind(AssertUnwindSafe(|| {
+                self.action.run(input.clone(), env.clone())
+            }))
+            .unwrap_or_else(|_| Output::error("the task panicked".to_string()));
+            match &retry {
+                Some(policy) if out.is_err() && policy.should_retry(attempt, &out) => {
+                    thread::sleep(policy.delay(attempt));
+                    attempt += 1;
+                }
+                _ => return out,
+            }
+        }
+    }
 }
 
 impl Task for DefaultTask {
@@ -178,6 +493,58 @@ impl Task for DefaultTask {
     fn name(&self) -> &str {
         &self.name
     }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn output_type(&self) -> Option<DataType> {
+        self.output_type
+    }
+
+    fn input_type(&self) -> Option<DataType> {
+        self.input_type
+    }
+
+    fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn labels(&self) -> TaskLabels {
+        self.labels.clone()
+    }
+
+    fn retry(&self) -> Option<RetryPolicy> {
+        self.retry.clone()
+    }
+
+    fn retry_filter(&self) -> Option<RetryPolicy> {
+        self.retry_filter.clone()
+    }
+
+    fn allow_failure(&self) -> bool {
+        self.allow_failure
+    }
+
+    fn run_on_cancel(&self) -> bool {
+        self.run_on_cancel
+    }
+
+    fn resource_request(&self) -> ResourceRequest {
+        self.resource_request
+    }
+
+    fn thread_affinity(&self) -> ThreadAffinity {
+        self.thread_affinity
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn success_check(&self) -> Option<Arc<SuccessCheck>> {
+        self.success_check.clone()
+    }
 }
 
 impl Default for DefaultTask {
@@ -190,6 +557,19 @@ impl Default for DefaultTask {
             name,
             precursors: Vec::new(),
             action: Action::Closure(Arc::new(action)),
+            enabled: true,
+            output_type: None,
+            input_type: None,
+            weight: 1.0,
+            labels: TaskLabels::default(),
+            retry: None,
+            retry_filter: None,
+            allow_failure: false,
+            run_on_cancel: false,
+            resource_request: ResourceRequest::default(),
+            thread_affinity: ThreadAffinity::Any,
+            timeout: None,
+            success_check: None,
         }
     }
 }
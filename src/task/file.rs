@@ -0,0 +1,39 @@
+//! Outputs passed by reference to a file.
+//!
+//! A task may output the path of a file it wrote with `Output::file` or `Output::temp_file`
+//! rather than its content, so that large data flows between the tasks on disk. Its successors
+//! get the path with `Input::paths` or `Content::path`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The path of a file output by a task. The file is removed once dropped if it is owned.
+#[derive(Debug)]
+pub(crate) struct FileRef {
+    path: PathBuf,
+    owned: bool,
+}
+
+impl FileRef {
+    pub(crate) fn new(path: PathBuf, owned: bool) -> Self {
+        Self { path, owned }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileRef {
+    fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+        match fs::remove_file(&self.path) {
+            Ok(()) => log::debug!("Removed the output file {:?}", self.path),
+            Err(err) => log::warn!("Failed to remove the output file {:?}: {}", self.path, err),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{Complex, EnvVar, Input, Output};
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The gates whose actions are currently waiting for a decision, so that they can be released
+/// when a dag is cancelled.
+static WAITING: Mutex<Vec<Arc<GateState>>> = Mutex::new(Vec::new());
+
+/// Release all the gates that are currently waiting, which then fail.
+pub(crate) fn cancel_waiting_gates() {
+    for state in WAITING.lock().unwrap().drain(..) {
+        state.decide(Decision::Cancelled);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Decision {
+    Approved,
+    Rejected(String),
+    /// The dag was cancelled while the gate was waiting. This decision only ends one run.
+    Cancelled,
+}
+
+#[derive(Debug, Default)]
+struct GateState {
+    decision: Mutex<Option<Decision>>,
+    decided: Condvar,
+}
+
+impl GateState {
+    fn decide(&self, decision: Decision) {
+        *self.decision.lock().unwrap() = Some(decision);
+        self.decided.notify_all();
+    }
+}
+
+/// The handle approving or rejecting a [`GateAction`], which may be sent to another thread.
+///
+/// A gate can be approved or rejected before its task starts: the task then goes through it
+/// without waiting. The last decision wins.
+#[derive(Debug, Clone)]
+pub struct Gate(Arc<GateState>);
+
+impl Gate {
+    /// Let the task of the gate succeed.
+    pub fn approve(&self) {
+        self.0.decide(Decision::Approved);
+    }
+
+    /// Make the task of the gate fail with the given reason.
+    pub fn reject(&self, reason: &str) {
+        self.0.decide(Decision::Rejected(reason.to_owned()));
+    }
+}
+
+/// [`GateAction`] is a specific implementation of [`Complex`], used to pause a branch of a dag
+/// until an external decision arrives, e.g. a manual approval before a deployment.
+///
+/// The task waits until the [`Gate`] of the action is approved or rejected, while the other
+/// branches of the dag proceed. Once approved, it passes its input through when there is
+/// exactly one, so that the gate can be inserted between two tasks, and its output is empty
+/// otherwise. It fails if the gate is rejected, if no decision arrives within the timeout, if
+/// one is set, or if the dag is cancelled.
+///
+/// # Example
+///
+/// ```rust
+/// use dagrs::{Dag, DefaultTask, GateAction, Output};
+///
+/// let build = DefaultTask::with_closure("build", |_, _| Output::new("artifact".to_string()));
+/// let action = GateAction::new();
+/// let gate = action.gate();
+/// let mut approval = DefaultTask::with_action("approval", action);
+/// approval.set_predecessors(&[&build]);
+/// let mut dag = Dag::with_tasks(vec![build, approval]);
+/// std::thread::spawn(move || gate.approve());
+/// assert!(dag.start().unwrap());
+/// assert_eq!(*dag.get_result::<String>().unwrap(), "artifact");
+/// ```
+#[derive(Debug, Default)]
+pub struct GateAction {
+    state: Arc<GateState>,
+    timeout: Option<Duration>,
+}
+
+impl GateAction {
+    /// Create a gate that waits for a decision as long as it takes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The handle approving or rejecting the gate.
+    pub fn gate(&self) -> Gate {
+        Gate(self.state.clone())
+    }
+
+    /// Fail the task if no decision arrives within `timeout` once it starts waiting.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Wait for a decision, or until the timeout expires.
+    fn wait(&self) -> Option<Decision> {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let mut decision = self.state.decision.lock().unwrap();
+        loop {
+            if let Some(made) = decision.as_ref() {
+                let made = made.clone();
+                if made == Decision::Cancelled {
+                    *decision = None;
+                }
+                return Some(made);
+            }
+            decision = match deadline {
+                Some(deadline) => {
+                    let left = deadline.checked_duration_since(Instant::now())?;
+                    self.state.decided.wait_timeout(decision, left).unwrap().0
+                }
+                None => self.state.decided.wait(decision).unwrap(),
+            };
+        }
+    }
+}
+
+impl Complex for GateAction {
+    fn run(&self, input: Input, _env: Arc<EnvVar>) -> Output {
+        WAITING.lock().unwrap().push(self.state.clone());
+        let decision = self.wait();
+        WAITING
+            .lock()
+            .unwrap()
+            .retain(|state| !Arc::ptr_eq(state, &self.state));
+        match decision {
+            Some(Decision::Approved) => {
+                let mut inputs = input.get_iter();
+                match (inputs.next(), inputs.next()) {
+                    (Some(content), None) => Output::Out(Some(content.clone())),
+                    _ => Output::empty(),
+                }
+            }
+            Some(Decision::Rejected(reason)) => {
+                Output::error(format!("the gate was rejected: {}", reason))
+            }
+            Some(Decision::Cancelled) => Output::error("the gate was cancelled".to_string()),
+            None => Output::error(format!(
+                "the gate was not approved within {:?}",
+                self.timeout.unwrap_or_default()
+            )),
+        }
+    }
+}
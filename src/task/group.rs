@@ -0,0 +1,93 @@
+use super::{DefaultTask, Task};
+
+/// A set of tasks sharing a setup and a teardown, reported and cancelled as a unit.
+///
+/// A task belongs to the group named by its `group` label ([`TaskGroup::LABEL`]), so any task
+/// can join a group by setting that label, e.g. in a yaml file. A [`TaskGroup`] adds a
+/// lifecycle to the group when it is added to a dag with `Dag::add_group`:
+///
+/// - The setup task runs before every member, and its output is given to each of them.
+/// - The teardown task runs once every member finished, whatever their outcome, provided the
+///   setup succeeded. It runs even if the dag stops because of a failure, but not if the dag
+///   is interrupted by its timeout or a signal.
+///
+/// Members may depend on tasks outside the group, and tasks outside the group may depend on
+/// members: these edges behave as usual. In particular, the successors of a member cancelled
+/// by `Dag::cancel_group` are skipped, whether they belong to the group or not.
+///
+/// # Example
+///
+/// ```rust
+/// use dagrs::{Dag, DefaultTask, Output, TaskGroup, TaskState};
+///
+/// let mut group = TaskGroup::new("database");
+/// group.set_setup(DefaultTask::with_closure("start db", |_input, _env| Output::empty()));
+/// group.set_teardown(DefaultTask::with_closure("stop db", |_input, _env| Output::empty()));
+/// group.add_task(DefaultTask::with_closure("migrate", |_input, _env| Output::empty()));
+/// let mut dag = Dag::with_tasks(Vec::<DefaultTask>::new());
+/// dag.add_group(group);
+/// assert!(dag.start().unwrap());
+/// assert_eq!(dag.run_report().group("database").unwrap().state, TaskState::Succeeded);
+/// ```
+pub struct TaskGroup {
+    name: String,
+    setup: Option<DefaultTask>,
+    teardown: Option<DefaultTask>,
+    members: Vec<DefaultTask>,
+}
+
+impl TaskGroup {
+    /// The label holding the name of the group of a task.
+    pub const LABEL: &'static str = "group";
+
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            setup: None,
+            teardown: None,
+            members: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set the task run before every member.
+    pub fn set_setup(&mut self, task: DefaultTask) {
+        self.setup = Some(task);
+    }
+
+    /// Set the task run after all the members.
+    pub fn set_teardown(&mut self, task: DefaultTask) {
+        self.teardown = Some(task);
+    }
+
+    /// Add a member to the group.
+    pub fn add_task(&mut self, task: DefaultTask) {
+        self.members.push(task);
+    }
+
+    /// Label all the tasks with the name of the group and wire the setup and the teardown
+    /// around the members. Returns the tasks, along with the ids of the setup and the teardown.
+    pub(crate) fn into_tasks(self) -> (Vec<DefaultTask>, Option<usize>, Option<usize>) {
+        let setup_id = self.setup.as_ref().map(|task| task.id());
+        let mut member_ids: Vec<usize> = self.members.iter().map(|task| task.id()).collect();
+        let mut tasks = Vec::with_capacity(self.members.len() + 2);
+        tasks.extend(self.setup);
+        for mut member in self.members {
+            member.set_predecessors_by_id(setup_id);
+            tasks.push(member);
+        }
+        let teardown_id = self.teardown.as_ref().map(|task| task.id());
+        if let Some(mut teardown) = self.teardown {
+            member_ids.extend(setup_id);
+            teardown.set_predecessors_by_id(member_ids);
+            tasks.push(teardown);
+        }
+        for task in tasks.iter_mut() {
+            task.set_label(Self::LABEL, &self.name);
+        }
+        (tasks, setup_id, teardown_id)
+    }
+}
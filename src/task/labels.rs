@@ -0,0 +1,53 @@
+use std::collections::BTreeMap;
+
+/// Key-value metadata attached to a task, such as `tier: deploy` or `team: infra`.
+///
+/// Labels do not change how a task runs. They are used to select tasks, see
+/// [`Dag::run_subset`](crate::Dag::run_subset).
+///
+/// # Example
+///
+/// ```rust
+/// use dagrs::TaskLabels;
+/// let mut labels = TaskLabels::new();
+/// labels.insert("tier", "deploy");
+/// assert_eq!(labels.get("tier"), Some("deploy"));
+/// assert!(!labels.contains("team"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskLabels(BTreeMap<String, String>);
+
+impl TaskLabels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the value of a label, replacing the previous one.
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.0.insert(key.to_owned(), value.to_owned());
+    }
+
+    /// Get the value of a label.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Whether the label is set, whatever its value.
+    pub fn contains(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Whether the label is set to the given value.
+    pub fn matches(&self, key: &str, value: &str) -> bool {
+        self.get(key) == Some(value)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the labels in the order of their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
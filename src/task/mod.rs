@@ -25,19 +25,53 @@
 //! [`Output`] is used to construct and store the output obtained by task execution. [`Input`] is used as a tool
 //! to provide users with the output of the predecessor task.
 use std::fmt::Debug;
-use std::sync::atomic::AtomicUsize;
+use std::sync::{atomic::AtomicUsize, Arc};
+use std::time::Duration;
 
 pub use self::action::{Action, Complex, Simple};
-pub use self::cmd::CommandAction;
+pub(crate) use self::affinity::run_with_affinity;
+pub use self::affinity::ThreadAffinity;
+pub use self::assert::AssertAction;
+pub(crate) use self::cmd::{isolate_commands, kill_running_commands};
+pub use self::cmd::{CommandAction, OutputFormat, RunningError};
+pub(crate) use self::compress::{compress, decompress};
+pub(crate) use self::convert::{convert, convertible};
 pub use self::default_task::DefaultTask;
+pub(crate) use self::gate::cancel_waiting_gates;
+pub use self::gate::{Gate, GateAction};
+pub use self::group::TaskGroup;
+pub use self::labels::TaskLabels;
+pub use self::resource::ResourceRequest;
+pub(crate) use self::resource_usage::take_usage;
+pub use self::resource_usage::TaskResourceUsage;
+pub use self::retry::RetryPolicy;
+pub use self::script::ScriptAction;
 pub use self::state::Content;
 pub(crate) use self::state::ExecState;
-pub use self::state::{Input, Output};
+pub use self::state::{DataType, Input, Output, TaskState};
+pub use self::stream::StreamFlush;
+#[cfg(feature = "wasm")]
+pub use self::wasm::WasmAction;
 
 mod action;
+mod affinity;
+mod assert;
 mod cmd;
+mod compress;
+mod convert;
 mod default_task;
+mod file;
+mod gate;
+mod group;
+mod labels;
+mod resource;
+mod resource_usage;
+mod retry;
+mod script;
 mod state;
+mod stream;
+#[cfg(feature = "wasm")]
+mod wasm;
 /// The Task trait
 ///
 /// Tasks can have many attributes, among which `id`, `name`, `predecessor_tasks`, and
@@ -56,8 +90,82 @@ pub trait Task: Send + Sync {
     fn id(&self) -> usize;
     /// Get the name of this task.
     fn name(&self) -> &str;
+    /// Whether this task is enabled. A disabled task stays in the dag and keeps its
+    /// dependencies, but its action is not executed: it is marked as skipped and its
+    /// successors run as if it had produced an empty output.
+    fn enabled(&self) -> bool {
+        true
+    }
+    /// The type of the output produced by this task, if declared.
+    fn output_type(&self) -> Option<DataType> {
+        None
+    }
+    /// The type this task expects from the outputs of its predecessors, if declared.
+    /// Edges where either side is undeclared are not checked.
+    fn input_type(&self) -> Option<DataType> {
+        None
+    }
+    /// The estimated cost of this task, in a unit chosen by the user. It is used to plan a
+    /// run before executing it, and to start the heaviest of the ready tasks first.
+    fn weight(&self) -> f64 {
+        1.0
+    }
+    /// The labels of this task, used to select a subset of the tasks to run.
+    fn labels(&self) -> TaskLabels {
+        TaskLabels::default()
+    }
+    /// How this task is retried when it fails, if at all. Tasks without a policy of their own
+    /// use the default policy of the dag, if any, see `Dag::set_default_retry`.
+    fn retry(&self) -> Option<RetryPolicy> {
+        None
+    }
+    /// Which failures of this task are retried, whatever the policy in effect. The condition
+    /// and the exit codes of the returned policy, if it has any, replace those of the policy
+    /// of the task or of the dag, while its attempts and backoff are ignored.
+    fn retry_filter(&self) -> Option<RetryPolicy> {
+        None
+    }
+    /// How long an attempt of this task may run before it fails. Tasks without a timeout of
+    /// their own use the default timeout of the dag, if any, see `Dag::set_default_timeout`.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+    /// Whether this task is allowed to fail. The failure of such a task does not fail the dag:
+    /// its successors run as if it had produced an empty output.
+    fn allow_failure(&self) -> bool {
+        false
+    }
+    /// Whether this task is a finalizer, which runs even if the run of the dag is cancelled,
+    /// times out or fails before the task ran. The finalizers that did not run are executed
+    /// one at a time, in the order of the dag, with an empty input since their predecessors
+    /// may not have run. They run like the other tasks, with their retries and the middlewares
+    /// of the dag, and their failures are reported but do not change the outcome of the run.
+    /// A finalizer is skipped if a task it depends on, directly or not, is still running, e.g.
+    /// because it was abandoned when the run timed out.
+    fn run_on_cancel(&self) -> bool {
+        false
+    }
+    /// The resources this task needs while it runs. Tasks request none by default, so they
+    /// are never held back by the resource budget of the dag.
+    fn resource_request(&self) -> ResourceRequest {
+        ResourceRequest::default()
+    }
+    /// The thread the action of this task runs on. Actions run on any thread of the blocking
+    /// thread pool by default.
+    fn thread_affinity(&self) -> ThreadAffinity {
+        ThreadAffinity::Any
+    }
+    /// Whether an output of the action that is not an error is a success. Every such output is
+    /// by default, otherwise the attempt fails when the check returns false.
+    fn success_check(&self) -> Option<Arc<SuccessCheck>> {
+        None
+    }
 }
 
+/// The type of predicate deciding whether an output of the action of a task is a success, see
+/// `Task::success_check`.
+pub type SuccessCheck = dyn Fn(&Output) -> bool + Send + Sync;
+
 /// IDAllocator for DefaultTask
 struct IDAllocator {
     id: AtomicUsize,
@@ -0,0 +1,25 @@
+/// The resources a task needs while it runs, see `Task::resource_request`.
+///
+/// When a resource budget is set with `Dag::set_resource_budget`, a task only starts once its
+/// request fits in what the running tasks leave of the budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceRequest {
+    /// The CPU time, in thousandths of a core.
+    pub cpu_millis: u32,
+    /// The memory, in megabytes.
+    pub memory_mb: u32,
+}
+
+impl ResourceRequest {
+    pub fn new(cpu_millis: u32, memory_mb: u32) -> Self {
+        Self {
+            cpu_millis,
+            memory_mb,
+        }
+    }
+
+    /// Whether the request fits in the given amount of resources.
+    pub fn fits(&self, available: &ResourceRequest) -> bool {
+        self.cpu_millis <= available.cpu_millis && self.memory_mb <= available.memory_mb
+    }
+}
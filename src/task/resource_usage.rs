@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, time::Duration};
+
+/// The resources used by the processes a task spawned, such as the commands of
+/// [`CommandAction`](crate::CommandAction) and the scripts of
+/// [`ScriptAction`](crate::ScriptAction), see `Dag::get_task_resources`.
+///
+/// The usage is only measured on Unix, where it is read from the kernel when the process
+/// exits. A task that spawned several processes, or that was retried, sums their CPU times and
+/// keeps the largest of their peak memory usages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskResourceUsage {
+    /// The CPU time spent by the processes, in user and system mode.
+    pub cpu_time: Duration,
+    /// The peak resident set size of the processes, in bytes.
+    pub max_rss: u64,
+}
+
+impl TaskResourceUsage {
+    /// Add the usage of another process.
+    pub(crate) fn merge(&mut self, other: TaskResourceUsage) {
+        self.cpu_time += other.cpu_time;
+        self.max_rss = self.max_rss.max(other.max_rss);
+    }
+}
+
+thread_local! {
+    /// The usage of the processes spawned on this thread since it was last taken.
+    static USAGE: Cell<Option<TaskResourceUsage>> = const { Cell::new(None) };
+}
+
+/// Record the usage of a process spawned on the current thread.
+pub(crate) fn record_usage(usage: TaskResourceUsage) {
+    USAGE.with(|recorded| {
+        let mut total = recorded.get().unwrap_or_default();
+        total.merge(usage);
+        recorded.set(Some(total));
+    });
+}
+
+/// Take the usage of the processes spawned on the current thread since the last call.
+pub(crate) fn take_usage() -> Option<TaskResourceUsage> {
+    USAGE.with(Cell::take)
+}
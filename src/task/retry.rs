@@ -0,0 +1,110 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use super::Output;
+
+/// The type of predicate deciding whether a failed task is retried.
+type RetryCondition = dyn Fn(&Output) -> bool + Send + Sync;
+
+/// How a failed task is retried.
+///
+/// A task with a [`RetryPolicy`] is executed again when its action returns an error or panics,
+/// up to `attempts` more times. Between two attempts the engine waits for the backoff, which
+/// doubles after each attempt. If a condition is given, only the errors it accepts are retried
+/// and the other ones fail the task immediately: the condition receives the erroneous output,
+/// or `Output::Err("the task panicked")` if the action panicked. If exit codes are given
+/// instead, only the failures of processes exiting with one of them are retried, e.g. the code
+/// 75 (`EX_TEMPFAIL`) by which some tools signal a transient failure.
+///
+/// # Example
+///
+/// ```rust
+/// use dagrs::{DefaultTask, Output, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let mut policy = RetryPolicy::new(3);
+/// policy.set_backoff(Duration::from_millis(100));
+/// let mut task = DefaultTask::with_closure("fetch", |_input, _env| Output::empty());
+/// task.set_retry(policy);
+/// task.set_retry_if(|out| matches!(out, Output::Err(err) if err.contains("timeout")));
+/// ```
+#[derive(Clone, Default)]
+pub struct RetryPolicy {
+    attempts: u32,
+    backoff: Duration,
+    condition: Option<Arc<RetryCondition>>,
+    /// The exit codes retried, which take precedence over the condition.
+    codes: Option<Vec<i32>>,
+}
+
+impl RetryPolicy {
+    /// Retry a failed task up to `attempts` times, immediately and whatever the error.
+    pub fn new(attempts: u32) -> Self {
+        Self {
+            attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Wait for `backoff` before the first retry, and twice as long before each of the next.
+    pub fn set_backoff(&mut self, backoff: Duration) {
+        self.backoff = backoff;
+    }
+
+    /// Only retry the errors for which `condition` returns true.
+    pub fn set_condition(&mut self, condition: impl Fn(&Output) -> bool + Send + Sync + 'static) {
+        self.condition = Some(Arc::new(condition));
+    }
+
+    /// Only retry the failures of commands and scripts that exit with one of `codes`, see
+    /// [`Output::exit_code`]. Failures without an exit code, such as a command killed by a
+    /// signal or a panic, are not retried. The codes take precedence over the condition
+    /// given by `set_condition`.
+    pub fn set_retry_on_codes(&mut self, codes: &[i32]) {
+        self.codes = Some(codes.to_vec());
+    }
+
+    /// The maximum number of retries.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Whether a task that failed with the given output after `attempt` retries is retried.
+    pub(crate) fn should_retry(&self, attempt: u32, out: &Output) -> bool {
+        if attempt >= self.attempts {
+            return false;
+        }
+        match (&self.codes, &self.condition) {
+            (Some(codes), _) => out.exit_code().is_some_and(|code| codes.contains(&code)),
+            (None, Some(cond)) => cond(out),
+            (None, None) => true,
+        }
+    }
+
+    /// This policy with the condition and the exit codes of `filter` instead of its own, if
+    /// `filter` has any, see `Task::retry_filter`.
+    pub(crate) fn filtered_by(mut self, filter: &RetryPolicy) -> Self {
+        if filter.condition.is_some() || filter.codes.is_some() {
+            self.condition = filter.condition.clone();
+            self.codes = filter.codes.clone();
+        }
+        self
+    }
+
+    /// How long to wait before the retry following `attempt` retries.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .checked_mul(2u32.saturating_pow(attempt))
+            .unwrap_or(Duration::MAX)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("attempts", &self.attempts)
+            .field("backoff", &self.backoff)
+            .field("conditional", &self.condition.is_some())
+            .field("codes", &self.codes)
+            .finish()
+    }
+}
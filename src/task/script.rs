@@ -0,0 +1,149 @@
+use crate::{Complex, EnvVar, Input, Output};
+use std::{io::Write, path::PathBuf, process::Command, sync::Arc};
+
+use super::cmd::{execute, set_nice, success_codes_attr, OutputFormat};
+use super::stream::{OutputStream, StreamFlush};
+use crate::task::Content;
+
+/// [`ScriptAction`] is a specific implementation of [`Complex`], used to execute a multi-line
+/// script with an interpreter such as `sh`, `bash` or `python3`.
+///
+/// Before each run the script is written to a temporary file with a unique name, which is
+/// given to the interpreter as its first argument and removed once the interpreter exits. The
+/// file is written to the directory given by the [`EnvVar::SCRIPT_DIR`] variable if it is set,
+/// and kept if [`EnvVar::KEEP_SCRIPTS`] is true, see `Dag::set_script_temp_dir`. As with
+/// [`CommandAction`](crate::CommandAction), the outputs of the predecessor tasks that are
+/// strings are appended to the arguments, and the output of the action holds the lines of the
+/// standard output and standard error, and the script succeeds if the interpreter exits with
+/// one of its success codes, only 0 by default. The standard output can be followed while the
+/// script runs with `set_stream`.
+pub struct ScriptAction {
+    interpreter: String,
+    script: String,
+    format: OutputFormat,
+    success_codes: Vec<i32>,
+    nice: Option<i32>,
+    capture_buffer: Option<usize>,
+    stream: Option<OutputStream>,
+    stream_flush: StreamFlush,
+}
+
+impl ScriptAction {
+    /// Execute `script` with the program `interpreter`.
+    pub fn new(interpreter: &str, script: &str) -> Self {
+        Self {
+            interpreter: interpreter.to_owned(),
+            script: script.to_owned(),
+            format: OutputFormat::Lines,
+            success_codes: vec![0],
+            nice: None,
+            capture_buffer: None,
+            stream: None,
+            stream_flush: StreamFlush::Lines,
+        }
+    }
+
+    /// Set how the standard output of the script is turned into the output of the action.
+    /// If it cannot be parsed in the given format, the action fails.
+    pub fn set_output_format(&mut self, format: OutputFormat) {
+        self.format = format;
+    }
+
+    /// Set the exit codes of the interpreter for which the script succeeds, `[0]` by default.
+    /// The script fails with any other code.
+    pub fn set_success_codes(&mut self, codes: &[i32]) {
+        self.success_codes = codes.to_vec();
+    }
+
+    /// Run the interpreter at the given nice value, see `CommandAction::set_nice`.
+    pub fn set_nice(&mut self, level: i32) {
+        self.nice = Some(level);
+    }
+
+    /// Read the standard output and standard error of the interpreter `bytes` at a time, see
+    /// `CommandAction::set_capture_buffer`.
+    pub fn set_capture_buffer(&mut self, bytes: usize) {
+        self.capture_buffer = Some(bytes);
+    }
+
+    /// Pass the lines of the standard output of the script to `observer` while it runs, see
+    /// `CommandAction::set_stream`.
+    pub fn set_stream(&mut self, observer: impl Fn(&[String]) + Send + Sync + 'static) {
+        self.stream = Some(OutputStream::new(observer));
+    }
+
+    /// Set how often the streamed lines are passed on, every line by default.
+    pub fn set_stream_flush(&mut self, flush: StreamFlush) {
+        self.stream_flush = flush;
+    }
+}
+
+impl Complex for ScriptAction {
+    fn run(&self, input: Input, env: Arc<EnvVar>) -> Output {
+        let dir = env
+            .get_ref::<PathBuf>(EnvVar::SCRIPT_DIR)
+            .cloned()
+            .unwrap_or_else(std::env::temp_dir);
+        let written = tempfile::Builder::new()
+            .prefix("dagrs-script-")
+            .tempfile_in(dir)
+            .and_then(|mut file| {
+                file.write_all(self.script.as_bytes())?;
+                Ok(file.into_temp_path())
+            });
+        let path = match written {
+            Ok(path) => path,
+            Err(err) => {
+                return Output::error_with_exit_code(
+                    err.raw_os_error(),
+                    Some(Content::new(err.to_string())),
+                )
+            }
+        };
+
+        let mut cmd = Command::new(&self.interpreter);
+        cmd.arg(&path);
+        input.get_iter().for_each(|input| {
+            if let Some(inp) = input.get::<String>() {
+                cmd.arg(inp);
+            } else if let Some(path) = input.path() {
+                cmd.arg(path);
+            }
+        });
+        cmd.envs(env.exported());
+        set_nice(&mut cmd, self.nice);
+        log::debug!("script: {:?}, interpreter: {}", path, self.interpreter);
+        let stream = self
+            .stream
+            .as_ref()
+            .map(|stream| (stream, self.stream_flush));
+        let out = execute(
+            cmd,
+            self.format,
+            &self.success_codes,
+            stream,
+            self.capture_buffer,
+        );
+        if env.get::<bool>(EnvVar::KEEP_SCRIPTS) == Some(true) {
+            match path.keep() {
+                Ok(path) => log::info!("Kept the script file {:?}", path),
+                Err(err) => log::warn!("Failed to keep the script file: {}", err),
+            }
+        }
+        out
+    }
+
+    fn config(&self) -> Option<Vec<(&'static str, serde_json::Value)>> {
+        let mut attrs = vec![
+            ("script", self.script.clone().into()),
+            ("lang", self.interpreter.clone().into()),
+        ];
+        if let Some(name) = self.format.name() {
+            attrs.push(("output_type", name.into()));
+        }
+        if let Some(codes) = success_codes_attr(&self.success_codes) {
+            attrs.push(("success_codes", codes));
+        }
+        Some(attrs)
+    }
+}
@@ -36,20 +36,27 @@
 //! to implement the logic of the program.
 
 use std::{
-    any::Any,
+    any::{Any, TypeId},
+    path::{Path, PathBuf},
     slice::Iter,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, Weak,
     },
+    time::{Duration, Instant},
 };
 
+use serde::{Deserialize, Serialize};
+
+use super::{decompress, file::FileRef, RunningError, TaskResourceUsage};
 use tokio::sync::Semaphore;
 
 /// Container type to store task output.
 #[derive(Debug, Clone)]
 pub struct Content {
     content: Arc<dyn Any + Send + Sync>,
+    /// The name of the type of the value.
+    type_name: &'static str,
 }
 
 impl Content {
@@ -57,19 +64,212 @@ impl Content {
     pub fn new<H: Send + Sync + 'static>(val: H) -> Self {
         Self {
             content: Arc::new(val),
+            type_name: std::any::type_name::<H>(),
         }
     }
 
     pub fn from_arc<H: Send + Sync + 'static>(val: Arc<H>) -> Self {
-        Self { content: val }
+        Self {
+            content: val,
+            type_name: std::any::type_name::<H>(),
+        }
+    }
+
+    /// Construct a [`Content`] whose value is computed by `compute` the first time it is read,
+    /// see [`Output::lazy`].
+    pub fn lazy<H: Send + Sync + 'static>(compute: impl FnOnce() -> H + Send + 'static) -> Self {
+        Self {
+            type_name: std::any::type_name::<H>(),
+            ..Self::new(Lazy {
+                value: OnceLock::new(),
+                compute: Mutex::new(Some(Box::new(move || Content::new(compute())))),
+                opaque: true,
+            })
+        }
+    }
+
+    /// Construct a [`Content`] that stands for the content computed by `compute` the first
+    /// time it is read. `type_name` is the name of the type of the computed value. Unlike
+    /// [`Content::lazy`], the engine computes it whenever it needs the value.
+    pub(crate) fn deferred(
+        type_name: &'static str,
+        compute: impl FnOnce() -> Content + Send + 'static,
+    ) -> Self {
+        Self {
+            type_name,
+            ..Self::new(Lazy {
+                value: OnceLock::new(),
+                compute: Mutex::new(Some(Box::new(compute))),
+                opaque: false,
+            })
+        }
+    }
+
+    /// A reference to the value of the content that does not keep it alive.
+    pub(crate) fn downgrade(&self) -> WeakContent {
+        WeakContent {
+            content: Arc::downgrade(&self.content),
+            type_name: self.type_name,
+        }
+    }
+
+    /// The name of the type of the value, e.g. to tell why [`Content::get`] returned None. It
+    /// is only meant for diagnostics, since the names of the types are not stable.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
     }
 
     pub fn get<H: 'static>(&self) -> Option<&H> {
-        self.content.downcast_ref::<H>()
+        match self.content.downcast_ref::<Lazy>() {
+            Some(lazy) => lazy.force().get::<H>(),
+            None => self.content.downcast_ref::<H>(),
+        }
     }
 
     pub fn into_inner<H: Send + Sync + 'static>(self) -> Option<Arc<H>> {
-        self.content.downcast::<H>().ok()
+        match self.content.downcast_ref::<Lazy>() {
+            Some(lazy) => lazy.force().clone().into_inner::<H>(),
+            None => self.content.downcast::<H>().ok(),
+        }
+    }
+
+    /// The path of the file held by the content, if it was output with [`Output::file`] or
+    /// [`Output::temp_file`].
+    pub fn path(&self) -> Option<&Path> {
+        self.get::<FileRef>().map(FileRef::path)
+    }
+
+    /// Whether the content is lazy and has not been computed yet. The engine does not look
+    /// into such contents, so that they are only computed if a task or the user reads them.
+    pub(crate) fn is_pending(&self) -> bool {
+        self.content
+            .downcast_ref::<Lazy>()
+            .is_some_and(|lazy| lazy.opaque && lazy.value.get().is_none())
+    }
+
+    /// Convert the content into a JSON value.
+    ///
+    /// Strings, booleans, numbers, byte vectors, [`serde_json::Value`] and the
+    /// `(stdout, stderr)` lines produced by `CommandAction` are supported, as well as
+    /// contents nested in a [`Content`]. Returns None for any other type.
+    pub fn to_json(&self) -> Option<serde_json::Value> {
+        use serde_json::{json, Value};
+
+        if let Some(inner) = self.get::<Content>() {
+            return inner.to_json();
+        }
+        macro_rules! try_as {
+            ($($ty:ty),*) => {
+                $(if let Some(val) = self.get::<$ty>() {
+                    return Some(json!(val));
+                })*
+            };
+        }
+
+        if let Some(val) = self.get::<Value>() {
+            return Some(val.clone());
+        }
+        if let Some((stdout, stderr)) = self.get::<(Vec<String>, Vec<String>)>() {
+            return Some(json!({ "stdout": stdout, "stderr": stderr }));
+        }
+        if let Some(path) = self.path() {
+            return Some(json!(path));
+        }
+        try_as!(
+            String,
+            &'static str,
+            bool,
+            i32,
+            i64,
+            u32,
+            u64,
+            usize,
+            f32,
+            f64,
+            Vec<u8>
+        );
+        None
+    }
+}
+
+/// The value of a lazy [`Content`], computed once by the first reader. The other readers wait
+/// for it and share the same value.
+/// A [`Content`] that does not keep its value alive, see [`Content::downgrade`].
+#[derive(Debug)]
+pub(crate) struct WeakContent {
+    content: Weak<dyn Any + Send + Sync>,
+    type_name: &'static str,
+}
+
+impl WeakContent {
+    /// The content, if its value is still alive.
+    pub(crate) fn upgrade(&self) -> Option<Content> {
+        Some(Content {
+            content: self.content.upgrade()?,
+            type_name: self.type_name,
+        })
+    }
+}
+
+struct Lazy {
+    value: OnceLock<Content>,
+    compute: Mutex<Option<Box<dyn FnOnce() -> Content + Send>>>,
+    /// Whether the engine leaves the content alone until it is computed, see
+    /// `Content::is_pending`.
+    opaque: bool,
+}
+
+impl Lazy {
+    fn force(&self) -> &Content {
+        self.value.get_or_init(|| {
+            let compute = self.compute.lock().unwrap().take();
+            compute.expect("the computation of the lazy content panicked")()
+        })
+    }
+}
+
+/// The execution state of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaskState {
+    /// The task has not started yet.
+    Pending,
+    /// The action of the task is being executed.
+    Running,
+    /// The action of the task returned a normal output.
+    Succeeded,
+    /// The action of the task returned an error or panicked.
+    Failed,
+    /// The action of the task returned an error or panicked, but the task is allowed to fail,
+    /// so its successors ran anyway and the dag can still succeed.
+    FailedAllowed,
+    /// The action of the task was not executed, either because the task is disabled or
+    /// because the dag stopped before reaching it.
+    Skipped,
+}
+
+/// The type of the values flowing along the edges of a dag.
+///
+/// Since the content of [`Output`] is type-erased, tasks may declare the type they produce and
+/// the type they expect from their predecessors, so that mismatches are caught when the dag is
+/// initialized rather than showing up as `None` at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataType {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl DataType {
+    /// The [`DataType`] of `T`.
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            id: TypeId::of::<T>(),
+            name: std::any::type_name::<T>(),
+        }
+    }
+
+    /// The name of the type.
+    pub fn name(&self) -> &'static str {
+        self.name
     }
 }
 
@@ -80,8 +280,26 @@ impl Content {
 pub(crate) struct ExecState {
     /// The execution succeed or not.
     success: AtomicBool,
+    /// The current execution state of the task.
+    state: Mutex<TaskState>,
     /// Output produced by a task.
     output: Arc<Mutex<Output>>,
+    /// The content last returned by `get_output`, so that the readers of a compressed output
+    /// share its decompressed value while any of them holds it.
+    shared_output: Mutex<Option<WeakContent>>,
+    /// The error message of a failed task.
+    error: Mutex<Option<String>>,
+    /// How long the action of the task ran.
+    duration: Mutex<Option<Duration>>,
+    /// When the action of the task started, or when the task was skipped.
+    started: Mutex<Option<Instant>>,
+    /// The resources used by the processes the action spawned.
+    resources: Mutex<Option<TaskResourceUsage>>,
+    /// The input given to the task, if retained.
+    input: Mutex<Option<Input>>,
+    /// The number of successors that have yet to read the output, when it is dropped once
+    /// they all did.
+    consumers: AtomicUsize,
     /// The semaphore is used to control the synchronous blocking of subsequent tasks to obtain the
     /// execution results of this task.
     /// When a task is successfully executed, the permits inside the semaphore will be increased to
@@ -93,16 +311,34 @@ pub(crate) struct ExecState {
 }
 
 /// Output produced by a task.
+///
+/// Variants may be added in minor releases, so a `match` on an output needs a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Output {
     Out(Option<Content>),
+    /// A successful output of a process that exited with the given code, see
+    /// [`Output::exit_code`].
+    OutWithExitCode(i32, Option<Content>),
     Err(String),
     ErrWithExitCode(Option<i32>, Option<Content>),
+    /// A successful output that also ends the run of the dag, see [`Output::finish`].
+    Finish(Content),
 }
 
 /// Task's input value.
-#[derive(Debug)]
-pub struct Input(Vec<Content>);
+///
+/// It holds the outputs of the predecessor tasks in the order the predecessors were declared
+/// in, e.g. with `set_predecessors` or `after`. Predecessors that produced no output are left
+/// out, so the output of a given predecessor is best looked up with [`Input::get_from`].
+#[derive(Debug, Clone)]
+pub struct Input {
+    contents: Vec<Content>,
+    /// The id of the task that produced each content, if known.
+    sources: Vec<usize>,
+    /// Whether the contents were accessed, by this input or one of its clones.
+    read: Arc<AtomicBool>,
+}
 
 impl ExecState {
     /// Construct a new [`ExeState`].
@@ -110,7 +346,15 @@ impl ExecState {
         // initialize the task to failure without output.
         Self {
             success: AtomicBool::new(false),
+            state: Mutex::new(TaskState::Pending),
             output: Arc::new(Mutex::new(Output::empty())),
+            shared_output: Mutex::new(None),
+            error: Mutex::new(None),
+            duration: Mutex::new(None),
+            started: Mutex::new(None),
+            resources: Mutex::new(None),
+            input: Mutex::new(None),
+            consumers: AtomicUsize::new(0),
             semaphore: Semaphore::new(0),
         }
     }
@@ -118,13 +362,24 @@ impl ExecState {
     /// After the task is successfully executed, set the execution result.
     pub(crate) fn set_output(&self, output: Output) {
         self.success.store(true, Ordering::Relaxed);
-        *self.output.lock().unwrap() = output;
+        let mut stored = self.output.lock().unwrap();
+        *stored = output;
+        *self.shared_output.lock().unwrap() = None;
     }
 
     /// [`Output`] for fetching internal storage.
     /// This function is generally not called directly, but first uses the semaphore for synchronization control.
+    /// A compressed output is decompressed the first time it is read, once for all the readers
+    /// that hold it at the same time.
     pub(crate) fn get_output(&self) -> Option<Content> {
-        self.output.lock().unwrap().get_out()
+        let output = self.output.lock().unwrap();
+        let mut shared = self.shared_output.lock().unwrap();
+        if let Some(content) = shared.as_ref().and_then(WeakContent::upgrade) {
+            return Some(content);
+        }
+        let content = output.get_out().map(decompress)?;
+        *shared = Some(content.downgrade());
+        Some(content)
     }
 
     /// The task execution succeed or not.
@@ -141,6 +396,89 @@ impl ExecState {
         self.success.store(false, Ordering::Relaxed)
     }
 
+    /// Record the error message of a failed task.
+    pub(crate) fn set_error(&self, err: String) {
+        *self.error.lock().unwrap() = Some(err);
+    }
+
+    /// Get the error message of a failed task.
+    pub(crate) fn get_error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// Record how long the action of the task ran.
+    pub(crate) fn set_duration(&self, duration: Duration) {
+        *self.duration.lock().unwrap() = Some(duration);
+    }
+
+    /// Get how long the action of the task ran, if it has run.
+    pub(crate) fn duration(&self) -> Option<Duration> {
+        *self.duration.lock().unwrap()
+    }
+
+    /// Record that the action of the task starts, or that the task is skipped, now.
+    pub(crate) fn mark_started(&self) -> Instant {
+        let now = Instant::now();
+        *self.started.lock().unwrap() = Some(now);
+        now
+    }
+
+    /// When the action of the task started, or when the task was skipped.
+    pub(crate) fn started(&self) -> Option<Instant> {
+        *self.started.lock().unwrap()
+    }
+
+    /// Add the resources used by processes the action spawned.
+    pub(crate) fn add_resources(&self, usage: TaskResourceUsage) {
+        self.resources
+            .lock()
+            .unwrap()
+            .get_or_insert_with(TaskResourceUsage::default)
+            .merge(usage);
+    }
+
+    /// Get the resources used by the processes the action spawned, if it spawned any.
+    pub(crate) fn resources(&self) -> Option<TaskResourceUsage> {
+        *self.resources.lock().unwrap()
+    }
+
+    /// Drop the output once the given number of successors read it with `consume`.
+    pub(crate) fn set_consumers(&self, count: usize) {
+        self.consumers.store(count, Ordering::Relaxed);
+    }
+
+    /// Record that a successor read the output, and drop it if it was the last one.
+    pub(crate) fn consume(&self) {
+        let left = self
+            .consumers
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+        if left == Ok(1) {
+            let mut stored = self.output.lock().unwrap();
+            *stored = Output::empty();
+            *self.shared_output.lock().unwrap() = None;
+        }
+    }
+
+    /// Retain the input given to the task.
+    pub(crate) fn set_input(&self, input: Input) {
+        *self.input.lock().unwrap() = Some(input);
+    }
+
+    /// Get the input given to the task, if it was retained.
+    pub(crate) fn input(&self) -> Option<Input> {
+        self.input.lock().unwrap().clone()
+    }
+
+    /// Get the current execution state of the task.
+    pub(crate) fn state(&self) -> TaskState {
+        *self.state.lock().unwrap()
+    }
+
+    /// Update the execution state of the task.
+    pub(crate) fn set_state(&self, state: TaskState) {
+        *self.state.lock().unwrap() = state;
+    }
+
     /// The semaphore is used to control the synchronous acquisition of task output results.
     /// Under normal circumstances, first use the semaphore to obtain a permit, and then call
     /// the `get_output` function to obtain the output. If the current task is not completed
@@ -160,11 +498,117 @@ impl Output {
         Self::Out(Some(Content::new(val)))
     }
 
+    /// Construct an [`Output`] whose value is computed by `compute` only when it is first read,
+    /// e.g. with [`Content::get`] by a successor.
+    ///
+    /// If no successor reads it, because they were skipped or disabled, or because they do not
+    /// look at their input, the value is never computed. It is computed at most once: when
+    /// several successors read it, the first one computes it, the others wait for it if it is
+    /// being computed, and they all share the same value. A panic of `compute` happens in the
+    /// first reader, and the later ones panic as well.
+    ///
+    /// The engine does not compute the value to record the events of the run, compress the
+    /// output, write the logs of the task or give it to the successors as an environment
+    /// variable, see `Dag::set_inputs_as_env`, so these do not show it unless a reader
+    /// computed it before. It does compute it to convert it into the input type declared by a
+    /// successor, see `Dag::set_convert_inputs`, since that successor reads it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output};
+    ///
+    /// let produce = DefaultTask::with_closure("produce", |_input, _env| {
+    ///     Output::lazy(|| (1..=10usize).sum::<usize>())
+    /// });
+    /// let mut dag = Dag::with_tasks(vec![produce]);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 55);
+    /// ```
+    pub fn lazy<H: Send + Sync + 'static>(compute: impl FnOnce() -> H + Send + 'static) -> Self {
+        Self::Out(Some(Content::lazy(compute)))
+    }
+
+    /// Construct an [`Output`] holding the path of a file rather than its content, so that
+    /// large data is passed to the successors on disk. The successors get the path with
+    /// [`Input::paths`] or [`Content::path`], and commands and scripts receive it as an
+    /// argument.
+    ///
+    /// The engine only passes the path along: the file belongs to the task that wrote it and
+    /// is never removed by the engine, see [`Output::temp_file`] for files the engine removes.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self::Out(Some(Content::new(FileRef::new(path.into(), false))))
+    }
+
+    /// Construct an [`Output`] holding the path of a file, like [`Output::file`], that is
+    /// owned by the output: the file is removed once the output is dropped along with every
+    /// input holding it.
+    ///
+    /// By default the dag keeps the outputs of the tasks, so the file is removed when the dag
+    /// is dropped. With `Dag::set_eager_output_drop`, it is removed as soon as all the
+    /// successors of the task ran, unless it is the result of the dag or the inputs are
+    /// retained. The file should not be written to the temporary directory of the task,
+    /// which is removed when the task finishes.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Input, Output};
+    ///
+    /// let dir = std::env::temp_dir().join("dagrs-temp-file-example");
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// let path = dir.join("data.txt");
+    /// let written = path.clone();
+    /// let write = DefaultTask::with_closure("write", move |_input, _env| {
+    ///     std::fs::write(&written, "large data").unwrap();
+    ///     Output::temp_file(&written)
+    /// });
+    /// let mut read = DefaultTask::with_closure("read", |input: Input, _env| {
+    ///     let path = input.paths().next().unwrap();
+    ///     Output::new(std::fs::read_to_string(path).unwrap().len())
+    /// });
+    /// read.set_predecessors(&[&write]);
+    /// let mut dag = Dag::with_tasks(vec![write, read]);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 10);
+    /// drop(dag);
+    /// assert!(!path.exists());
+    /// ```
+    pub fn temp_file(path: impl Into<PathBuf>) -> Self {
+        Self::Out(Some(Content::new(FileRef::new(path.into(), true))))
+    }
+
     /// Construct an empty [`Output`].
     pub fn empty() -> Self {
         Self::Out(None)
     }
 
+    /// Construct an [`Output`] that ends the run of the dag with the given value.
+    ///
+    /// Once a task finishes the dag, the tasks that have not started yet are skipped, and
+    /// `Dag::start` returns `Ok(true)` without waiting for them. The tasks already running are
+    /// not interrupted: the dag waits for them, but ignores their outputs and failures.
+    /// The value becomes the result of the dag, returned by `Dag::get_result`, and the successors
+    /// of the task do not run.
+    ///
+    /// A task can only finish a dag that may still succeed: if another task failed first, the
+    /// dag fails as usual and the value is treated as a regular output. Since the dag succeeds,
+    /// the failure handler does not run, while the hooks registered by `Dag::on_complete` do.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, Output, TaskState};
+    ///
+    /// let search = DefaultTask::with_closure("search", |_input, _env| Output::finish(42usize));
+    /// let mut refine = DefaultTask::with_closure("refine", |_input, _env| Output::new(0usize));
+    /// refine.set_predecessors(&[&search]);
+    /// let mut dag = Dag::with_tasks(vec![search, refine]);
+    /// assert!(dag.start().unwrap());
+    /// assert_eq!(*dag.get_result::<usize>().unwrap(), 42);
+    /// assert_eq!(dag.task_state("refine"), Some(TaskState::Skipped));
+    /// ```
+    pub fn finish<H: Send + Sync + 'static>(val: H) -> Self {
+        Self::Finish(Content::new(val))
+    }
+
     /// Construct an [`Output`]` with an error message.
     pub fn error(msg: String) -> Self {
         Self::Err(msg)
@@ -175,30 +619,77 @@ impl Output {
         Self::ErrWithExitCode(code, msg)
     }
 
+    /// Construct a successful [`Output`] of a process that exited with the given code.
+    pub fn with_exit_code(code: i32, out: Option<Content>) -> Self {
+        Self::OutWithExitCode(code, out)
+    }
+
+    /// The exit code of the process that produced the [`Output`], if any.
+    ///
+    /// Commands and scripts report the code they exited with, whether they succeeded or not:
+    /// a code listed by `set_success_codes` gives a successful output that still holds it. A
+    /// command that could not be started reports the error code of the operating system
+    /// instead, and one killed by a signal has no code.
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            Self::OutWithExitCode(code, _) => Some(*code),
+            Self::ErrWithExitCode(code, _) => *code,
+            Self::Out(_) | Self::Err(_) | Self::Finish(_) => None,
+        }
+    }
+
+    /// The error of a command or script that could not run, such as a missing program, as
+    /// opposed to one that ran and failed.
+    pub fn running_error(&self) -> Option<&RunningError> {
+        match self {
+            Self::ErrWithExitCode(_, Some(err)) => err.get::<RunningError>(),
+            _ => None,
+        }
+    }
+
+    /// Determine whether [`Output`] ends the run of the dag.
+    pub fn is_finish(&self) -> bool {
+        matches!(self, Self::Finish(_))
+    }
+
     /// Determine whether [`Output`] stores error information.
-    pub(crate) fn is_err(&self) -> bool {
+    pub fn is_err(&self) -> bool {
         match self {
             Self::Err(_) | Self::ErrWithExitCode(_, _) => true,
-            Self::Out(_) => false,
+            Self::Out(_) | Self::OutWithExitCode(_, _) | Self::Finish(_) => false,
         }
     }
 
     /// Get the contents of [`Output`].
     pub(crate) fn get_out(&self) -> Option<Content> {
         match self {
-            Self::Out(ref out) => out.clone(),
+            Self::Out(ref out) | Self::OutWithExitCode(_, ref out) => out.clone(),
+            Self::Finish(ref out) => Some(out.clone()),
             Self::Err(_) | Self::ErrWithExitCode(_, _) => None,
         }
     }
 
+    /// Transform the contents of [`Output`], keeping its variant and exit code.
+    pub(crate) fn map_out(self, f: impl FnOnce(Content) -> Content) -> Self {
+        match self {
+            Self::Out(out) => Self::Out(out.map(f)),
+            Self::OutWithExitCode(code, out) => Self::OutWithExitCode(code, out.map(f)),
+            Self::Finish(out) => Self::Finish(f(out)),
+            Self::Err(_) | Self::ErrWithExitCode(_, _) => self,
+        }
+    }
+
     /// Get error information stored in [`Output`].
     pub(crate) fn get_err(&self) -> Option<String> {
         match self {
-            Self::Out(_) => None,
+            Self::Out(_) | Self::OutWithExitCode(_, _) | Self::Finish(_) => None,
             Self::Err(err) => Some(err.to_string()),
             Self::ErrWithExitCode(_, err) => {
                 if let Some(e) = err {
-                    Some(e.get::<String>()?.to_string())
+                    match e.get::<RunningError>() {
+                        Some(running) => Some(running.to_string()),
+                        None => Some(e.get::<String>()?.to_string()),
+                    }
                 } else {
                     None
                 }
@@ -210,12 +701,48 @@ impl Output {
 impl Input {
     /// Constructs input using output produced by a non-empty predecessor task.
     pub fn new(input: Vec<Content>) -> Self {
-        Self(input)
+        Self {
+            contents: input,
+            sources: Vec::new(),
+            read: Arc::default(),
+        }
+    }
+
+    /// Constructs input from the outputs of the predecessors, along with their ids.
+    pub(crate) fn from_predecessors(input: Vec<(usize, Content)>) -> Self {
+        let (sources, contents) = input.into_iter().unzip();
+        Self {
+            contents,
+            sources,
+            read: Arc::default(),
+        }
+    }
+
+    /// Whether the input holds contents that were never accessed.
+    pub(crate) fn unread(&self) -> bool {
+        !self.contents.is_empty() && !self.read.load(Ordering::Relaxed)
     }
 
     /// Since [`Input`] can contain multi-input values, and it's implemented
     /// by [`Vec`] actually, of course it can be turned into a iterator.
-    pub fn get_iter(&self) -> Iter<Content> {
-        self.0.iter()
+    /// The outputs are iterated in the order the predecessors were declared in.
+    pub fn get_iter(&self) -> Iter<'_, Content> {
+        self.read.store(true, Ordering::Relaxed);
+        self.contents.iter()
+    }
+
+    /// The paths of the files output by the predecessors with [`Output::file`] or
+    /// [`Output::temp_file`], in the order the predecessors were declared in.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.get_iter().filter_map(Content::path)
+    }
+
+    /// Get the output of the predecessor task with the given id, if it produced one.
+    pub fn get_from(&self, task_id: usize) -> Option<&Content> {
+        self.read.store(true, Ordering::Relaxed);
+        self.sources
+            .iter()
+            .position(|id| *id == task_id)
+            .map(|index| &self.contents[index])
     }
 }
@@ -0,0 +1,126 @@
+//! Streaming of the standard output of commands and scripts while they run.
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The type of function receiving the lines streamed by a command or a script.
+type LineObserver = dyn Fn(&[String]) + Send + Sync;
+
+/// When the lines of the standard output buffered by a stream are passed on to its observer.
+///
+/// Whatever the policy, the lines left in the buffer are passed on when the process exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamFlush {
+    /// Pass on every line as soon as it is written.
+    #[default]
+    Lines,
+    /// Pass on the buffered lines every time the given time has passed, from a timer of its
+    /// own, so the last lines of a process that goes quiet are not held back until it writes
+    /// again or exits. Nothing is passed on when no line was written in the meantime.
+    Interval(Duration),
+    /// Pass on the buffered lines once they hold at least the given number of bytes.
+    Bytes(usize),
+}
+
+/// The function the lines of the standard output of a process are streamed to, see
+/// `CommandAction::set_stream`.
+#[derive(Clone)]
+pub(crate) struct OutputStream(Arc<LineObserver>);
+
+impl OutputStream {
+    pub(crate) fn new(observer: impl Fn(&[String]) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(observer))
+    }
+
+    /// Read the output until its end, `buffer` bytes at a time if given, passing its lines on
+    /// to the observer as the policy says, and return all the bytes read.
+    pub(crate) fn forward(
+        &self,
+        output: impl Read,
+        flush: StreamFlush,
+        buffer: Option<usize>,
+    ) -> Vec<u8> {
+        let mut reader = match buffer {
+            Some(size) => BufReader::with_capacity(size.max(1), output),
+            None => BufReader::new(output),
+        };
+        if let StreamFlush::Interval(interval) = flush {
+            return self.forward_every(reader, interval);
+        }
+        let mut read = Vec::new();
+        let mut buffer = Vec::new();
+        let mut bytes = 0;
+        while let Some(line) = read_line(&mut reader, &mut read) {
+            bytes += line.len();
+            buffer.push(line);
+            let full = match flush {
+                // The intervals are handled by `forward_every`.
+                StreamFlush::Lines | StreamFlush::Interval(_) => true,
+                StreamFlush::Bytes(size) => bytes >= size,
+            };
+            if full {
+                (self.0)(&buffer);
+                buffer.clear();
+                bytes = 0;
+            }
+        }
+        if !buffer.is_empty() {
+            (self.0)(&buffer);
+        }
+        read
+    }
+
+    /// Read the output until its end like `forward`, while a timer thread passes the buffered
+    /// lines on to the observer every `interval`.
+    fn forward_every(&self, mut reader: impl BufRead, interval: Duration) -> Vec<u8> {
+        // The buffered lines, and whether the output ended.
+        let pending = Mutex::new((Vec::<String>::new(), false));
+        let wake = Condvar::new();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut deadline = Instant::now() + interval;
+                let mut state = pending.lock().unwrap();
+                loop {
+                    let now = Instant::now();
+                    if now < deadline && !state.1 {
+                        state = wake.wait_timeout(state, deadline - now).unwrap().0;
+                        continue;
+                    }
+                    if !state.0.is_empty() {
+                        let lines = std::mem::take(&mut state.0);
+                        drop(state);
+                        (self.0)(&lines);
+                        state = pending.lock().unwrap();
+                    }
+                    if state.1 {
+                        break;
+                    }
+                    deadline = Instant::now() + interval;
+                }
+            });
+            let mut read = Vec::new();
+            while let Some(line) = read_line(&mut reader, &mut read) {
+                pending.lock().unwrap().0.push(line);
+            }
+            pending.lock().unwrap().1 = true;
+            wake.notify_one();
+            read
+        })
+    }
+}
+
+/// Read the next line of the output, appending its bytes to `read`, or None at its end.
+fn read_line(reader: &mut impl BufRead, read: &mut Vec<u8>) -> Option<String> {
+    let start = read.len();
+    match reader.read_until(b'\n', read) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => {
+            let line = String::from_utf8_lossy(&read[start..]);
+            Some(line.trim_end_matches(['\n', '\r']).to_string())
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{Complex, EnvVar, Input, Output};
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// [`WasmAction`] is a specific implementation of [`Complex`], used to execute a function exported
+/// by a WebAssembly module in a sandbox.
+///
+/// Each run instantiates the module in a fresh store, so no state is shared between runs or
+/// with the host. The module must follow a small calling convention:
+/// - It exports its linear memory as `memory`.
+/// - It exports `alloc(len: i32) -> i32`, returning a pointer to `len` writable bytes.
+/// - The entry function has the signature `(ptr: i32, len: i32) -> i64`. It receives the
+///   input, and returns the location of the output packed as `(out_ptr << 32) | out_len`.
+///
+/// The input is a JSON array holding the outputs of the predecessor tasks, where outputs of
+/// types that cannot be represented in JSON are `null`. The bytes returned by the entry
+/// function are stored as a `Vec<u8>` output. Traps and aborts of the module are converted
+/// into [`Output::Err`].
+///
+/// Each run is given a fuel budget, consumed by the executed instructions, and a cap on the
+/// linear memory of the module, so that a module that loops forever or allocates without
+/// bounds fails its task instead of hanging it or exhausting the host. See
+/// [`WasmAction::set_fuel`] and [`WasmAction::set_memory_limit`].
+///
+/// This action is only available with the `wasm` feature.
+pub struct WasmAction {
+    source: WasmSource,
+    function: String,
+    engine: Engine,
+    /// The module is compiled on the first run and reused afterwards.
+    module: OnceLock<Result<Module, String>>,
+    /// The fuel given to each run.
+    fuel: u64,
+    /// The maximum size of the linear memory, in bytes.
+    memory_limit: usize,
+}
+
+/// Where the module comes from.
+enum WasmSource {
+    File(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+impl WasmAction {
+    /// Execute the function `function` exported by the module stored in the given file.
+    pub fn new(module_path: &str, function: &str) -> Self {
+        Self::with_source(WasmSource::File(module_path.into()), function)
+    }
+
+    /// Execute the function `function` exported by the module given in binary or text format.
+    pub fn from_bytes(module: impl Into<Vec<u8>>, function: &str) -> Self {
+        Self::with_source(WasmSource::Bytes(module.into()), function)
+    }
+
+    fn with_source(source: WasmSource, function: &str) -> Self {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Self {
+            source,
+            function: function.to_owned(),
+            engine: Engine::new(&config).expect("the wasm engine configuration is valid"),
+            module: OnceLock::new(),
+            fuel: 1_000_000_000,
+            memory_limit: 256 << 20,
+        }
+    }
+
+    /// Set the fuel given to each run, about one unit per executed instruction. A run that
+    /// consumes all of it traps. Defaults to one billion.
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = fuel;
+    }
+
+    /// Set the maximum size of the linear memory of the module, in bytes. Growing the memory
+    /// beyond it fails. Defaults to 256 MiB.
+    pub fn set_memory_limit(&mut self, bytes: usize) {
+        self.memory_limit = bytes;
+    }
+
+    fn module(&self) -> Result<&Module, String> {
+        self.module
+            .get_or_init(|| {
+                match &self.source {
+                    WasmSource::File(path) => Module::from_file(&self.engine, path),
+                    WasmSource::Bytes(bytes) => Module::new(&self.engine, bytes),
+                }
+                .map_err(|err| format!("failed to load wasm module: {}", err))
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    fn call(&self, input: &[u8]) -> Result<Vec<u8>, String> {
+        let module = self.module()?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.memory_limit)
+            .build();
+        let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel).map_err(|err| err.to_string())?;
+        let instance = Instance::new(&mut store, module, &[]).map_err(|err| err.to_string())?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or("the wasm module does not export 'memory'")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(|err| err.to_string())?;
+        let function = instance
+            .get_typed_func::<(i32, i32), i64>(&mut store, &self.function)
+            .map_err(|err| err.to_string())?;
+
+        let len = i32::try_from(input.len()).map_err(|err| err.to_string())?;
+        let ptr = alloc
+            .call(&mut store, len)
+            .map_err(|err| format!("wasm trap: {:#}", err))?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(|err| err.to_string())?;
+
+        let packed = function
+            .call(&mut store, (ptr, len))
+            .map_err(|err| format!("wasm trap: {:#}", err))? as u64;
+        let (out_ptr, out_len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        // The length comes from the module, so it is checked before anything is allocated.
+        out_ptr
+            .checked_add(out_len)
+            .and_then(|end| memory.data(&store).get(out_ptr..end))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| {
+                format!(
+                    "the output of {} bytes at {} is out of the bounds of the memory",
+                    out_len, out_ptr
+                )
+            })
+    }
+}
+
+impl Complex for WasmAction {
+    fn run(&self, input: Input, _env: Arc<EnvVar>) -> Output {
+        let input: Vec<serde_json::Value> = input
+            .get_iter()
+            .map(|content| content.to_json().unwrap_or_default())
+            .collect();
+        let input = serde_json::to_vec(&input).unwrap();
+        match self.call(&input) {
+            Ok(out) => Output::new(out),
+            Err(err) => Output::error(err),
+        }
+    }
+}
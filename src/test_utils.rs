@@ -0,0 +1,84 @@
+//! Helpers for testing pipelines
+//!
+//! These helpers make the integration tests of pipelines built with dagrs concise: they run a
+//! [`Dag`] and collect the outputs of its tasks, or check the output of a task, panicking with
+//! a message that tells what was found instead. They are only compiled with the `test-utils`
+//! feature, which is meant to be enabled in the `dev-dependencies` of a crate:
+//!
+//! ```toml
+//! [dev-dependencies]
+//! dagrs = { version = "0.3", features = ["test-utils"] }
+//! ```
+//!
+//! # Example
+//!
+//! ```rust
+//! use dagrs::test_utils::{assert_task_output, run_and_collect};
+//! use dagrs::{Dag, DefaultTask, Output};
+//!
+//! let a = DefaultTask::with_closure("a", |_input, _env| Output::new(20usize));
+//! let mut b = DefaultTask::with_closure("b", |input, _env| {
+//!     Output::new(input.get_iter().next().unwrap().get::<usize>().unwrap() + 22)
+//! });
+//! b.set_predecessors(&[&a]);
+//! let mut dag = Dag::with_tasks(vec![a, b]);
+//! let outputs = run_and_collect(&mut dag);
+//! assert_eq!(outputs.len(), 2);
+//! assert_task_output(&dag, "b", 42usize);
+//! ```
+
+use std::{any::type_name, collections::HashMap, fmt::Debug};
+
+use crate::{Dag, Output};
+
+/// Run the dag and collect the outputs of its tasks by name, whether the dag succeeded or not.
+///
+/// A task that failed gives [`Output::Err`] with its error, and the other ones [`Output::Out`]
+/// with their output, which is empty for the tasks that did not run. Panics if the dag could
+/// not be started, e.g. because its dependencies loop.
+pub fn run_and_collect(dag: &mut Dag) -> HashMap<String, Output> {
+    if let Err(err) = dag.start() {
+        panic!("the dag could not be started: {}", err);
+    }
+    dag.task_outputs()
+        .into_iter()
+        .map(|(name, content, error)| {
+            let output = match error {
+                Some(error) => Output::Err(error),
+                None => Output::Out(content),
+            };
+            (name, output)
+        })
+        .collect()
+}
+
+/// Check that the task with the given name output `expected` in the last run of the dag.
+///
+/// Panics with a message telling what the task did instead if it does not exist, failed,
+/// produced no output, produced a value of another type, naming the type it produced, or
+/// produced another value.
+#[track_caller]
+pub fn assert_task_output<T: PartialEq + Debug + 'static>(dag: &Dag, name: &str, expected: T) {
+    let (_, content, error) = dag
+        .task_outputs()
+        .into_iter()
+        .find(|(task, _, _)| task == name)
+        .unwrap_or_else(|| panic!("task '{}' does not exist", name));
+    if let Some(error) = error {
+        panic!("task '{}' failed: {}", name, error);
+    }
+    let content = content.unwrap_or_else(|| panic!("task '{}' produced no output", name));
+    match content.get::<T>() {
+        Some(actual) => assert_eq!(
+            *actual, expected,
+            "task '{}' produced another value than expected",
+            name
+        ),
+        None => panic!(
+            "task '{}' produced a value of type {}, not {}",
+            name,
+            content.type_name(),
+            type_name::<T>()
+        ),
+    }
+}
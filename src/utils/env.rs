@@ -1,6 +1,12 @@
 use crate::task::Content;
 
-use std::collections::HashMap;
+use log::warn;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
 pub type Variable = Content;
 
@@ -11,16 +17,50 @@ pub type Variable = Content;
 /// Before all tasks run, the user builds a [`EnvVar`] and sets all the environment
 /// variables. One [`EnvVar`] corresponds to one dag. All tasks in a job can
 /// be shared and immutable at runtime. environment variables.
-#[derive(Debug, Default)]
+///
+/// Variables set by [`EnvVar::set_secret`] are regarded as secrets: the engine and the
+/// command runner pass any text they log through [`EnvVar::redact`], so that the values
+/// of secrets are replaced with `***`.
+#[derive(Debug, Default, Clone)]
 pub struct EnvVar {
     variables: HashMap<String, Variable>,
+    /// Names of the variables holding secrets.
+    secrets: HashSet<String>,
 }
 
 impl EnvVar {
+    /// The variable holding the path of the temporary directory of the running task, when
+    /// temporary directories are enabled with `Dag::set_task_temp_dirs`. Commands and scripts
+    /// receive it as an environment variable of the same name.
+    pub const TASK_TMPDIR: &'static str = "TASK_TMPDIR";
+
+    /// The prefix of the variables holding the outputs of the predecessors of the running task
+    /// in JSON, when enabled with `Dag::set_inputs_as_env`. Commands and scripts receive them
+    /// as environment variables of the same name.
+    pub const INPUT_PREFIX: &'static str = "DAGRS_INPUT_";
+
+    /// The variable holding the directory, as a `PathBuf`, where [`ScriptAction`] writes its
+    /// scripts, set by `Dag::set_script_temp_dir`. The system temporary directory is used
+    /// when it is not set.
+    ///
+    /// [`ScriptAction`]: crate::ScriptAction
+    pub const SCRIPT_DIR: &'static str = "DAGRS_SCRIPT_DIR";
+
+    /// The variable telling [`ScriptAction`] to keep its script files once they ran, as a
+    /// `bool`, set by `Dag::set_keep_script_files`.
+    ///
+    /// [`ScriptAction`]: crate::ScriptAction
+    pub const KEEP_SCRIPTS: &'static str = "DAGRS_KEEP_SCRIPTS";
+
+    /// The variable holding the state shared by the tasks, set by `Dag::set_shared_state`
+    /// and read with [`EnvVar::shared_state`].
+    pub const SHARED_STATE: &'static str = "DAGRS_SHARED_STATE";
+
     /// Allocate a new [`EnvVar`].
     pub fn new() -> Self {
         Self {
             variables: HashMap::new(),
+            secrets: HashSet::new(),
         }
     }
 
@@ -37,6 +77,120 @@ impl EnvVar {
         self.variables.insert(name.to_owned(), v);
     }
 
+    /// Set a global variable holding a secret, such as an API token.
+    ///
+    /// Secrets can be read like any other variable, but they never appear in the logs
+    /// of dagrs. Only secrets of type `String` or `&'static str` can be redacted.
+    ///
+    /// # Example
+    /// ```rust
+    /// # let mut env = dagrs::EnvVar::new();
+    /// env.set_secret("TOKEN", "s3cr3t".to_string());
+    /// assert_eq!(env.redact("token=s3cr3t"), "token=***");
+    /// ```
+    pub fn set_secret<H: Send + Sync + 'static>(&mut self, name: &str, var: H) {
+        self.set(name, var);
+        self.secrets.insert(name.to_owned());
+    }
+
+    /// Whether the variable with the given name holds a secret.
+    pub fn is_secret(&self, name: &str) -> bool {
+        self.secrets.contains(name)
+    }
+
+    /// Replace the values of all secrets appearing in the text with `***`.
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for name in self.secrets.iter() {
+            let secret = match self.get_ref::<String>(name) {
+                Some(secret) => secret.as_str(),
+                None => match self.get_ref::<&'static str>(name) {
+                    Some(secret) => secret,
+                    None => continue,
+                },
+            };
+            if !secret.is_empty() {
+                text = text.replace(secret, "***");
+            }
+        }
+        text
+    }
+
+    /// Unset the variable with the given name, if it is set.
+    pub(crate) fn remove(&mut self, name: &str) {
+        self.variables.remove(name);
+        self.secrets.remove(name);
+    }
+
+    /// Whether a variable with the given name is set.
+    pub fn contains(&self, name: &str) -> bool {
+        self.variables.contains_key(name)
+    }
+
+    /// Set the variables defined in a `.env` file as `String`s, except the ones that are
+    /// already set, so that the file provides defaults for the variables set in code.
+    ///
+    /// The file holds one `KEY=value` pair per line, optionally preceded by `export`. Blank
+    /// lines and lines starting with `#` are ignored. Values may be enclosed in single quotes,
+    /// taken literally, or in double quotes, where `\n`, `\t`, `\"` and `\\` are unescaped.
+    /// Unquoted values end at a ` #` comment and are trimmed. Malformed lines are logged as
+    /// warnings and skipped rather than failing the whole file.
+    ///
+    /// # Example
+    /// ```rust
+    /// # let dir = tempfile::tempdir().unwrap();
+    /// # let path = dir.path().join(".env");
+    /// std::fs::write(&path, "# Local settings\nexport HOST=localhost\nGREETING=\"hello world\"\n")
+    ///     .unwrap();
+    /// let mut env = dagrs::EnvVar::new();
+    /// env.set("HOST", "example.com".to_string());
+    /// env.load_env_file(&path).unwrap();
+    /// assert_eq!(env.get::<String>("HOST").unwrap(), "example.com");
+    /// assert_eq!(env.get::<String>("GREETING").unwrap(), "hello world");
+    /// ```
+    pub fn load_env_file(&mut self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        for (number, line) in text.lines().enumerate() {
+            match parse_env_line(line) {
+                Ok(Some((key, value))) => {
+                    if !self.contains(&key) {
+                        self.set(&key, value);
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => warn!(
+                    "{}:{}: {}, skipping the line",
+                    path.display(),
+                    number + 1,
+                    err
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// The variables exported to the processes started by commands and scripts:
+    /// [`EnvVar::TASK_TMPDIR`] and the variables starting with [`EnvVar::INPUT_PREFIX`].
+    pub(crate) fn exported(&self) -> Vec<(&str, &str)> {
+        self.variables
+            .iter()
+            .filter(|(name, _)| {
+                name.as_str() == Self::TASK_TMPDIR || name.starts_with(Self::INPUT_PREFIX)
+            })
+            .filter_map(|(name, var)| Some((name.as_str(), var.get::<String>()?.as_str())))
+            .collect()
+    }
+
+    /// Set the variables of `defaults` that are not set yet.
+    pub(crate) fn merge_defaults(&mut self, defaults: &EnvVar) {
+        for (name, var) in defaults.variables.iter() {
+            if !self.contains(name) {
+                self.variables.insert(name.clone(), var.clone());
+            }
+        }
+    }
+
     /// Get environment variables through keys of type &str.
     ///
     /// Note: This method will clone the value. To avoid cloning, use [`get_ref`].
@@ -44,6 +198,17 @@ impl EnvVar {
         self.get_ref(name).cloned()
     }
 
+    /// Get the state shared by the tasks of the dag, set by `Dag::set_shared_state`.
+    /// Returns None if no state was set, or if it is not a `T`.
+    pub fn shared_state<T: Send + 'static>(&self) -> Option<Arc<Mutex<T>>> {
+        self.get(Self::SHARED_STATE)
+    }
+
+    /// Set a variable to the given value, whose type is erased.
+    pub(crate) fn set_variable(&mut self, name: &str, var: Variable) {
+        self.variables.insert(name.to_owned(), var);
+    }
+
     /// Get environment variables through keys of type &str.
     pub fn get_ref<H: Send + Sync + 'static>(&self, name: &str) -> Option<&H> {
         if let Some(content) = self.variables.get(name) {
@@ -53,3 +218,52 @@ impl EnvVar {
         }
     }
 }
+
+/// Parse a line of a `.env` file, see [`EnvVar::load_env_file`]. Returns None for blank lines
+/// and comments.
+fn parse_env_line(line: &str) -> Result<Option<(String, String)>, &'static str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (key, value) = line.split_once('=').ok_or("missing '='")?;
+    let key = key.trim();
+    let mut chars = key.chars();
+    let valid_key = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if !valid_key {
+        return Err("invalid variable name");
+    }
+
+    let value = value.trim_start();
+    let (value, rest) = if let Some(quoted) = value.strip_prefix('\'') {
+        let end = quoted.find('\'').ok_or("unterminated quote")?;
+        (quoted[..end].to_string(), &quoted[end + 1..])
+    } else if let Some(quoted) = value.strip_prefix('"') {
+        let mut unescaped = String::new();
+        let mut chars = quoted.char_indices();
+        let end = loop {
+            match chars.next().ok_or("unterminated quote")? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next().ok_or("unterminated quote")?.1 {
+                    'n' => unescaped.push('\n'),
+                    't' => unescaped.push('\t'),
+                    c => unescaped.push(c),
+                },
+                (_, c) => unescaped.push(c),
+            }
+        };
+        (unescaped, &quoted[end + 1..])
+    } else {
+        let end = value.find(" #").unwrap_or(value.len());
+        (value[..end].trim_end().to_string(), "")
+    };
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err("unexpected characters after the quoted value");
+    }
+    Ok(Some((key.to_string(), value)))
+}
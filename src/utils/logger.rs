@@ -0,0 +1,189 @@
+//! Logger with per-target levels.
+//!
+//! dagrs logs through the `log` crate, and the target of every record is the module it comes
+//! from, e.g. `dagrs::engine::dag` for scheduling or `dagrs::task::cmd` for commands. The
+//! logger installed by [`init_logger`] writes records with `env_logger`, and its levels can be
+//! changed at runtime: [`set_target_level`] overrides the global level for one target, so that
+//! the scheduling logs can be made verbose while the task output stays quiet.
+//!
+//! The levels are colored when the logs are written to a terminal, which can be changed with
+//! [`init_logger_with_color`] and a [`ColorMode`]. Logs written to a file are never colored.
+//!
+//! # Example
+//!
+//! ```rust
+//! use dagrs::utils::logger::{init_logger, set_target_level};
+//! use log::LevelFilter;
+//!
+//! init_logger(LevelFilter::Info).unwrap();
+//! set_target_level("engine", LevelFilter::Debug);
+//! ```
+
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+};
+
+use env_logger::WriteStyle;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Whether the logs are colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    /// Color the logs if they are written to a terminal and the `NO_COLOR` environment variable
+    /// is not set.
+    #[default]
+    Auto,
+    /// Always color the logs written to the standard error or output, even if they are
+    /// redirected.
+    Always,
+    /// Never color the logs.
+    Never,
+}
+
+impl From<ColorMode> for WriteStyle {
+    fn from(mode: ColorMode) -> Self {
+        match mode {
+            ColorMode::Auto if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) => {
+                WriteStyle::Never
+            }
+            ColorMode::Auto => WriteStyle::Auto,
+            ColorMode::Always => WriteStyle::Always,
+            ColorMode::Never => WriteStyle::Never,
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    /// Parse `auto`, `always` or `never`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "unknown color mode {:?}, expected auto, always or never",
+                s
+            )),
+        }
+    }
+}
+
+/// The levels consulted by the logger.
+struct Levels {
+    global: LevelFilter,
+    targets: HashMap<String, LevelFilter>,
+}
+
+static LEVELS: OnceLock<RwLock<Levels>> = OnceLock::new();
+
+fn levels() -> &'static RwLock<Levels> {
+    LEVELS.get_or_init(|| {
+        RwLock::new(Levels {
+            global: LevelFilter::Info,
+            targets: HashMap::new(),
+        })
+    })
+}
+
+impl Levels {
+    /// The level of the most specific configured target matching `target`, or the global level.
+    ///
+    /// A configured target matches the targets equal to it or nested in it, either as a full
+    /// module path (`dagrs::engine`) or relative to the dagrs crate (`engine`).
+    fn level_for(&self, target: &str) -> LevelFilter {
+        let relative = target.strip_prefix("dagrs::");
+        self.targets
+            .iter()
+            .filter(|(configured, _)| {
+                matches_target(target, configured)
+                    || relative.is_some_and(|relative| matches_target(relative, configured))
+            })
+            .max_by_key(|(configured, _)| configured.len())
+            .map_or(self.global, |(_, level)| *level)
+    }
+
+    /// The most verbose level of all, which is the level the `log` macros have to let through.
+    fn max_level(&self) -> LevelFilter {
+        self.targets.values().copied().fold(self.global, Ord::max)
+    }
+}
+
+fn matches_target(target: &str, configured: &str) -> bool {
+    target == configured
+        || target
+            .strip_prefix(configured)
+            .is_some_and(|rest| rest.starts_with("::"))
+}
+
+/// The logger installed by [`init_logger`].
+struct Logger {
+    inner: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= levels().read().unwrap().level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the dagrs logger, writing to the standard error with the given global level.
+pub fn init_logger(level: LevelFilter) -> Result<(), SetLoggerError> {
+    init_logger_with(level, env_logger::Builder::new())
+}
+
+/// Install the dagrs logger, writing to the standard error with the given global level and
+/// coloring the logs as given by `color`.
+pub fn init_logger_with_color(level: LevelFilter, color: ColorMode) -> Result<(), SetLoggerError> {
+    let mut builder = env_logger::Builder::new();
+    builder.write_style(color.into());
+    init_logger_with(level, builder)
+}
+
+/// Install the dagrs logger with the given global level, using `builder` to decide where and
+/// how records are written. The levels configured on the builder are ignored.
+pub fn init_logger_with(
+    level: LevelFilter,
+    mut builder: env_logger::Builder,
+) -> Result<(), SetLoggerError> {
+    let inner = builder.filter_level(LevelFilter::Trace).build();
+    log::set_boxed_logger(Box::new(Logger { inner }))?;
+    set_global_level(level);
+    Ok(())
+}
+
+/// Change the level of the targets that have no level of their own.
+pub fn set_global_level(level: LevelFilter) {
+    let mut levels = levels().write().unwrap();
+    levels.global = level;
+    log::set_max_level(levels.max_level());
+}
+
+/// Override the level of a target and the targets nested in it. The target is either a full
+/// module path such as `dagrs::engine`, or a path relative to the dagrs crate such as `engine`.
+pub fn set_target_level(target: &str, level: LevelFilter) {
+    let mut levels = levels().write().unwrap();
+    levels.targets.insert(target.to_owned(), level);
+    log::set_max_level(levels.max_level());
+}
+
+/// Remove the level of a target, which falls back to the global level again.
+pub fn clear_target_level(target: &str) {
+    let mut levels = levels().write().unwrap();
+    levels.targets.remove(target);
+    log::set_max_level(levels.max_level());
+}
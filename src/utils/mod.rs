@@ -1,10 +1,11 @@
 //! general tool.
 //!
 //! This module contains common tools for the program, such as: environment
-//! variables, task generation macros.
+//! variables, task generation macros, the logger.
 
 mod env;
 pub mod file;
+pub mod logger;
 mod parser;
 
 pub use self::env::EnvVar;
@@ -42,6 +42,94 @@
 //!     cmd: echo h
 //! ```
 //!
+//! The `cmd` of a task is run by the system shell, so it may use pipes and redirections. With
+//! `shell: false`, it is instead split into a program and its arguments, following the quoting
+//! rules of the shell, and the program is run directly.
+//!
+//! Instead of `cmd`, a task may give a multi-line script in `script`, along with the language it is
+//! written in in `lang`. The language is `sh` by default, `python` and `javascript` are run by
+//! `python3` and `node`, and any other language is taken as the name of its interpreter:
+//!
+//! ```yaml
+//! dagrs:
+//!   a:
+//!     name: "Task 1"
+//!     lang: bash
+//!     script: |
+//!       for i in 1 2 3; do
+//!         echo $i
+//!       done
+//! ```
+//!
+//! By default, the output of a `cmd` or `script` task holds the lines of its standard output and
+//! standard error. With `output_type: int`, `float` or `json`, the standard output is instead
+//! parsed into an `i64`, a `f64` or a `serde_json::Value`, so that the successors written in Rust
+//! can use it directly. The task fails if its output cannot be parsed.
+//!
+//! A `cmd` or `script` task fails if it exits with a code other than 0, unless the code is
+//! listed in `success_codes`, e.g. `success_codes: [0, 1]` for a `grep` that may match nothing.
+//! Either way, its output holds the exit code, see `Output::exit_code`.
+//!
+//! Besides the attributes above, a task may set `enabled: false` to be skipped when the dag
+//! runs without removing it from the file, so the `after` references to it stay valid, and
+//! `allow_failure: true` to let the dag succeed and its successors run even if it fails.
+//! Tasks may also carry `labels`, a map of keys to values such as `tier: deploy`, which are
+//! used to select the tasks to run with `Dag::run_subset`.
+//!
+//! Attributes shared by many tasks can be defined once in a template, in the `templates`
+//! section next to `dagrs`. A task refers to a template with `use`, and inherits all the
+//! attributes of the template that it does not define itself:
+//!
+//! ```yaml
+//! templates:
+//!   greet:
+//!     name: "Greeting"
+//!     cmd: echo hello
+//! dagrs:
+//!   a:
+//!     use: greet
+//!   b:
+//!     use: greet
+//!     name: "Another greeting"
+//!     after: [ a ]
+//! ```
+//!
+//! Tasks that share predecessors can be listed in a group, in the `groups` section next to
+//! `dagrs`. The members of a group run after the tasks given by the `after` of the group, in
+//! addition to the ones given by their own `after`. A task may be a member of several groups,
+//! and the name of a group may not be the id of a task:
+//!
+//! ```yaml
+//! groups:
+//!   checks:
+//!     after: [ build, fetch ]
+//!     tasks: [ unit, lint ]
+//! dagrs:
+//!   unit:
+//!     name: "Unit tests"
+//!     after: [ setup ]
+//!     cmd: echo unit
+//! ```
+//!
+//! A file may pull in the tasks and templates of other files by listing them in `includes`, with
+//! paths relative to the including file. Included files may include other files in turn, so
+//! that pipelines can be composed from a library of fragments. A file included several times is
+//! merged once, and a task or template defined in two different files is an error, as is a file
+//! including itself:
+//!
+//! ```yaml
+//! includes:
+//!   - fragments/build.yaml
+//! dagrs:
+//!   deploy:
+//!     name: "Deploy"
+//!     after: [ build ]
+//!     cmd: echo deploy
+//! ```
+//!
+//! The format is described by the JSON Schema returned by [`pipeline_schema`], which editors can
+//! use to complete and validate configuration files.
+//!
 //! Users can read the yaml configuration file programmatically or by using the compiled `dagrs`
 //! command line tool. Either way, you need to enable the `yaml` feature.
 //!
@@ -52,11 +140,15 @@
 //! let dag = Dag::with_yaml("some_path",std::collections::HashMap::new());
 //! ```
 
+mod schema;
+mod task_config;
 mod yaml_parser;
 mod yaml_task;
 
 use thiserror::Error;
 
+pub use self::schema::pipeline_schema;
+pub use self::task_config::TaskConfig;
 pub use self::yaml_parser::YamlParser;
 pub use self::yaml_task::YamlTask;
 
@@ -77,6 +169,41 @@ pub enum YamlTaskError {
     /// `script` is not defined.
     #[error("The 'script' attribute is not defined. [{0}]")]
     NoScriptAttr(String),
+    /// A task uses a template that is not defined.
+    #[error("Task uses an undefined template '{1}'. [{0}]")]
+    TemplateNotFound(String, String),
+    /// An attribute is given a value of the wrong type.
+    #[error("The '{1}' attribute has an illegal value. [{0}]")]
+    IllegalAttrValue(String, String),
+    /// An entry of `includes` is not a path.
+    #[error("Illegal include. [{0}]")]
+    IllegalInclude(String),
+    /// A file includes itself, directly or through other files.
+    #[error("The file is included in a cycle. [{0}]")]
+    IncludeCycle(String),
+    /// The name of a group is also the id of a task.
+    #[error("The group '{0}' has the same name as a task.")]
+    GroupNameCollision(String),
+    /// A group lists a member that is not a task, given as the name of the group and the id of
+    /// the member.
+    #[error("The group '{0}' lists '{1}', which is not a task.")]
+    GroupMemberNotFound(String, String),
+    /// A task or a template is defined by several of the included files.
+    #[error("'{0}' is defined in several files.")]
+    DuplicateDefinition(String),
+    /// Tasks fail the checks registered with `YamlParser::add_required_field_check`, given as
+    /// the id of the task and the message of the check.
+    #[error("Tasks fail the required field checks: {}", violations(.0))]
+    RequiredFieldChecks(Vec<(String, String)>),
+}
+
+/// Format the failed checks of [`YamlTaskError::RequiredFieldChecks`].
+fn violations(failed: &[(String, String)]) -> String {
+    failed
+        .iter()
+        .map(|(id, msg)| format!("[{}] {}", id, msg))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 /// Error about file information.
@@ -0,0 +1,145 @@
+//! JSON Schema of the yaml configuration files.
+
+use serde_json::{json, Value};
+
+/// The [JSON Schema](https://json-schema.org) describing the yaml configuration files read by
+/// [`YamlParser`](super::YamlParser), for editors to complete and validate pipeline files.
+///
+/// The schema follows the attributes accepted by the parser, with two differences. Unknown
+/// attributes, which the parser ignores, are rejected so that typos are caught. And whether a
+/// task gives a `cmd` or a `script` is not checked, since it may inherit it from a template or
+/// be given its action in code.
+///
+/// # Example
+///
+/// ```rust
+/// let schema = dagrs::yaml::pipeline_schema();
+/// std::fs::write(
+///     std::env::temp_dir().join("dagrs.schema.json"),
+///     serde_json::to_string_pretty(&schema).unwrap(),
+/// )
+/// .unwrap();
+/// ```
+pub fn pipeline_schema() -> Value {
+    let mut task = attributes();
+    task["use"] = json!({
+        "description": "The template the task inherits the attributes it does not define from.",
+        "type": "string"
+    });
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "dagrs pipeline",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "includes": {
+                "description": "Files whose tasks and templates are merged into this one, relative to it.",
+                "type": "array",
+                "items": { "type": "string" }
+            },
+            "dagrs": {
+                "description": "The tasks of the dag, by id.",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/definitions/task" }
+            },
+            "templates": {
+                "description": "Attributes shared by tasks, which refer to them with `use`.",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/definitions/template" }
+            },
+            "groups": {
+                "description": "Tasks sharing predecessors, by group name.",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/definitions/group" }
+            }
+        },
+        "definitions": {
+            "group": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["tasks"],
+                "properties": {
+                    "after": {
+                        "description": "The ids of the tasks to run before the members of the group.",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    },
+                    "tasks": {
+                        "description": "The ids of the members of the group.",
+                        "type": "array",
+                        "items": { "type": "string" }
+                    }
+                }
+            },
+            "template": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": attributes()
+            },
+            "task": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": task
+            }
+        }
+    })
+}
+
+/// The attributes a task or a template may define.
+fn attributes() -> Value {
+    json!({
+        "name": {
+            "description": "The name of the task.",
+            "type": "string"
+        },
+        "after": {
+            "description": "The ids of the tasks to run before this one.",
+            "type": "array",
+            "items": { "type": "string" }
+        },
+        "cmd": {
+            "description": "The command run by the task.",
+            "type": "string"
+        },
+        "shell": {
+            "description": "Whether the command is run by the system shell, or split into a program and its arguments.",
+            "type": "boolean",
+            "default": true
+        },
+        "script": {
+            "description": "The script run by the task, instead of a command.",
+            "type": "string"
+        },
+        "lang": {
+            "description": "The language of the script, or the name of its interpreter.",
+            "type": "string",
+            "default": "sh",
+            "examples": ["sh", "shell", "bash", "python", "javascript", "js"]
+        },
+        "output_type": {
+            "description": "How the standard output of the task is parsed.",
+            "enum": ["int", "float", "json"]
+        },
+        "success_codes": {
+            "description": "The exit codes for which the command or the script succeeds.",
+            "type": "array",
+            "items": { "type": "integer" },
+            "default": [0]
+        },
+        "enabled": {
+            "description": "Whether the task runs. A disabled task is skipped.",
+            "type": "boolean",
+            "default": true
+        },
+        "allow_failure": {
+            "description": "Whether the dag succeeds and the successors run even if the task fails.",
+            "type": "boolean",
+            "default": false
+        },
+        "labels": {
+            "description": "Metadata used to select tasks.",
+            "type": "object",
+            "additionalProperties": { "type": ["string", "number", "boolean"] }
+        }
+    })
+}
@@ -0,0 +1,58 @@
+//! The raw configuration of a task, as checked by the policies of a [`YamlParser`].
+//!
+//! [`YamlParser`]: super::YamlParser
+
+use yaml_rust::Yaml;
+
+/// The attributes of a task in a configuration file, after the template it uses has been
+/// merged in, but before the task is built from them.
+///
+/// It is given to the checks registered with `YamlParser::add_required_field_check`.
+#[derive(Debug, Clone)]
+pub struct TaskConfig {
+    id: String,
+    item: Yaml,
+}
+
+impl TaskConfig {
+    pub(crate) fn new(id: &str, item: Yaml) -> Self {
+        Self {
+            id: id.to_owned(),
+            item,
+        }
+    }
+
+    /// The id of the task in the configuration file.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The `name` attribute of the task, if it is a string.
+    pub fn name(&self) -> Option<&str> {
+        self.item["name"].as_str()
+    }
+
+    /// The value of an attribute of the task, or None if it is not given.
+    pub fn get(&self, attr: &str) -> Option<&Yaml> {
+        match &self.item[attr] {
+            Yaml::BadValue => None,
+            value => Some(value),
+        }
+    }
+
+    /// The value of the label `key` of the task, formatted as a string like the labels of the
+    /// built tasks, or None if the task has no such label.
+    pub fn label(&self, key: &str) -> Option<String> {
+        match &self.get("labels")?[key] {
+            Yaml::String(value) | Yaml::Real(value) => Some(value.clone()),
+            Yaml::Integer(value) => Some(value.to_string()),
+            Yaml::Boolean(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    /// All the attributes of the task.
+    pub fn yaml(&self) -> &Yaml {
+        &self.item
+    }
+}
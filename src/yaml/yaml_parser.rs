@@ -1,14 +1,116 @@
 //! Default yaml configuration file parser.
 
-use super::{FileContentError, YamlTask, YamlTaskError};
-use crate::{utils::file::load_file, utils::ParseError, Action, CommandAction, Parser, Task};
-use std::{collections::HashMap, sync::Arc};
-use yaml_rust::{Yaml, YamlLoader};
+use super::{FileContentError, FileNotFound, TaskConfig, YamlTask, YamlTaskError};
+use crate::{
+    utils::file::load_file, utils::ParseError, Action, CommandAction, OutputFormat, Parser,
+    ScriptAction, Task,
+};
+use log::warn;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use yaml_rust::{yaml::Hash, Yaml, YamlLoader};
+
+/// The type of a check run on the configuration of every task.
+type FieldCheck = dyn Fn(&TaskConfig) -> Result<(), String> + Send + Sync;
 
 /// An implementation of [`Parser`]. It is the default yaml configuration file parser.
-pub struct YamlParser;
+#[derive(Clone, Default)]
+pub struct YamlParser {
+    checks: Vec<Arc<FieldCheck>>,
+    /// Whether identical definitions of a task in several files are merged.
+    merge_duplicates: bool,
+    /// Whether the dependencies on tasks that do not exist are dropped with a warning.
+    lenient_deps: bool,
+}
 
 impl YamlParser {
+    /// Create a parser without checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a check run on the configuration of every task, once its template is merged
+    /// in and before the task is built, e.g. to enforce the conventions of a team such as
+    /// giving every task a `team` label.
+    ///
+    /// The tasks are all checked before the parsing fails, and the error
+    /// [`YamlTaskError::RequiredFieldChecks`] lists every failed check along with the id of
+    /// the task.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, YamlParser};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut parser = YamlParser::new();
+    /// parser.add_required_field_check(|task| match task.label("team") {
+    ///     Some(_) => Ok(()),
+    ///     None => Err("the task has no team label".to_string()),
+    /// });
+    /// let yaml = "dagrs:\n  a:\n    name: a\n    cmd: echo a\n";
+    /// let dag = Dag::with_config_str_and_parser(yaml, Box::new(parser), HashMap::new());
+    /// assert!(dag.is_err());
+    /// ```
+    pub fn add_required_field_check(
+        &mut self,
+        check: impl Fn(&TaskConfig) -> Result<(), String> + Send + Sync + 'static,
+    ) {
+        self.checks.push(Arc::new(check));
+    }
+
+    /// Merge the tasks and templates defined in several of the included files into a single
+    /// one when their definitions are equivalent, instead of failing with
+    /// [`YamlTaskError::DuplicateDefinition`]. This makes it practical to compose files that
+    /// overlap, e.g. two fragments both defining the build task they depend on.
+    ///
+    /// Two definitions are equivalent when they have the same attributes, such as the name,
+    /// the command or the script, apart from `after`: the predecessors of the merged task are
+    /// the union of the ones of the definitions. Definitions that differ otherwise still fail
+    /// the parsing. The attributes are compared as written, before the templates are applied.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, YamlParser};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut parser = YamlParser::new();
+    /// parser.set_merge_duplicates(true);
+    /// let dag = Dag::with_config_file_and_parser(
+    ///     "tests/config/include_merge.yaml",
+    ///     Box::new(parser),
+    ///     HashMap::new(),
+    /// );
+    /// assert!(dag.is_ok());
+    /// ```
+    pub fn set_merge_duplicates(&mut self, merge: bool) {
+        self.merge_duplicates = merge;
+    }
+
+    /// Drop the dependencies on tasks that do not exist, logging a warning that names the
+    /// task and its missing predecessor, instead of failing with
+    /// [`YamlTaskError::NotFoundPrecursor`]. This keeps a large configuration usable while a
+    /// task it refers to is temporarily removed. Strict dependencies are the default.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, YamlParser};
+    /// use std::collections::HashMap;
+    ///
+    /// let yaml = "dagrs:\n  a:\n    name: a\n    after: [gone]\n    cmd: echo a\n";
+    /// let mut parser = YamlParser::new();
+    /// parser.set_lenient_deps(true);
+    /// let mut dag =
+    ///     Dag::with_config_str_and_parser(yaml, Box::new(parser), HashMap::new()).unwrap();
+    /// assert!(dag.start().unwrap());
+    /// ```
+    pub fn set_lenient_deps(&mut self, lenient: bool) {
+        self.lenient_deps = lenient;
+    }
+
     /// Parses an item in the configuration file into a task.
     /// An item refers to:
     ///
@@ -36,57 +138,408 @@ impl YamlParser {
                 .for_each(|task_id| precursors.push(task_id.as_str().unwrap().to_owned()));
         }
 
-        if let Some(action) = specific_action {
-            Ok(YamlTask::new(id, precursors, name, action))
+        // enabled is optional and defaults to true
+        let enabled = match &item["enabled"] {
+            Yaml::BadValue => true,
+            value => value.as_bool().ok_or(YamlTaskError::IllegalAttrValue(
+                name.clone(),
+                "enabled".to_owned(),
+            ))?,
+        };
+
+        // allow_failure is optional and defaults to false
+        let allow_failure = match &item["allow_failure"] {
+            Yaml::BadValue => false,
+            value => value.as_bool().ok_or(YamlTaskError::IllegalAttrValue(
+                name.clone(),
+                "allow_failure".to_owned(),
+            ))?,
+        };
+
+        // output_type is optional, the lines of the output are kept by default
+        let format = match &item["output_type"] {
+            Yaml::BadValue => OutputFormat::Lines,
+            value => match value.as_str() {
+                Some("int") => OutputFormat::Int,
+                Some("float") => OutputFormat::Float,
+                Some("json") => OutputFormat::Json,
+                _ => {
+                    return Err(YamlTaskError::IllegalAttrValue(
+                        name,
+                        "output_type".to_owned(),
+                    ))
+                }
+            },
+        };
+
+        // success_codes is optional, only 0 is a success by default
+        let success_codes = match &item["success_codes"] {
+            Yaml::BadValue => vec![0],
+            Yaml::Array(codes) => codes
+                .iter()
+                .map(|code| code.as_i64().and_then(|code| i32::try_from(code).ok()))
+                .collect::<Option<Vec<i32>>>()
+                .ok_or(YamlTaskError::IllegalAttrValue(
+                    name.clone(),
+                    "success_codes".to_owned(),
+                ))?,
+            _ => {
+                return Err(YamlTaskError::IllegalAttrValue(
+                    name,
+                    "success_codes".to_owned(),
+                ))
+            }
+        };
+
+        // labels are optional, their values may be strings, numbers or booleans
+        let mut labels = Vec::new();
+        match &item["labels"] {
+            Yaml::BadValue => {}
+            Yaml::Hash(hash) => {
+                for (key, value) in hash {
+                    let value = match value {
+                        Yaml::String(value) | Yaml::Real(value) => Some(value.clone()),
+                        Yaml::Integer(value) => Some(value.to_string()),
+                        Yaml::Boolean(value) => Some(value.to_string()),
+                        _ => None,
+                    };
+                    match (key.as_str(), value) {
+                        (Some(key), Some(value)) => labels.push((key.to_owned(), value)),
+                        _ => {
+                            return Err(YamlTaskError::IllegalAttrValue(name, "labels".to_owned()))
+                        }
+                    }
+                }
+            }
+            _ => return Err(YamlTaskError::IllegalAttrValue(name, "labels".to_owned())),
+        }
+
+        let mut task = if let Some(action) = specific_action {
+            YamlTask::new(id, precursors, name, action)
+        } else if let Some(script) = item["script"].as_str() {
+            let lang = match &item["lang"] {
+                Yaml::BadValue => "sh",
+                value => value.as_str().ok_or(YamlTaskError::IllegalAttrValue(
+                    name.clone(),
+                    "lang".to_owned(),
+                ))?,
+            };
+            let mut action = ScriptAction::new(interpreter(lang), script);
+            action.set_output_format(format);
+            action.set_success_codes(&success_codes);
+            YamlTask::new(id, precursors, name, Action::Structure(Arc::new(action)))
         } else {
             let cmd = item["cmd"]
                 .as_str()
                 .ok_or(YamlTaskError::NoScriptAttr(name.clone()))?;
-            Ok(YamlTask::new(
-                id,
-                precursors,
-                name,
-                Action::Structure(Arc::new(CommandAction::new(cmd))),
-            ))
+            let mut action = CommandAction::new(cmd);
+            action.set_output_format(format);
+            action.set_success_codes(&success_codes);
+            // shell is optional and defaults to true
+            match &item["shell"] {
+                Yaml::BadValue => {}
+                value => action.set_shell(value.as_bool().ok_or(
+                    YamlTaskError::IllegalAttrValue(name.clone(), "shell".to_owned()),
+                )?),
+            }
+            YamlTask::new(id, precursors, name, Action::Structure(Arc::new(action)))
+        };
+        task.set_enabled(enabled);
+        task.set_allow_failure(allow_failure);
+        for (key, value) in labels {
+            task.set_label(&key, &value);
         }
+        if format != OutputFormat::Lines {
+            task.set_output_type(format.data_type());
+        }
+        Ok(task)
     }
 }
 
-impl Parser for YamlParser {
-    fn parse_tasks(
+/// The sections of a document and of the files it includes.
+#[derive(Default)]
+struct Sections {
+    tasks: Hash,
+    templates: Hash,
+    groups: Hash,
+}
+
+/// The predecessors given to the members of the groups, by task id.
+fn group_predecessors<'a>(
+    groups: &'a Hash,
+    tasks: &Hash,
+) -> Result<HashMap<&'a str, Vec<Yaml>>, ParseError> {
+    let mut afters: HashMap<&str, Vec<Yaml>> = HashMap::new();
+    for (name, group) in groups {
+        let name = name
+            .as_str()
+            .ok_or(ParseError("Invalid YAML Node Type".to_string()))?;
+        if tasks.contains_key(&Yaml::String(name.to_owned())) {
+            return Err(YamlTaskError::GroupNameCollision(name.to_owned()).into());
+        }
+        let illegal =
+            |attr: &str| YamlTaskError::IllegalAttrValue(name.to_owned(), attr.to_owned());
+        let after = match &group["after"] {
+            Yaml::BadValue => &[][..],
+            Yaml::Array(after) if after.iter().all(|pre| pre.as_str().is_some()) => after,
+            _ => return Err(illegal("after").into()),
+        };
+        let members = group["tasks"].as_vec().ok_or_else(|| illegal("tasks"))?;
+        for member in members {
+            let member = member.as_str().ok_or_else(|| illegal("tasks"))?;
+            if !tasks.contains_key(&Yaml::String(member.to_owned())) {
+                return Err(
+                    YamlTaskError::GroupMemberNotFound(name.to_owned(), member.to_owned()).into(),
+                );
+            }
+            let member_afters = afters.entry(member).or_default();
+            for pre in after {
+                if !member_afters.contains(pre) {
+                    member_afters.push(pre.clone());
+                }
+            }
+        }
+    }
+    Ok(afters)
+}
+
+/// Add predecessors to the `after` of a task, after its own ones.
+fn add_predecessors(id: &str, item: &mut Yaml, afters: &[Yaml]) -> Result<(), YamlTaskError> {
+    let illegal = || YamlTaskError::IllegalAttrValue(id.to_owned(), "after".to_owned());
+    let Yaml::Hash(item) = item else {
+        return Err(illegal());
+    };
+    let key = Yaml::String("after".to_owned());
+    let mut after = match item.get(&key) {
+        None => Vec::new(),
+        Some(Yaml::Array(after)) => after.clone(),
+        Some(_) => return Err(illegal()),
+    };
+    for pre in afters {
+        if !after.contains(pre) {
+            after.push(pre.clone());
+        }
+    }
+    item.insert(key, Yaml::Array(after));
+    Ok(())
+}
+
+/// Merge two definitions of the same task or template, if they only differ by their
+/// predecessors, into a definition whose predecessors are the ones of both.
+fn merge_definitions(first: &Yaml, second: &Yaml) -> Option<Yaml> {
+    let (first, second) = (first.as_hash()?, second.as_hash()?);
+    let after = Yaml::String("after".to_owned());
+    let without_after = |item: &Hash| {
+        let mut item = item.clone();
+        item.remove(&after);
+        item
+    };
+    if without_after(first) != without_after(second) {
+        return None;
+    }
+    let mut merged = first.clone();
+    let mut predecessors: Vec<Yaml> = Vec::new();
+    for item in [first, second] {
+        match item.get(&after) {
+            None => {}
+            Some(Yaml::Array(pres)) => {
+                for pre in pres {
+                    if !predecessors.contains(pre) {
+                        predecessors.push(pre.clone());
+                    }
+                }
+            }
+            Some(_) => return None,
+        }
+    }
+    if !predecessors.is_empty() {
+        merged.insert(after, Yaml::Array(predecessors));
+    }
+    Some(Yaml::Hash(merged))
+}
+
+/// The interpreter of the scripts written in the given language. Languages that are not known
+/// are taken as the name of the interpreter.
+fn interpreter(lang: &str) -> &str {
+    match lang {
+        "shell" => "sh",
+        "python" => "python3",
+        "javascript" | "js" => "node",
+        _ => lang,
+    }
+}
+
+impl YamlParser {
+    /// Expand the template referenced by the `use` attribute of an item, if any.
+    ///
+    /// The attributes of the item are merged into a copy of the template, so the attributes
+    /// given by the item win over the ones of the template.
+    fn expand_template(
+        &self,
+        id: &str,
+        item: &Yaml,
+        templates: Option<&Hash>,
+    ) -> Result<Yaml, YamlTaskError> {
+        let template_name = match &item["use"] {
+            Yaml::BadValue => return Ok(item.clone()),
+            value => value.as_str().ok_or(YamlTaskError::IllegalAttrValue(
+                id.to_owned(),
+                "use".to_owned(),
+            ))?,
+        };
+        let mut merged = templates
+            .and_then(|templates| templates.get(&Yaml::String(template_name.to_owned())))
+            .and_then(Yaml::as_hash)
+            .cloned()
+            .ok_or(YamlTaskError::TemplateNotFound(
+                id.to_owned(),
+                template_name.to_owned(),
+            ))?;
+        if let Some(attrs) = item.as_hash() {
+            for (key, value) in attrs {
+                if key.as_str() != Some("use") {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Ok(Yaml::Hash(merged))
+    }
+
+    /// Parse a document along with the files it includes, relative to `dir`, and merge their
+    /// `dagrs`, `templates` and `groups` sections into `sections`.
+    ///
+    /// `stack` holds the files being included, to detect cycles, and `loaded` all the files
+    /// included so far, so that a file included several times is only merged once.
+    fn load_document(
+        &self,
+        content: &str,
+        dir: &Path,
+        stack: &mut Vec<PathBuf>,
+        loaded: &mut HashSet<PathBuf>,
+        sections: &mut Sections,
+    ) -> Result<(), ParseError> {
+        let docs =
+            YamlLoader::load_from_str(content).map_err(FileContentError::IllegalYamlContent)?;
+        let doc = docs
+            .first()
+            .ok_or(ParseError("No Tasks found".to_string()))?;
+        for (section, merged) in [
+            ("dagrs", &mut sections.tasks),
+            ("templates", &mut sections.templates),
+            ("groups", &mut sections.groups),
+        ] {
+            let items = match &doc[section] {
+                Yaml::BadValue => continue,
+                items => items.as_hash().ok_or(YamlTaskError::StartWordError)?,
+            };
+            for (id, item) in items {
+                let item = match merged.get(id) {
+                    None => item.clone(),
+                    Some(existing) => self
+                        .merge_duplicates
+                        .then(|| merge_definitions(existing, item))
+                        .flatten()
+                        .ok_or_else(|| {
+                            let id = id.as_str().unwrap_or_default().to_owned();
+                            YamlTaskError::DuplicateDefinition(id)
+                        })?,
+                };
+                merged.insert(id.clone(), item);
+            }
+        }
+
+        let includes = match &doc["includes"] {
+            Yaml::BadValue => return Ok(()),
+            Yaml::Array(includes) => includes,
+            _ => return Err(YamlTaskError::IllegalInclude("includes".to_owned()).into()),
+        };
+        for include in includes {
+            let include = include
+                .as_str()
+                .ok_or(YamlTaskError::IllegalInclude(format!("{:?}", include)))?;
+            let path = fs::canonicalize(dir.join(include)).map_err(FileNotFound)?;
+            if stack.contains(&path) {
+                return Err(YamlTaskError::IncludeCycle(path.display().to_string()).into());
+            }
+            if !loaded.insert(path.clone()) {
+                continue;
+            }
+            let content = fs::read_to_string(&path).map_err(FileNotFound)?;
+            let dir = path.parent().unwrap_or(dir).to_owned();
+            stack.push(path);
+            self.load_document(&content, &dir, stack, loaded, sections)?;
+            stack.pop();
+        }
+        Ok(())
+    }
+
+    /// Parse a configuration file into [`YamlTask`]s, like `parse_tasks` but without erasing
+    /// their type, e.g. to turn them into `DefaultTask`s with [`YamlTask::into_default`].
+    pub fn parse_yaml_tasks(
         &self,
         file: &str,
         specific_actions: HashMap<String, Action>,
-    ) -> Result<Vec<Box<dyn Task>>, ParseError> {
+    ) -> Result<Vec<YamlTask>, ParseError> {
         let content = load_file(file)?;
-        self.parse_tasks_from_str(&content, specific_actions)
+        let path = fs::canonicalize(file)?;
+        let dir = path.parent().unwrap_or(Path::new(".")).to_owned();
+        self.parse_document(&content, &dir, Some(path), specific_actions)
     }
 
-    fn parse_tasks_from_str(
+    /// Parse the tasks of a document whose includes are relative to `dir`. `file` is the path
+    /// of the document, if it was read from a file.
+    fn parse_document(
         &self,
         content: &str,
+        dir: &Path,
+        file: Option<PathBuf>,
         mut specific_actions: HashMap<String, Action>,
-    ) -> Result<Vec<Box<dyn Task>>, ParseError> {
-        // Parse Yaml
-        let yaml_tasks =
-            YamlLoader::load_from_str(content).map_err(FileContentError::IllegalYamlContent)?;
+    ) -> Result<Vec<YamlTask>, ParseError> {
+        let mut sections = Sections::default();
+        let mut stack: Vec<PathBuf> = file.into_iter().collect();
+        let mut loaded: HashSet<PathBuf> = stack.iter().cloned().collect();
+        self.load_document(content, dir, &mut stack, &mut loaded, &mut sections)?;
+        let Sections {
+            tasks: yaml_tasks,
+            templates,
+            groups,
+        } = sections;
         if yaml_tasks.is_empty() {
-            return Err(ParseError("No Tasks found".to_string()));
+            return Err(YamlTaskError::StartWordError.into());
         }
-        let yaml_tasks = yaml_tasks[0]["dagrs"]
-            .as_hash()
-            .ok_or(YamlTaskError::StartWordError)?;
+        let group_afters = group_predecessors(&groups, &yaml_tasks)?;
 
-        let mut tasks = Vec::with_capacity(yaml_tasks.len());
-        let mut map = HashMap::with_capacity(yaml_tasks.len());
-        // Read tasks
-        for (v, w) in yaml_tasks {
+        let mut items = Vec::with_capacity(yaml_tasks.len());
+        let mut failed = Vec::new();
+        for (v, w) in yaml_tasks.iter() {
             let id = v
                 .as_str()
                 .ok_or(ParseError("Invalid YAML Node Type".to_string()))?;
+            let mut w = self.expand_template(id, w, Some(&templates))?;
+            if let Some(afters) = group_afters.get(id) {
+                add_predecessors(id, &mut w, afters)?;
+            }
+            if !self.checks.is_empty() {
+                let config = TaskConfig::new(id, w.clone());
+                for check in &self.checks {
+                    if let Err(msg) = check(&config) {
+                        failed.push((id.to_owned(), msg));
+                    }
+                }
+            }
+            items.push((id, w));
+        }
+        if !failed.is_empty() {
+            return Err(YamlTaskError::RequiredFieldChecks(failed).into());
+        }
+
+        let mut tasks = Vec::with_capacity(items.len());
+        let mut map = HashMap::with_capacity(items.len());
+        // Read tasks
+        for (id, w) in items {
             let task = specific_actions.remove(id).map_or_else(
-                || self.parse_one(id, w, None),
-                |action| self.parse_one(id, w, Some(action)),
+                || self.parse_one(id, &w, None),
+                |action| self.parse_one(id, &w, Some(action)),
             )?;
             map.insert(id, task.id());
             tasks.push(task);
@@ -97,16 +550,45 @@ impl Parser for YamlParser {
             for pre in task.str_precursors() {
                 if map.contains_key(&pre[..]) {
                     pres.push(map[&pre[..]]);
+                } else if self.lenient_deps {
+                    warn!(
+                        "Task '{}' depends on '{}', which does not exist: the dependency is dropped",
+                        task.name(),
+                        pre
+                    );
                 } else {
                     return Err(YamlTaskError::NotFoundPrecursor(task.name().to_string()).into());
                 }
             }
             task.init_precursors(pres);
         }
+        Ok(tasks)
+    }
+}
+
+/// Erase the type of the parsed tasks.
+fn boxed(tasks: Vec<YamlTask>) -> Vec<Box<dyn Task>> {
+    tasks
+        .into_iter()
+        .map(|task| Box::new(task) as Box<dyn Task>)
+        .collect()
+}
+
+impl Parser for YamlParser {
+    fn parse_tasks(
+        &self,
+        file: &str,
+        specific_actions: HashMap<String, Action>,
+    ) -> Result<Vec<Box<dyn Task>>, ParseError> {
+        self.parse_yaml_tasks(file, specific_actions).map(boxed)
+    }
 
-        Ok(tasks
-            .into_iter()
-            .map(|task| Box::new(task) as Box<dyn Task>)
-            .collect())
+    fn parse_tasks_from_str(
+        &self,
+        content: &str,
+        specific_actions: HashMap<String, Action>,
+    ) -> Result<Vec<Box<dyn Task>>, ParseError> {
+        self.parse_document(content, Path::new("."), None, specific_actions)
+            .map(boxed)
     }
 }
@@ -7,7 +7,7 @@
 //! It is different from `DefaultTask`, in addition to the four mandatory attributes of the
 //! task type, he has several additional attributes.
 
-use crate::{alloc_id, Action, Task};
+use crate::{alloc_id, Action, DataType, DefaultTask, Task, TaskLabels};
 
 /// Task struct for yaml file.
 pub struct YamlTask {
@@ -19,6 +19,14 @@ pub struct YamlTask {
     precursors: Vec<String>,
     precursors_id: Vec<usize>,
     action: Action,
+    /// Whether the task is enabled, configured by the `enabled` attribute.
+    enabled: bool,
+    /// The type of the output, configured by the `output_type` attribute.
+    output_type: Option<DataType>,
+    /// The labels of the task, configured by the `labels` attribute.
+    labels: TaskLabels,
+    /// Whether the task may fail, configured by the `allow_failure` attribute.
+    allow_failure: bool,
 }
 
 impl YamlTask {
@@ -31,6 +39,10 @@ impl YamlTask {
             precursors,
             precursors_id: Vec::new(),
             action,
+            enabled: true,
+            output_type: None,
+            labels: TaskLabels::default(),
+            allow_failure: false,
         }
     }
     /// After the configuration file is parsed, the id of each task has been assigned.
@@ -51,6 +63,61 @@ impl YamlTask {
     pub fn str_id(&self) -> &str {
         &self.yid
     }
+
+    /// Enable or disable the task.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Declare the type of the output of the task.
+    pub fn set_output_type(&mut self, output_type: DataType) {
+        self.output_type = Some(output_type);
+    }
+
+    /// Allow the task to fail without failing the dag.
+    pub fn set_allow_failure(&mut self, allow: bool) {
+        self.allow_failure = allow;
+    }
+
+    /// Attach a label to the task.
+    pub fn set_label(&mut self, key: &str, value: &str) {
+        self.labels.insert(key, value);
+    }
+
+    /// Turn the task into a [`DefaultTask`] with the same id, name, dependencies, action and
+    /// settings, so that it can be changed with the programmatic API, e.g. given a retry policy.
+    ///
+    /// The id is kept, so the tasks depending on this one still do after the conversion.
+    /// The predecessors must have been initialized with `init_precursors`, as they are in the
+    /// tasks returned by `YamlParser::parse_yaml_tasks`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use dagrs::{Dag, DefaultTask, RetryPolicy, YamlParser};
+    /// use std::collections::HashMap;
+    ///
+    /// let tasks = YamlParser::new()
+    ///     .parse_yaml_tasks("tests/config/correct.yaml", HashMap::new())
+    ///     .unwrap();
+    /// let tasks: Vec<DefaultTask> = tasks
+    ///     .into_iter()
+    ///     .map(|task| {
+    ///         let mut task = task.into_default();
+    ///         task.set_retry(RetryPolicy::new(2));
+    ///         task
+    ///     })
+    ///     .collect();
+    /// let dag = Dag::with_tasks(tasks);
+    /// ```
+    pub fn into_default(self) -> DefaultTask {
+        DefaultTask::copy_of(&self)
+    }
+}
+
+impl From<YamlTask> for DefaultTask {
+    fn from(task: YamlTask) -> Self {
+        task.into_default()
+    }
 }
 
 impl Task for YamlTask {
@@ -66,4 +133,16 @@ impl Task for YamlTask {
     fn name(&self) -> &str {
         &self.name
     }
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+    fn output_type(&self) -> Option<DataType> {
+        self.output_type
+    }
+    fn labels(&self) -> TaskLabels {
+        self.labels.clone()
+    }
+    fn allow_failure(&self) -> bool {
+        self.allow_failure
+    }
 }
@@ -1,8 +1,18 @@
 //! Some tests of the dag engine.
 
-use std::{collections::HashMap, env::set_var, sync::Arc};
+use std::{
+    collections::HashMap,
+    env::set_var,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use dagrs::{Complex, Dag, DagError, DefaultTask, EnvVar, Input, Output};
+use dagrs::{
+    task::Content, Action, AssertAction, CommandAction, Complex, Dag, DagError, DefaultTask,
+    EnvVar, GanttBar, Gate, GateAction, Input, NameCollision, Output, OutputFormat, RetryPolicy,
+    RunEventKind, RunningError, ScriptAction, StreamFlush, Task, TaskGroup, TaskMiddleware,
+    TaskReport, TaskState, ThreadAffinity, Topology, UnconsumedOutput,
+};
 
 #[test]
 fn yaml_task_correct_execute() {
@@ -68,6 +78,7 @@ impl Complex for FailedActionC {
     }
 }
 
+#[allow(dead_code)]
 struct FailedActionD(usize);
 
 impl Complex for FailedActionD {
@@ -152,3 +163,2291 @@ fn task_failed_execute() {
 fn task_keep_going() {
     test_dag(true, Some(8));
 }
+
+#[test]
+fn yaml_task_disabled() {
+    let mut job = Dag::with_yaml("tests/config/disabled_task.yaml", HashMap::new()).unwrap();
+    assert!(job.start().unwrap());
+    assert_eq!(job.task_state("Task 2"), Some(TaskState::Skipped));
+    assert_eq!(job.task_state("Task 3"), Some(TaskState::Succeeded));
+}
+
+#[test]
+fn yaml_task_script() {
+    let mut job = Dag::with_yaml("tests/config/script.yaml", HashMap::new()).unwrap();
+    assert!(job.start().unwrap());
+    assert_eq!(job.task_state("Task 1"), Some(TaskState::Succeeded));
+    let out = job.get_result::<Content>().unwrap();
+    let (stdout, _) = out.get::<(Vec<String>, Vec<String>)>().unwrap();
+    assert_eq!(stdout, &["1", "2", "3"]);
+}
+
+#[test]
+fn log_dir_receives_streams() {
+    let dir = std::env::temp_dir().join(format!("dagrs-log-dir-{}", std::process::id()));
+    let a = DefaultTask::with_action("a", CommandAction::new("echo out; echo err >&2"));
+    let mut b = DefaultTask::with_action("b/c", CommandAction::new("echo failed; exit 1"));
+    b.set_predecessors(&[&a]);
+
+    let mut job = Dag::with_tasks(vec![a, b]);
+    job.set_log_dir(&dir);
+    assert!(!job.start().unwrap());
+    assert_eq!(std::fs::read_to_string(dir.join("a.out")).unwrap(), "out\n");
+    assert_eq!(std::fs::read_to_string(dir.join("a.err")).unwrap(), "err\n");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("b_c.out")).unwrap(),
+        "failed\n"
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn inputs_in_declaration_order() {
+    for _ in 0..5 {
+        // The predecessors finish in the reverse order of their declaration.
+        let slow = DefaultTask::with_closure("slow", |_, _| {
+            std::thread::sleep(Duration::from_millis(60));
+            Output::new(1usize)
+        });
+        let medium = DefaultTask::with_closure("medium", |_, _| {
+            std::thread::sleep(Duration::from_millis(30));
+            Output::new(2usize)
+        });
+        let fast = DefaultTask::with_closure("fast", |_, _| Output::new(3usize));
+        let empty = DefaultTask::with_closure("empty", |_, _| Output::empty());
+        let (slow_id, fast_id, empty_id) = (slow.id(), fast.id(), empty.id());
+        let mut reduce = DefaultTask::with_closure("reduce", move |input, _| {
+            let values: Vec<usize> = input
+                .get_iter()
+                .map(|content| *content.get::<usize>().unwrap())
+                .collect();
+            assert_eq!(values, vec![1, 2, 3]);
+            assert_eq!(input.get_from(fast_id).unwrap().get::<usize>(), Some(&3));
+            assert_eq!(input.get_from(slow_id).unwrap().get::<usize>(), Some(&1));
+            assert!(input.get_from(empty_id).is_none());
+            Output::empty()
+        });
+        reduce.set_predecessors(&[&slow, &empty, &medium, &fast]);
+
+        let mut job = Dag::with_tasks(vec![fast, medium, empty, slow, reduce]);
+        assert!(job.start().unwrap());
+    }
+}
+
+#[test]
+fn clone_structure_runs_independently() {
+    let a = DefaultTask::with_closure("a", |_, env| Output::new(env.get::<usize>("BASE").unwrap()));
+    let mut b = DefaultTask::with_closure("b", |input, _| {
+        let base = input.get_iter().next().unwrap().get::<usize>().unwrap();
+        Output::new(base * 10)
+    });
+    b.set_predecessors(&[&a]);
+    let template = Dag::with_tasks(vec![a, b]);
+
+    let handles: Vec<_> = (1..=3usize)
+        .map(|base| {
+            let mut job = template.clone_structure();
+            let mut env = EnvVar::new();
+            env.set("BASE", base);
+            job.set_env(env);
+            std::thread::spawn(move || {
+                assert!(job.start().unwrap());
+                *job.get_result::<usize>().unwrap()
+            })
+        })
+        .collect();
+    let results: Vec<usize> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(results, vec![10, 20, 30]);
+    assert_eq!(template.task_state("b"), None);
+}
+
+#[test]
+fn yaml_task_output_type() {
+    struct Sum;
+    impl Complex for Sum {
+        fn run(&self, input: Input, _env: Arc<EnvVar>) -> Output {
+            let mut ints = input.get_iter().filter_map(|c| c.get::<i64>());
+            let mut floats = input.get_iter().filter_map(|c| c.get::<f64>());
+            let json = input
+                .get_iter()
+                .find_map(|c| c.get::<serde_json::Value>())
+                .unwrap();
+            let sum = *ints.next().unwrap() as f64
+                + floats.next().unwrap()
+                + json["retries"].as_f64().unwrap();
+            Output::new(sum)
+        }
+    }
+
+    let mut actions: HashMap<String, Action> = HashMap::new();
+    actions.insert("d".to_string(), Action::Structure(Arc::new(Sum)));
+    let mut job = Dag::with_yaml("tests/config/output_type.yaml", actions).unwrap();
+    assert!(job.start().unwrap());
+    assert_eq!(*job.get_result::<f64>().unwrap(), 45.5);
+
+    let mut job = Dag::with_yaml("tests/config/output_type_mismatch.yaml", HashMap::new()).unwrap();
+    assert!(!job.start().unwrap());
+    assert!(job
+        .run_report()
+        .task("Count")
+        .unwrap()
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("forty-two"));
+}
+
+#[test]
+fn task_disabled() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::new(2usize));
+    let mut c = DefaultTask::with_closure("c", |input, _| {
+        Output::new(
+            input
+                .get_iter()
+                .map(|i| *i.get::<usize>().unwrap())
+                .sum::<usize>(),
+        )
+    });
+    b.set_predecessors(&[&a]);
+    b.set_enabled(false);
+    c.set_predecessors(&[&a, &b]);
+
+    let mut job = Dag::with_tasks(vec![a, b, c]);
+    assert!(job.start().unwrap());
+    assert_eq!(job.task_state("b"), Some(TaskState::Skipped));
+    // The disabled task contributes nothing to the input of its successor.
+    assert_eq!(*job.get_result::<usize>().unwrap(), 1);
+}
+
+#[test]
+fn on_complete_receives_report() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::error("broken".to_string()));
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::empty());
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&b]);
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let mut job = Dag::with_tasks(vec![a, b, c]);
+    let collected = reports.clone();
+    job.on_complete(move |report| collected.lock().unwrap().push(report.clone()));
+    assert!(!job.start().unwrap());
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert!(!report.success);
+    assert_eq!(report.task("a").unwrap().state, TaskState::Succeeded);
+    assert_eq!(report.task("b").unwrap().state, TaskState::Failed);
+    assert_eq!(report.task("b").unwrap().error.as_deref(), Some("broken"));
+    assert_eq!(report.task("c").unwrap().state, TaskState::Skipped);
+}
+
+#[test]
+fn on_complete_on_init_failure() {
+    let mut a = DefaultTask::with_closure("a", |_, _| Output::empty());
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::empty());
+    a.set_predecessors(&[&b]);
+    b.set_predecessors(&[&a]);
+
+    let called = Arc::new(Mutex::new(None));
+    let mut job = Dag::with_tasks(vec![a, b]);
+    let flag = called.clone();
+    job.on_complete(move |report| *flag.lock().unwrap() = Some(report.success));
+    assert!(job.start().is_err());
+    assert_eq!(*called.lock().unwrap(), Some(false));
+}
+
+/// Records the calls of the middleware, tagged with its own name.
+struct RecordingMiddleware(&'static str, Arc<Mutex<Vec<String>>>);
+
+impl TaskMiddleware for RecordingMiddleware {
+    fn before(&self, task_name: &str, _input: &Input) {
+        let call = format!("{} before {}", self.0, task_name);
+        self.1.lock().unwrap().push(call);
+    }
+
+    fn after(&self, task_name: &str, output: &Output) {
+        let call = format!("{} after {} {}", self.0, task_name, output.is_err());
+        self.1.lock().unwrap().push(call);
+    }
+}
+
+#[test]
+fn middlewares_wrap_tasks() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |_, _| -> Output { panic!("broken") });
+    b.set_predecessors(&[&a]);
+
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let mut job = Dag::with_tasks(vec![a, b]);
+    job.add_middleware(RecordingMiddleware("outer", calls.clone()));
+    job.add_middleware(RecordingMiddleware("inner", calls.clone()));
+    assert!(!job.start().unwrap());
+
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![
+            "outer before a",
+            "inner before a",
+            "inner after a false",
+            "outer after a false",
+            "outer before b",
+            "inner before b",
+            "inner after b true",
+            "outer after b true",
+        ]
+    );
+}
+
+#[test]
+fn middleware_panicking_before() {
+    struct Refusing;
+
+    impl TaskMiddleware for Refusing {
+        fn before(&self, _task_name: &str, _input: &Input) {
+            panic!("refused");
+        }
+    }
+
+    let ran = Arc::new(Mutex::new(false));
+    let flag = ran.clone();
+    let a = DefaultTask::with_closure("a", move |_, _| {
+        *flag.lock().unwrap() = true;
+        Output::empty()
+    });
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    let mut job = Dag::with_tasks(vec![a]);
+    job.add_middleware(RecordingMiddleware("outer", calls.clone()));
+    job.add_middleware(Refusing);
+    job.add_middleware(RecordingMiddleware("inner", calls.clone()));
+    assert!(!job.start().unwrap());
+    assert!(!*ran.lock().unwrap());
+    assert_eq!(job.task_state("a"), Some(TaskState::Failed));
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec!["outer before a", "outer after a true"]
+    );
+}
+
+#[test]
+fn start_with_timeout() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |_, _| {
+        std::thread::sleep(Duration::from_secs(2));
+        Output::empty()
+    });
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::empty());
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&b]);
+
+    let mut job = Dag::with_tasks(vec![a, b, c]);
+    let res = job.start_with_timeout(Duration::from_millis(200));
+    assert!(matches!(res, Err(DagError::Timeout)));
+    assert_eq!(job.task_state("a"), Some(TaskState::Succeeded));
+    assert_ne!(job.task_state("c"), Some(TaskState::Succeeded));
+}
+
+#[test]
+fn finish_within_timeout() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut job = Dag::with_tasks(vec![a]);
+    assert!(job.start_with_timeout(Duration::from_secs(10)).unwrap());
+}
+
+#[test]
+fn add_mapped_tasks_from_range() {
+    let mut job = Dag::with_tasks(Vec::<DefaultTask>::new());
+    let ids = job.add_mapped_tasks(0..1000usize, |i| {
+        DefaultTask::with_closure(&format!("Task {}", i), move |_, _| Output::new(i))
+    });
+    assert_eq!(ids.len(), 1000);
+
+    let mut sum = DefaultTask::with_closure("sum", |input, _| {
+        Output::new(
+            input
+                .get_iter()
+                .map(|i| *i.get::<usize>().unwrap())
+                .sum::<usize>(),
+        )
+    });
+    sum.set_predecessors_by_id(ids);
+    job.add_task(sum);
+
+    assert!(job.start().unwrap());
+    assert_eq!(
+        *job.get_result::<usize>().unwrap(),
+        (0..1000usize).sum::<usize>()
+    );
+}
+
+#[test]
+fn declared_types_checked_at_init() {
+    let mut a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    a.declares_output::<usize>();
+    let b = DefaultTask::with_closure("b", |_, _| Output::new("b".to_string()));
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::empty());
+    c.declares_input::<usize>();
+    // b declares nothing, so the edge b -> c is not checked.
+    c.set_predecessors(&[&a, &b]);
+    assert!(Dag::with_tasks(vec![a, b, c]).start().unwrap());
+
+    let mut a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    a.declares_output::<usize>();
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::empty());
+    c.declares_input::<String>();
+    c.set_predecessors(&[&a]);
+    let res = Dag::with_tasks(vec![a, c]).start();
+    assert!(matches!(
+        res,
+        Err(DagError::TypeMismatch { producer, consumer, .. }) if producer == "a" && consumer == "c"
+    ));
+}
+
+#[test]
+fn rerun_from_failed_task() {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    let a_runs = Arc::new(AtomicUsize::new(0));
+    let fixed = Arc::new(AtomicBool::new(false));
+
+    let counter = a_runs.clone();
+    let a = DefaultTask::with_closure("a", move |_, _| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Output::new(1usize)
+    });
+    let flag = fixed.clone();
+    let mut b = DefaultTask::with_closure("b", move |input, _| {
+        if !flag.load(Ordering::SeqCst) {
+            return Output::error("not fixed yet".to_string());
+        }
+        Output::new(input.get_iter().next().unwrap().get::<usize>().unwrap() + 1)
+    });
+    let mut c = DefaultTask::with_closure("c", |input, _| {
+        Output::new(input.get_iter().next().unwrap().get::<usize>().unwrap() * 10)
+    });
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&b]);
+
+    let mut job = Dag::with_tasks(vec![a, b, c]);
+    assert!(!job.start().unwrap());
+    assert_eq!(job.task_state("c"), Some(TaskState::Skipped));
+
+    fixed.store(true, Ordering::SeqCst);
+    assert!(job.rerun_from("b").unwrap());
+    assert_eq!(a_runs.load(Ordering::SeqCst), 1);
+    assert_eq!(*job.get_result::<usize>().unwrap(), 20);
+
+    assert!(matches!(
+        job.rerun_from("no such task"),
+        Err(DagError::TaskNotFound(_))
+    ));
+}
+
+#[test]
+fn task_temp_dirs() {
+    let write = |name: &'static str| {
+        DefaultTask::with_closure(name, move |_, env| {
+            let dir = env.get::<String>(EnvVar::TASK_TMPDIR).unwrap();
+            std::fs::write(format!("{}/scratch", dir), name).unwrap();
+            Output::new(dir)
+        })
+    };
+    let (a, b) = (write("a"), write("b"));
+    let shell = DefaultTask::with_action(
+        "shell",
+        CommandAction::new("test -d \"$TASK_TMPDIR\" && echo $TASK_TMPDIR"),
+    );
+    let mut fail = DefaultTask::with_closure("fail", |_, env| {
+        Output::error(env.get::<String>(EnvVar::TASK_TMPDIR).unwrap())
+    });
+    fail.set_predecessors(&[&a, &b]);
+    let ids = [a.id(), b.id()];
+
+    let mut job = Dag::with_tasks(vec![a, b, shell, fail]).keep_going();
+    job.set_task_temp_dirs(true);
+    job.set_keep_failed_temp_dirs(true);
+    assert!(!job.start().unwrap());
+
+    let results = job.get_results::<String>();
+    let (dir_a, dir_b) = (
+        results[&ids[0]].clone().unwrap(),
+        results[&ids[1]].clone().unwrap(),
+    );
+    assert_ne!(dir_a, dir_b);
+    assert!(!std::path::Path::new(dir_a.as_str()).exists());
+    assert_eq!(job.task_state("shell"), Some(TaskState::Succeeded));
+    let kept = job
+        .run_report()
+        .task("fail")
+        .unwrap()
+        .error
+        .clone()
+        .unwrap();
+    assert!(std::path::Path::new(&kept).is_dir());
+    std::fs::remove_dir_all(kept).unwrap();
+}
+
+#[test]
+fn root_input_given_to_every_root() {
+    let double = DefaultTask::with_closure("double", |input, _| {
+        let value = input.get_iter().next().unwrap().get::<usize>().unwrap();
+        Output::new(value * 2)
+    });
+    let triple = DefaultTask::with_closure("triple", |input, _| {
+        let value = input.get_iter().next().unwrap().get::<usize>().unwrap();
+        Output::new(value * 3)
+    });
+    let mut sum = DefaultTask::with_closure("sum", |input, _| {
+        let values: Vec<usize> = input
+            .get_iter()
+            .map(|content| *content.get::<usize>().unwrap())
+            .collect();
+        Output::new(values.iter().sum::<usize>())
+    });
+    sum.set_predecessors(&[&double, &triple]);
+
+    let mut job = Dag::with_tasks(vec![double, triple, sum]);
+    job.set_root_input(10usize);
+    assert!(job.start().unwrap());
+    assert_eq!(*job.get_result::<usize>().unwrap(), 50);
+}
+
+#[test]
+fn assertions() {
+    let count = DefaultTask::with_closure("count", |_, _| Output::new(3usize));
+    let mut positive = DefaultTask::with_action(
+        "positive",
+        AssertAction::new(|count: &usize| *count > 0, "no items"),
+    );
+    positive.set_predecessors(&[&count]);
+    let mut double = DefaultTask::with_closure("double", |input, _| {
+        let count = input.get_iter().next().unwrap().get::<usize>().unwrap();
+        Output::new(count * 2)
+    });
+    double.set_predecessors(&[&positive]);
+    let mut small = DefaultTask::with_action(
+        "small",
+        AssertAction::new(|count: &usize| *count < 5, "too many items"),
+    );
+    small.set_predecessors(&[&double]);
+
+    let mut job = Dag::with_tasks(vec![count, positive, double, small]);
+    assert!(!job.start().unwrap());
+    assert_eq!(job.task_state("double"), Some(TaskState::Succeeded));
+    let report = job.run_report();
+    assert_eq!(
+        report.task("small").unwrap().error.as_deref(),
+        Some("too many items: 6")
+    );
+}
+
+#[test]
+fn run_subset_by_label() {
+    let mut job = Dag::with_yaml("tests/config/labels.yaml", HashMap::new()).unwrap();
+    assert!(job
+        .run_subset(|labels| labels.matches("tier", "deploy"))
+        .unwrap());
+    // The predecessors of the selected task run, whatever their labels.
+    assert_eq!(job.task_state("Build"), Some(TaskState::Succeeded));
+    assert_eq!(job.task_state("Deploy"), Some(TaskState::Succeeded));
+    assert_eq!(job.task_state("Docs"), Some(TaskState::Skipped));
+    assert_eq!(job.task_state("Announce"), Some(TaskState::Skipped));
+
+    let mut job = Dag::with_yaml("tests/config/labels.yaml", HashMap::new()).unwrap();
+    assert!(matches!(
+        job.run_subset(|labels| labels.contains("owner")),
+        Err(DagError::EmptyJob)
+    ));
+}
+
+#[test]
+fn compressed_outputs_are_transparent() {
+    let text = "some line of text\n".repeat(10_000);
+    let expected = text.clone();
+    let big = DefaultTask::with_closure("big", move |_, _| Output::new(text.clone()));
+    let small = DefaultTask::with_closure("small", |_, _| Output::new("tiny".to_string()));
+    let lines = DefaultTask::with_action("lines", CommandAction::new("seq 1 20000"));
+    let mut check = DefaultTask::with_closure("check", move |input, _| {
+        let mut contents = input.get_iter();
+        let big = contents.next().unwrap().get::<String>().unwrap();
+        let small = contents.next().unwrap().get::<String>().unwrap();
+        let lines = contents.next().unwrap().get::<Content>().unwrap();
+        let (stdout, _) = lines.get::<(Vec<String>, Vec<String>)>().unwrap();
+        assert_eq!(big, &expected);
+        assert_eq!(small, "tiny");
+        Output::new(stdout.clone())
+    });
+    check.set_predecessors(&[&big, &small, &lines]);
+    // The successors reading the output at the same time share its decompressed value.
+    let barrier = Arc::new(std::sync::Barrier::new(2));
+    let addresses = Arc::new(Mutex::new(Vec::new()));
+    let reader = |name: &str| {
+        let (barrier, addresses) = (barrier.clone(), addresses.clone());
+        let mut reader = DefaultTask::with_closure(name, move |input, _| {
+            let text = input.get_iter().next().unwrap().get::<String>().unwrap();
+            addresses.lock().unwrap().push(text.as_ptr() as usize);
+            barrier.wait();
+            Output::empty()
+        });
+        reader.set_predecessors(&[&big]);
+        reader
+    };
+    let (first, second) = (reader("first"), reader("second"));
+
+    let mut job = Dag::with_tasks(vec![big, small, lines, check, first, second]);
+    job.set_compress_outputs(true);
+    job.set_result_task("check");
+    assert!(job.start().unwrap());
+    let stdout = job.get_result::<Vec<String>>().unwrap();
+    assert_eq!(stdout.len(), 20000);
+    assert_eq!(stdout[19999], "20000");
+    let addresses = addresses.lock().unwrap();
+    assert_eq!(addresses.len(), 2);
+    assert_eq!(addresses[0], addresses[1]);
+}
+
+#[test]
+fn failure_handler_receives_failed_tasks() {
+    let runs = Arc::new(Mutex::new(Vec::new()));
+    let handler = |runs: Arc<Mutex<Vec<Vec<String>>>>| {
+        DefaultTask::with_closure("On failure", move |input, _| {
+            let names = input
+                .get_iter()
+                .map(|failure| failure.get::<TaskReport>().unwrap().name.clone())
+                .collect();
+            runs.lock().unwrap().push(names);
+            Output::error("cannot notify".to_string())
+        })
+    };
+
+    let ok = DefaultTask::with_closure("ok", |_, _| Output::empty());
+    let mut job = Dag::with_tasks(vec![ok]);
+    job.set_on_failure_task(handler(runs.clone()));
+    assert!(job.start().unwrap());
+    assert!(runs.lock().unwrap().is_empty());
+
+    let a = DefaultTask::with_closure("a", |_, _| Output::error("bad a".to_string()));
+    let b = DefaultTask::with_closure("b", |_, _| panic!("bad b"));
+    let c = DefaultTask::with_closure("c", |_, _| Output::empty());
+    let mut job = Dag::with_tasks(vec![a, b, c]).keep_going();
+    job.set_on_failure_task(handler(runs.clone()));
+    // The failure of the handler itself does not change the result.
+    assert!(!job.start().unwrap());
+    assert_eq!(*runs.lock().unwrap(), vec![vec!["a", "b"]]);
+    let events = job.event_log();
+    assert_eq!(events.last().unwrap().task, "On failure");
+    assert_eq!(events.last().unwrap().kind, RunEventKind::Failed);
+}
+
+#[test]
+fn command_quoted_arguments() {
+    let stdout = |cmd: &str, shell: bool| {
+        let mut action = CommandAction::new(cmd);
+        action.set_shell(shell);
+        let mut job = Dag::with_tasks(vec![DefaultTask::with_action("cmd", action)]);
+        if !job.start().unwrap() {
+            return None;
+        }
+        let content = job.get_result::<Content>().unwrap();
+        let (stdout, _) = content.get::<(Vec<String>, Vec<String>)>().unwrap();
+        Some(stdout.clone())
+    };
+    let printf = r#"printf '%s\n' "hello world" 'a b' c\ d"#;
+    for shell in [true, false] {
+        assert_eq!(
+            stdout(printf, shell).unwrap(),
+            vec!["hello world", "a b", "c d"]
+        );
+    }
+    // Without the shell, pipes are not interpreted.
+    assert_eq!(stdout("echo a | tr a b", true).unwrap(), vec!["b"]);
+    assert_eq!(
+        stdout("echo a | tr a b", false).unwrap(),
+        vec!["a | tr a b"]
+    );
+    assert_eq!(stdout("echo \"unterminated", false), None);
+
+    let yaml = "dagrs:\n  a:\n    name: \"Task 1\"\n    shell: false\n    cmd: echo \"a;b\"\n";
+    let mut job = Dag::with_yaml_str(yaml, HashMap::new()).unwrap();
+    assert!(job.start().unwrap());
+    let content = job.get_result::<Content>().unwrap();
+    let (stdout, _) = content.get::<(Vec<String>, Vec<String>)>().unwrap();
+    assert_eq!(stdout, &vec!["a;b".to_string()]);
+}
+
+#[test]
+fn conditional_retries() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A task failing with the given errors in turn, then succeeding.
+    let flaky = |name: &str, errors: Vec<&'static str>, runs: Arc<AtomicUsize>| {
+        DefaultTask::with_closure(name, move |_, _| {
+            match errors.get(runs.fetch_add(1, Ordering::SeqCst)) {
+                Some(err) => Output::error(err.to_string()),
+                None => Output::empty(),
+            }
+        })
+    };
+    let is_timeout = |out: &Output| matches!(out, Output::Err(err) if err.contains("timeout"));
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let mut task = flaky("transient", vec!["timeout", "timeout"], runs.clone());
+    let mut policy = RetryPolicy::new(3);
+    policy.set_backoff(Duration::from_millis(10));
+    task.set_retry(policy);
+    task.set_retry_if(is_timeout);
+    let mut job = Dag::with_tasks(vec![task]);
+    assert!(job.start().unwrap());
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+    // The condition is kept whatever the order of the calls.
+    let runs = Arc::new(AtomicUsize::new(0));
+    let mut task = flaky("syntax", vec!["syntax error"], runs.clone());
+    task.set_retry_if(is_timeout);
+    task.set_retry(RetryPolicy::new(3));
+    let mut job = Dag::with_tasks(vec![task]);
+    assert!(!job.start().unwrap());
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+    // Without a condition every error is retried, until the retries run out.
+    let runs = Arc::new(AtomicUsize::new(0));
+    let mut task = flaky("any", vec!["a", "b", "c"], runs.clone());
+    task.set_retry(RetryPolicy::new(2));
+    let mut job = Dag::with_tasks(vec![task]);
+    assert!(!job.start().unwrap());
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+    assert_eq!(job.task_state("any"), Some(TaskState::Failed));
+}
+
+#[test]
+fn retries_on_exit_codes() {
+    let dir = tempfile::tempdir().unwrap();
+    // A command counting its runs in a file, and exiting with the given code until its third.
+    let counted = |name: &str, code: i32| {
+        let file = dir.path().join(format!("runs-{}", name));
+        let cmd = format!(
+            "n=$(cat {0} 2>/dev/null || echo 0); n=$((n+1)); echo $n > {0}; [ $n -ge 3 ] || exit {1}",
+            file.display(),
+            code
+        );
+        let runs = move || std::fs::read_to_string(&file).unwrap().trim().to_string();
+        (
+            DefaultTask::with_action(name, CommandAction::new(&cmd)),
+            runs,
+        )
+    };
+
+    let (mut task, runs) = counted("codes", 75);
+    task.set_retry(RetryPolicy::new(3));
+    task.set_retry_on_codes(&[75]);
+    assert!(Dag::with_tasks(vec![task]).start().unwrap());
+    assert_eq!(runs(), "3");
+
+    // The codes take precedence over the condition.
+    let (mut task, runs) = counted("condition", 1);
+    task.set_retry(RetryPolicy::new(3));
+    task.set_retry_if(|_| true);
+    task.set_retry_on_codes(&[75]);
+    assert!(!Dag::with_tasks(vec![task]).start().unwrap());
+    assert_eq!(runs(), "1");
+
+    // The codes also apply to the default policy of the dag.
+    let (mut task, runs) = counted("default", 75);
+    task.set_retry_on_codes(&[75]);
+    let mut job = Dag::with_tasks(vec![task]);
+    job.set_default_retry(RetryPolicy::new(3));
+    assert!(job.start().unwrap());
+    assert_eq!(runs(), "3");
+}
+
+#[test]
+fn allowed_failures() {
+    let mut job = Dag::with_yaml("tests/config/allow_failure.yaml", HashMap::new()).unwrap();
+    assert!(job.start().unwrap());
+    assert_eq!(job.task_state("Lint"), Some(TaskState::FailedAllowed));
+    assert_eq!(job.task_state("Deploy"), Some(TaskState::Succeeded));
+    let report = job.run_report();
+    assert!(report.success);
+    assert!(report.task("Lint").unwrap().error.is_some());
+
+    let mut flaky = DefaultTask::with_closure("flaky", |_, _| panic!("best effort"));
+    flaky.set_allow_failure(true);
+    let mut next =
+        DefaultTask::with_closure("next", |input, _| Output::new(input.get_iter().count()));
+    next.set_predecessors(&[&flaky]);
+    let mut job = Dag::with_tasks(vec![flaky, next]);
+    assert!(job.start().unwrap());
+    assert_eq!(job.task_state("flaky"), Some(TaskState::FailedAllowed));
+    assert_eq!(*job.get_result::<usize>().unwrap(), 0);
+}
+
+#[test]
+fn stall_detection() {
+    let stuck = || {
+        DefaultTask::with_closure("stuck", |_, _| {
+            std::thread::sleep(Duration::from_millis(600));
+            Output::empty()
+        })
+    };
+
+    // Without aborting, a stall is only reported.
+    let mut job = Dag::with_tasks(vec![stuck()]);
+    job.set_stall_timeout(Duration::from_millis(100));
+    assert!(job.start().unwrap());
+
+    let mut job = Dag::with_tasks(vec![stuck()]);
+    job.set_stall_timeout(Duration::from_millis(100));
+    job.set_abort_on_stall(true);
+    assert!(matches!(job.start(), Err(DagError::Stalled(_))));
+    assert_eq!(job.task_state("stuck"), Some(TaskState::Running));
+}
+
+#[test]
+fn inputs_given_as_env() {
+    let users = DefaultTask::with_closure("Fetch users", |_, _| {
+        Output::new(serde_json::json!({ "users": ["ann", "bob"] }))
+    });
+    let huge = DefaultTask::with_closure("huge", |_, _| Output::new("x".repeat(100 * 1024)));
+    // A lazy output is not computed only to be given as a variable.
+    let lazy = DefaultTask::with_closure("lazy", |_, _| Output::lazy(|| "late".to_string()));
+    let mut action = CommandAction::new(
+        "printf '{\"users\": %s, \"huge\": %s, \"lazy\": %s}' \"$DAGRS_INPUT_FETCH_USERS\" \"${DAGRS_INPUT_HUGE:-null}\" \"${DAGRS_INPUT_LAZY:-null}\"",
+    );
+    action.set_output_format(OutputFormat::Json);
+    let mut read = DefaultTask::with_action("read", action);
+    read.set_predecessors(&[&users, &huge, &lazy]);
+
+    let mut job = Dag::with_tasks(vec![users, huge, lazy, read]);
+    job.set_inputs_as_env(true);
+    assert!(job.start().unwrap());
+    assert_eq!(
+        *job.get_result::<serde_json::Value>().unwrap(),
+        serde_json::json!({ "users": { "users": ["ann", "bob"] }, "huge": null, "lazy": null })
+    );
+}
+
+#[test]
+fn resource_budget() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let tasks: Vec<DefaultTask> = (0..4)
+        .map(|i| {
+            let (running, peak) = (running.clone(), peak.clone());
+            let mut task = DefaultTask::with_closure(&format!("task {}", i), move |_, _| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(50));
+                running.fetch_sub(1, Ordering::SeqCst);
+                Output::empty()
+            });
+            task.set_resource_request(600, 100);
+            task
+        })
+        .collect();
+    let mut job = Dag::with_tasks(tasks);
+    job.set_resource_budget(1000, 1000);
+    assert!(job.start().unwrap());
+    assert_eq!(peak.load(Ordering::SeqCst), 1);
+
+    let mut greedy = DefaultTask::with_closure("greedy", |_, _| Output::empty());
+    greedy.set_resource_request(100, 4096);
+    let mut job = Dag::with_tasks(vec![greedy]);
+    job.set_resource_budget(1000, 1024);
+    assert!(matches!(job.start(), Err(DagError::OverBudget(name)) if name == "greedy"));
+}
+
+#[test]
+fn run_task_in_isolation() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let mut env = EnvVar::new();
+    env.set("factor", 3usize);
+    let task = DefaultTask::with_closure("scale", |input, env| {
+        let factor = env.get::<usize>("factor").unwrap();
+        let sum: usize = input.get_iter().filter_map(|c| c.get::<usize>()).sum();
+        Output::new(sum * factor)
+    });
+    let input = Input::new(vec![Content::new(2usize), Content::new(5usize)]);
+    let out = task.run_isolated(input, Arc::new(env));
+    assert!(matches!(out, Output::Out(Some(c)) if c.get::<usize>() == Some(&21)));
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let counter = runs.clone();
+    let mut task = DefaultTask::with_closure("flaky", move |_, _| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            panic!("first run");
+        }
+        Output::empty()
+    });
+    task.set_retry(RetryPolicy::new(1));
+    let out = task.run_isolated(Input::new(Vec::new()), Arc::new(EnvVar::new()));
+    assert!(!out.is_err());
+    assert_eq!(runs.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn finish_ends_the_run() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let sleep_then = |name: &str, millis: u64, out: fn() -> Output| {
+        DefaultTask::with_closure(name, move |_, _| {
+            std::thread::sleep(Duration::from_millis(millis));
+            out()
+        })
+    };
+    let found = sleep_then("found", 0, || Output::finish("answer".to_string()));
+    let mut next = DefaultTask::with_closure("next", |_, _| Output::empty());
+    next.set_predecessors(&[&found]);
+    let slow = sleep_then("slow", 100, Output::empty);
+    let mut after_slow = DefaultTask::with_closure("after slow", |_, _| Output::empty());
+    after_slow.set_predecessors(&[&slow]);
+    let failing = sleep_then("failing", 50, || Output::error("too late".to_string()));
+    let mut job = Dag::with_tasks(vec![found, next, slow, after_slow, failing]);
+    let handled = Arc::new(AtomicBool::new(false));
+    let flag = handled.clone();
+    job.set_on_failure_task(DefaultTask::with_closure("handler", move |_, _| {
+        flag.store(true, Ordering::SeqCst);
+        Output::empty()
+    }));
+    assert!(job.start().unwrap());
+    assert_eq!(*job.get_result::<String>().unwrap(), "answer");
+    assert_eq!(job.task_state("found"), Some(TaskState::Succeeded));
+    assert_eq!(job.task_state("next"), Some(TaskState::Skipped));
+    assert_eq!(job.task_state("slow"), Some(TaskState::Succeeded));
+    assert_eq!(job.task_state("after slow"), Some(TaskState::Skipped));
+    assert_eq!(job.task_state("failing"), Some(TaskState::Failed));
+    assert!(!handled.load(Ordering::SeqCst));
+
+    // A task cannot finish a dag that has already failed.
+    let broken = sleep_then("broken", 0, || Output::error("broken".to_string()));
+    let late = sleep_then("late", 50, || Output::finish(1usize));
+    let mut job = Dag::with_tasks(vec![broken, late]);
+    assert!(!job.start().unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn run_in_async_context() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |input, _| {
+        Output::new(input.get_iter().next().unwrap().get::<usize>().unwrap() + 1)
+    });
+    b.set_predecessors(&[&a]);
+    let mut job = Dag::with_tasks(vec![a, b]);
+    let job = tokio::spawn(async move {
+        assert!(job.run_async().await.unwrap());
+        job
+    })
+    .await
+    .unwrap();
+    assert_eq!(*job.get_result::<usize>().unwrap(), 2);
+
+    let slow = DefaultTask::with_closure("slow", |_, _| {
+        std::thread::sleep(Duration::from_millis(500));
+        Output::empty()
+    });
+    let mut job = Dag::with_tasks(vec![slow]);
+    job.set_timeout(Duration::from_millis(50));
+    assert!(matches!(job.run_async().await, Err(DagError::Timeout)));
+}
+
+#[test]
+fn task_groups() {
+    let noop = |name: &str| DefaultTask::with_closure(name, |_, _| Output::empty());
+    let group = |fail: bool| {
+        let mut group = TaskGroup::new("db");
+        group.set_setup(noop("start db"));
+        group.set_teardown(noop("stop db"));
+        group.add_task(DefaultTask::with_closure("migrate", move |_, _| {
+            if fail {
+                Output::error("migration failed".to_string())
+            } else {
+                Output::empty()
+            }
+        }));
+        group
+    };
+
+    // The teardown runs even though a member failed.
+    let mut job = Dag::with_tasks(vec![noop("lint")]);
+    job.add_group(group(true));
+    assert!(!job.start().unwrap());
+    assert_eq!(job.task_state("stop db"), Some(TaskState::Succeeded));
+    let report = job.run_report();
+    let db = report.group("db").unwrap();
+    assert_eq!(db.state, TaskState::Failed);
+    assert_eq!(db.tasks, vec!["migrate", "start db", "stop db"]);
+    assert!(report.group("lint").is_none());
+
+    // Cancelling the group skips it along with its successors outside of the group, and the
+    // tasks joining the group by its label.
+    let mut group = TaskGroup::new("db");
+    group.set_setup(noop("start db"));
+    group.set_teardown(noop("stop db"));
+    let migrate = noop("migrate");
+    let mut report = noop("report");
+    report.set_predecessors(&[&migrate]);
+    group.add_task(migrate);
+    let mut seed = noop("seed");
+    seed.set_label(TaskGroup::LABEL, "db");
+    let mut job = Dag::with_tasks(vec![noop("lint"), seed, report]);
+    job.add_group(group);
+    job.cancel_group("db");
+    assert!(job.start().unwrap());
+    for name in ["start db", "migrate", "seed", "stop db", "report"] {
+        assert_eq!(job.task_state(name), Some(TaskState::Skipped), "{}", name);
+    }
+    assert_eq!(job.task_state("lint"), Some(TaskState::Succeeded));
+    let db = job.run_report().group("db").unwrap().clone();
+    assert!(db.cancelled);
+    assert_eq!(db.state, TaskState::Skipped);
+
+    // A member cancels the rest of its group while it runs.
+    let mut job = Dag::with_tasks(Vec::<DefaultTask>::new());
+    let canceller = job.group_canceller();
+    let mut group = TaskGroup::new("db");
+    group.set_setup(noop("start db"));
+    group.set_teardown(noop("stop db"));
+    let abort = DefaultTask::with_closure("abort", move |_, _| {
+        canceller.cancel("db");
+        Output::empty()
+    });
+    let mut after_abort = noop("after abort");
+    after_abort.set_predecessors(&[&abort]);
+    group.add_task(abort);
+    group.add_task(after_abort);
+    job.add_group(group);
+    assert!(job.start().unwrap());
+    assert_eq!(job.task_state("abort"), Some(TaskState::Succeeded));
+    assert_eq!(job.task_state("after abort"), Some(TaskState::Skipped));
+    assert_eq!(job.task_state("stop db"), Some(TaskState::Succeeded));
+}
+
+#[test]
+fn script_temp_dir() {
+    let run = |keep: bool| {
+        let dir = tempfile::tempdir().unwrap();
+        let tasks = (0..3)
+            .map(|i| {
+                DefaultTask::with_action(
+                    &format!("script {}", i),
+                    ScriptAction::new("sh", "echo hi"),
+                )
+            })
+            .collect();
+        let mut job = Dag::with_tasks(tasks);
+        job.set_script_temp_dir(dir.path());
+        job.set_keep_script_files(keep);
+        assert!(job.start().unwrap());
+        let files: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        (dir, files)
+    };
+    let (_dir, files) = run(false);
+    assert!(files.is_empty());
+    let (_dir, files) = run(true);
+    assert_eq!(files.len(), 3);
+    assert_eq!(std::fs::read_to_string(&files[0]).unwrap(), "echo hi");
+
+    // The settings of a previous run do not leak into the next one.
+    let dir = tempfile::tempdir().unwrap();
+    let task = DefaultTask::with_action("script", ScriptAction::new("sh", "echo hi"));
+    let mut job = Dag::with_tasks(vec![task]);
+    job.set_script_temp_dir(dir.path());
+    job.set_keep_script_files(true);
+    assert!(job.start().unwrap());
+    let mut job = job.clone_structure();
+    job.set_keep_script_files(false);
+    assert!(job.start().unwrap());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_mode_runs_every_task() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::error("no network".to_string()));
+    let mut b = DefaultTask::with_closure("b", |input, _| Output::new(input.get_iter().count()));
+    b.set_predecessors(&[&a]);
+    let mut c = DefaultTask::with_closure("c", |_, _| -> Output { panic!("broken") });
+    c.set_predecessors(&[&b]);
+    let d = DefaultTask::with_closure("d", |_, _| Output::empty());
+    let mut job = Dag::with_tasks(vec![a, b, c, d]);
+    let report = job.start_test_mode().unwrap();
+    assert!(!report.success);
+    let state = |name| report.task(name).unwrap().state;
+    assert_eq!(state("a"), TaskState::Failed);
+    assert_eq!(state("b"), TaskState::Succeeded);
+    assert_eq!(state("c"), TaskState::Failed);
+    assert_eq!(state("d"), TaskState::Succeeded);
+    assert_eq!(
+        report.task("a").unwrap().error.as_deref(),
+        Some("no network")
+    );
+    assert_eq!(
+        *job.get_results::<usize>()
+            .into_values()
+            .flatten()
+            .next()
+            .unwrap(),
+        0
+    );
+}
+
+#[test]
+fn retained_inputs() {
+    let make = || {
+        let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+        let b = DefaultTask::with_closure("b", |_, _| Output::empty());
+        let c = DefaultTask::with_closure("c", |_, _| Output::new(3usize));
+        let mut sum = DefaultTask::with_closure("sum", |_, _| Output::empty());
+        sum.set_predecessors(&[&a, &b, &c]);
+        let ids = (a.id(), c.id());
+        (Dag::with_tasks(vec![a, b, c, sum]), ids)
+    };
+
+    let (mut job, (a, c)) = make();
+    job.set_retain_inputs(true);
+    assert!(job.start().unwrap());
+    let input = job.get_task_input("sum").unwrap();
+    let values: Vec<usize> = input
+        .get_iter()
+        .map(|c| *c.get::<usize>().unwrap())
+        .collect();
+    assert_eq!(values, vec![1, 3]);
+    assert_eq!(input.get_from(a).unwrap().get::<usize>(), Some(&1));
+    assert_eq!(input.get_from(c).unwrap().get::<usize>(), Some(&3));
+    assert_eq!(job.get_task_input("a").unwrap().get_iter().count(), 0);
+    assert!(job.get_task_input("missing").is_none());
+
+    let (mut job, _) = make();
+    assert!(job.start().unwrap());
+    assert!(job.get_task_input("sum").is_none());
+}
+
+#[test]
+fn eager_output_drop() {
+    use std::sync::Weak;
+
+    let run = |eager: bool| {
+        let (sender, receiver) = std::sync::mpsc::channel::<Weak<String>>();
+        let sender = Mutex::new(sender);
+        let a = DefaultTask::with_closure("a", move |_, _| {
+            let value = Arc::new("x".repeat(1 << 20));
+            sender.lock().unwrap().send(Arc::downgrade(&value)).unwrap();
+            Output::Out(Some(Content::from_arc(value)))
+        });
+        let mut b = DefaultTask::with_closure("b", |input, _| {
+            Output::new(
+                input
+                    .get_iter()
+                    .next()
+                    .unwrap()
+                    .get::<String>()
+                    .unwrap()
+                    .len(),
+            )
+        });
+        b.set_predecessors(&[&a]);
+        let mut c = DefaultTask::with_closure("c", |input, _| {
+            Output::new(*input.get_iter().next().unwrap().get::<usize>().unwrap() * 2)
+        });
+        c.set_predecessors(&[&b]);
+        let mut job = Dag::with_tasks(vec![a, b, c]);
+        job.set_eager_output_drop(eager);
+        assert!(job.start().unwrap());
+        assert_eq!(*job.get_result::<usize>().unwrap(), 2 << 20);
+        receiver.recv().unwrap().upgrade().is_none()
+    };
+    assert!(run(true));
+    assert!(!run(false));
+}
+
+#[test]
+fn output_transform() {
+    let colored = DefaultTask::with_closure("colored", |_, _| {
+        Output::new("\u{1b}[32mok\u{1b}[0m".to_string())
+    });
+    let mut flaky = DefaultTask::with_closure("flaky", |_, _| Output::error("soft".to_string()));
+    flaky.set_predecessors(&[&colored]);
+    let mut last = DefaultTask::with_closure("last", |input, _| {
+        let texts: Vec<String> = input
+            .get_iter()
+            .map(|c| c.get::<String>().unwrap().clone())
+            .collect();
+        Output::new(texts.join(","))
+    });
+    last.set_predecessors(&[&colored, &flaky]);
+    let mut job = Dag::with_tasks(vec![colored, flaky, last]);
+    job.set_output_transform(|name, out| match out {
+        Output::Out(Some(content)) => match content.get::<String>() {
+            Some(text) if name == "colored" => {
+                Output::new(text.replace("\u{1b}[32m", "").replace("\u{1b}[0m", ""))
+            }
+            _ => Output::Out(Some(content)),
+        },
+        Output::Err(err) if err == "soft" => Output::new("recovered".to_string()),
+        out => out,
+    });
+    assert!(job.start().unwrap());
+    assert_eq!(*job.get_result::<String>().unwrap(), "ok,recovered");
+}
+
+#[test]
+fn command_exit_codes() {
+    let env = Arc::new(EnvVar::new());
+    let run = |cmd: &str, codes: &[i32]| {
+        let mut action = CommandAction::new(cmd);
+        action.set_success_codes(codes);
+        DefaultTask::with_action("cmd", action).run_isolated(Input::new(Vec::new()), env.clone())
+    };
+    let out = run("echo a", &[0]);
+    assert!(!out.is_err());
+    assert_eq!(out.exit_code(), Some(0));
+    let out = run("echo a | grep b", &[0]);
+    assert!(out.is_err());
+    assert_eq!(out.exit_code(), Some(1));
+    let out = run("echo a | grep b", &[0, 1]);
+    assert!(!out.is_err());
+    assert_eq!(out.exit_code(), Some(1));
+    let out = run("exit 2", &[0, 1]);
+    assert!(out.is_err());
+    assert_eq!(out.exit_code(), Some(2));
+    assert_eq!(Output::new(1usize).exit_code(), None);
+
+    let mut script = ScriptAction::new("sh", "echo 3\nexit 1\n");
+    script.set_output_format(OutputFormat::Int);
+    script.set_success_codes(&[1]);
+    let mut job = Dag::with_tasks(vec![DefaultTask::with_action("script", script)]);
+    assert!(job.start().unwrap());
+    assert_eq!(*job.get_result::<i64>().unwrap(), 3);
+}
+
+#[test]
+fn start_and_get_result() {
+    let mut job = Dag::with_yaml("tests/config/correct.yaml", HashMap::new()).unwrap();
+    let out = job.start_and_get::<Content>().unwrap().unwrap();
+    let (stdout, _) = out.get::<(Vec<String>, Vec<String>)>().unwrap();
+    assert_eq!(stdout.len(), 1);
+
+    let count = DefaultTask::with_closure("count", |_, _| Output::new(3usize));
+    let mut job = Dag::with_tasks(vec![count]);
+    assert_eq!(job.start_and_get::<String>().unwrap(), None);
+
+    let fail = DefaultTask::with_closure("fail", |_, _| Output::error("no".to_string()));
+    let mut job = Dag::with_tasks(vec![fail]);
+    assert_eq!(job.start_and_get::<usize>().unwrap(), None);
+
+    let mut job = Dag::with_tasks(Vec::<DefaultTask>::new());
+    assert!(matches!(
+        job.start_and_get::<usize>(),
+        Err(DagError::EmptyJob)
+    ));
+}
+
+#[test]
+fn seeded_schedule_order() {
+    let run = |seed: u64| {
+        let root = DefaultTask::with_closure("root", |_, _| Output::empty());
+        let mut tasks = vec![];
+        for name in ["a", "b", "c", "d", "e", "f", "g", "h"] {
+            let mut task = DefaultTask::with_closure(name, |_, _| Output::empty());
+            task.set_predecessors(&[&root]);
+            tasks.push(task);
+        }
+        let mut last = DefaultTask::with_closure("last", |_, _| Output::empty());
+        last.set_predecessors(&[&tasks[0]]);
+        tasks.push(root);
+        tasks.push(last);
+        let mut job = Dag::with_tasks(tasks);
+        job.set_schedule_seed(seed);
+        assert!(job.start().unwrap());
+        job.event_log()
+            .into_iter()
+            .filter(|event| event.kind == RunEventKind::Started)
+            .map(|event| event.task)
+            .collect::<Vec<_>>()
+    };
+    let first = run(7);
+    assert_eq!(first.len(), 10);
+    assert_eq!(first[0], "root");
+    let a = first.iter().position(|name| name == "a").unwrap();
+    let last = first.iter().position(|name| name == "last").unwrap();
+    assert!(a < last);
+    assert_eq!(run(7), first);
+    assert!((0..8).any(|seed| run(seed) != first));
+}
+
+#[test]
+fn streamed_command_output() {
+    let run = |flush: StreamFlush| {
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let seen = batches.clone();
+        let mut action = CommandAction::new("for i in 1 2 3 4 5 6; do echo line$i; done");
+        action.set_stream(move |lines| seen.lock().unwrap().push(lines.to_vec()));
+        action.set_stream_flush(flush);
+        let mut job = Dag::with_tasks(vec![DefaultTask::with_action("lines", action)]);
+        assert!(job.start().unwrap());
+        let content = job.get_result::<Content>().unwrap();
+        let (stdout, _) = content.get::<(Vec<String>, Vec<String>)>().unwrap();
+        assert_eq!(stdout.len(), 6);
+        let batches = batches.lock().unwrap().clone();
+        assert_eq!(batches.concat(), *stdout);
+        batches.iter().map(Vec::len).collect::<Vec<_>>()
+    };
+    assert_eq!(run(StreamFlush::Lines), vec![1; 6]);
+    assert_eq!(run(StreamFlush::Bytes(10)), vec![2, 2, 2]);
+    assert_eq!(run(StreamFlush::Bytes(1000)), vec![6]);
+    assert_eq!(run(StreamFlush::Interval(Duration::from_secs(60))), vec![6]);
+
+    // The lines of a process that goes quiet are passed on while it is quiet.
+    let flushed = Arc::new(Mutex::new(None));
+    let seen = flushed.clone();
+    let started = std::time::Instant::now();
+    let mut action = CommandAction::new("echo early; sleep 2; echo late");
+    action.set_stream(move |lines| {
+        let mut seen = seen.lock().unwrap();
+        if seen.is_none() {
+            *seen = Some((lines.to_vec(), started.elapsed()));
+        }
+    });
+    action.set_stream_flush(StreamFlush::Interval(Duration::from_millis(100)));
+    let mut job = Dag::with_tasks(vec![DefaultTask::with_action("quiet", action)]);
+    assert!(job.start().unwrap());
+    let (lines, elapsed) = flushed.lock().unwrap().clone().unwrap();
+    assert_eq!(lines, vec!["early".to_string()]);
+    assert!(elapsed < Duration::from_secs(1));
+}
+
+#[test]
+fn gate_actions() {
+    let run = |decide: fn(&Gate), timeout: Option<Duration>| {
+        let action = {
+            let mut action = GateAction::new();
+            if let Some(timeout) = timeout {
+                action.set_timeout(timeout);
+            }
+            action
+        };
+        let gate = action.gate();
+        let start = DefaultTask::with_closure("start", |_, _| Output::new(1usize));
+        let mut approval = DefaultTask::with_action("approval", action);
+        approval.set_predecessors(&[&start]);
+        let mut deploy = DefaultTask::with_closure("deploy", |input, _| {
+            Output::new(*input.get_iter().next().unwrap().get::<usize>().unwrap() + 1)
+        });
+        deploy.set_predecessors(&[&approval]);
+        // Another branch proceeds while the gate waits.
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sender = Mutex::new(sender);
+        let other = DefaultTask::with_closure("other", move |_, _| {
+            sender.lock().unwrap().send(()).unwrap();
+            Output::empty()
+        });
+        std::thread::spawn(move || {
+            receiver.recv().unwrap();
+            decide(&gate);
+        });
+        let mut job = Dag::with_tasks(vec![start, approval, deploy, other]);
+        let success = job.start().unwrap();
+        (success, job.task_state("deploy"))
+    };
+    assert_eq!(
+        run(|gate| gate.approve(), None),
+        (true, Some(TaskState::Succeeded))
+    );
+    assert_eq!(
+        run(|gate| gate.reject("not today"), None),
+        (false, Some(TaskState::Skipped))
+    );
+    assert_eq!(
+        run(|_| {}, Some(Duration::from_millis(100))),
+        (false, Some(TaskState::Skipped))
+    );
+}
+
+#[test]
+fn converted_inputs() {
+    let run = |convert: bool| {
+        let config = DefaultTask::with_action(
+            "config",
+            CommandAction::new(r#"echo '{"name": "dagrs", "port": 80}'"#),
+        );
+        let mut port = DefaultTask::with_closure("port", |input, _| {
+            let config = input.get_iter().next().unwrap();
+            let config = config.get::<serde_json::Value>().unwrap();
+            Output::new(config["port"].as_i64().unwrap())
+        });
+        port.declares_input::<serde_json::Value>();
+        port.declares_output::<i64>();
+        port.set_predecessors(&[&config]);
+        let mut address = DefaultTask::with_closure("address", |input, _| {
+            let port = input.get_iter().next().unwrap();
+            Output::new(format!("localhost:{}", port.get::<String>().unwrap()))
+        });
+        address.declares_input::<String>();
+        address.set_predecessors(&[&port]);
+        let mut job = Dag::with_tasks(vec![config, port, address]);
+        job.set_convert_inputs(convert);
+        job.start().map(|_| job.get_result::<String>().unwrap())
+    };
+    assert_eq!(*run(true).unwrap(), "localhost:80");
+    assert!(matches!(run(false), Err(DagError::TypeMismatch { .. })));
+}
+
+#[test]
+fn scheduler_stats() {
+    let mut job = Dag::with_tasks(
+        (0..4)
+            .map(|i| {
+                let mut task = DefaultTask::with_closure(&format!("task {}", i), |_, _| {
+                    std::thread::sleep(Duration::from_millis(20));
+                    Output::empty()
+                });
+                task.set_resource_request(600, 100);
+                task
+            })
+            .collect(),
+    );
+    assert!(job.scheduler_stats().samples().is_empty());
+    job.set_resource_budget(1000, 1000);
+    job.set_record_scheduler_stats(true);
+    assert!(job.start().unwrap());
+    let stats = job.scheduler_stats();
+    let samples = stats.samples();
+    assert_eq!(samples.first().unwrap().pending, 4);
+    let last = samples.last().unwrap();
+    assert_eq!((last.pending, last.ready, last.running), (0, 0, 0));
+    assert!(samples.windows(2).all(|pair| pair[0].at <= pair[1].at));
+    // The budget lets a single task run at a time, so the others wait while ready.
+    assert_eq!(stats.peak_running(), 1);
+    assert!(stats.peak_ready() >= 2);
+}
+
+#[test]
+fn main_thread_affinity() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let threads = Arc::new(Mutex::new(Vec::new()));
+    let active = Arc::new(AtomicUsize::new(0));
+    let overlapped = Arc::new(AtomicUsize::new(0));
+    let mut tasks: Vec<DefaultTask> = (0..4)
+        .map(|i| {
+            let (threads, active, overlapped) =
+                (threads.clone(), active.clone(), overlapped.clone());
+            let mut task = DefaultTask::with_closure(&format!("main {}", i), move |_, _| {
+                if active.fetch_add(1, Ordering::SeqCst) > 0 {
+                    overlapped.fetch_add(1, Ordering::SeqCst);
+                }
+                threads.lock().unwrap().push(std::thread::current().id());
+                std::thread::sleep(Duration::from_millis(10));
+                active.fetch_sub(1, Ordering::SeqCst);
+                Output::empty()
+            });
+            task.set_thread_affinity(ThreadAffinity::Main);
+            task
+        })
+        .collect();
+    let mut panicking = DefaultTask::with_closure("panicking", |_, _| panic!("thread-hostile"));
+    panicking.set_thread_affinity(ThreadAffinity::Main);
+    panicking.set_allow_failure(true);
+    tasks[0].set_predecessors(&[&panicking]);
+    let pool_thread = Arc::new(Mutex::new(None));
+    let pool = {
+        let pool_thread = pool_thread.clone();
+        DefaultTask::with_closure("pool", move |_, _| {
+            *pool_thread.lock().unwrap() = Some(std::thread::current().id());
+            Output::empty()
+        })
+    };
+    tasks.push(panicking);
+    tasks.push(pool);
+    let mut job = Dag::with_tasks(tasks);
+    assert!(job.start().unwrap());
+    assert_eq!(job.task_state("main 0"), Some(TaskState::Succeeded));
+
+    let threads = threads.lock().unwrap();
+    assert_eq!(threads.len(), 4);
+    assert!(threads.iter().all(|thread| *thread == threads[0]));
+    assert_eq!(overlapped.load(Ordering::SeqCst), 0);
+    assert_ne!(pool_thread.lock().unwrap().unwrap(), threads[0]);
+}
+
+#[test]
+fn single_threaded_start() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let build = || {
+        let a = DefaultTask::with_action("a", CommandAction::new("echo 20"));
+        let mut b = DefaultTask::with_closure("b", |input, _| {
+            let content = input.get_iter().next().unwrap().get::<Content>().unwrap();
+            let (stdout, _) = content.get::<(Vec<String>, Vec<String>)>().unwrap();
+            Output::new(stdout[0].parse::<usize>().unwrap() + 1)
+        });
+        b.set_predecessors(&[&a]);
+        let mut c = DefaultTask::with_closure("c", |input, _| {
+            Output::new(*input.get_iter().next().unwrap().get::<usize>().unwrap() * 2)
+        });
+        c.set_predecessors(&[&b]);
+        Dag::with_tasks(vec![a, b, c])
+    };
+    let mut threaded = build();
+    assert!(threaded.start().unwrap());
+    let mut single = build();
+    assert!(single.start_blocking_single_threaded().unwrap());
+    assert_eq!(*single.get_result::<usize>().unwrap(), 42);
+    assert_eq!(single.get_result::<usize>(), threaded.get_result::<usize>());
+
+    // Independent tasks run one at a time.
+    let active = Arc::new(AtomicUsize::new(0));
+    let overlapped = Arc::new(AtomicUsize::new(0));
+    let tasks = (0..3)
+        .map(|i| {
+            let (active, overlapped) = (active.clone(), overlapped.clone());
+            DefaultTask::with_closure(&i.to_string(), move |_, _| {
+                if active.fetch_add(1, Ordering::SeqCst) > 0 {
+                    overlapped.fetch_add(1, Ordering::SeqCst);
+                }
+                std::thread::sleep(Duration::from_millis(10));
+                active.fetch_sub(1, Ordering::SeqCst);
+                Output::empty()
+            })
+        })
+        .collect();
+    assert!(Dag::with_tasks(tasks)
+        .start_blocking_single_threaded()
+        .unwrap());
+    assert_eq!(overlapped.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn terminal_task_results() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::new("b".to_string()));
+    b.set_predecessors(&[&a]);
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::new(3usize));
+    c.set_predecessors(&[&a]);
+    let d = DefaultTask::with_closure("d", |_, _| Output::empty());
+    let mut job = Dag::with_tasks(vec![d, c, b, a]);
+    assert_eq!(job.terminal_tasks(), vec!["b", "c", "d"]);
+    assert!(job.get_result_of::<usize>("c").is_none());
+    assert!(job.start().unwrap());
+    assert_eq!(*job.get_result_of::<String>("b").unwrap(), "b");
+    assert_eq!(*job.get_result_of::<usize>("c").unwrap(), 3);
+    assert!(job.get_result_of::<usize>("b").is_none());
+    assert!(job.get_result_of::<usize>("d").is_none());
+    assert!(job.get_result_of::<usize>("e").is_none());
+}
+
+#[test]
+fn task_resource_usage() {
+    let busy = DefaultTask::with_action(
+        "busy",
+        CommandAction::new("i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done"),
+    );
+    let closure = DefaultTask::with_closure("closure", |_, _| Output::empty());
+    let mut job = Dag::with_tasks(vec![busy, closure]);
+    assert!(job.start().unwrap());
+    assert!(job.get_task_resources("closure").is_none());
+    let usage = job.get_task_resources("busy");
+    if cfg!(unix) {
+        let usage = usage.unwrap();
+        assert!(usage.cpu_time > Duration::ZERO);
+        assert!(usage.max_rss > 0);
+    }
+    let report = job.run_report();
+    let busy = report
+        .tasks
+        .iter()
+        .find(|task| task.name == "busy")
+        .unwrap();
+    assert_eq!(busy.resources, usage);
+}
+
+#[test]
+fn run_progress() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::empty());
+    let (release, released) = std::sync::mpsc::channel::<()>();
+    let released = Mutex::new(released);
+    let mut b = DefaultTask::with_closure("b", move |_, _| {
+        released.lock().unwrap().recv().unwrap();
+        Output::empty()
+    });
+    b.set_predecessors(&[&a]);
+    let mut job = Dag::with_tasks(vec![a, b]);
+    assert_eq!(job.progress(), 0.0);
+    let progress = job.progress_handle();
+    let poller = std::thread::spawn(move || {
+        while progress.done() < 1 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let halfway = (progress.fraction(), progress.total());
+        release.send(()).unwrap();
+        halfway
+    });
+    assert!(job.start().unwrap());
+    assert_eq!(poller.join().unwrap(), (0.5, 2));
+    assert_eq!(job.progress(), 1.0);
+
+    // Failed and skipped tasks are done too.
+    let a = DefaultTask::with_closure("a", |_, _| Output::error("a".to_string()));
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::empty());
+    b.set_predecessors(&[&a]);
+    let mut job = Dag::with_tasks(vec![a, b]);
+    assert!(!job.start().unwrap());
+    assert_eq!(job.progress(), 1.0);
+}
+
+#[test]
+fn timed_out_attempts_do_not_overlap() {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    for single_threaded in [false, true] {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(AtomicUsize::new(0));
+        let overlapped = Arc::new(AtomicBool::new(false));
+        let (count, now_running, overlap) = (attempts.clone(), running.clone(), overlapped.clone());
+        let mut task = DefaultTask::with_closure("flaky", move |_, _| {
+            if now_running.fetch_add(1, Ordering::SeqCst) > 0 {
+                overlap.store(true, Ordering::SeqCst);
+            }
+            // The first attempt times out, the second one does not.
+            if count.fetch_add(1, Ordering::SeqCst) == 0 {
+                std::thread::sleep(Duration::from_millis(300));
+            }
+            now_running.fetch_sub(1, Ordering::SeqCst);
+            Output::empty()
+        });
+        task.set_timeout(Duration::from_millis(200));
+        task.set_retry(RetryPolicy::new(1));
+        let mut job = Dag::with_tasks(vec![task]);
+        let succeeded = match single_threaded {
+            true => job.start_blocking_single_threaded().unwrap(),
+            false => job.start().unwrap(),
+        };
+        assert!(succeeded);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert!(!overlapped.load(Ordering::SeqCst));
+    }
+
+    // An attempt still running after twice the timeout is not retried.
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let count = attempts.clone();
+    let mut task = DefaultTask::with_closure("stuck", move |_, _| {
+        count.fetch_add(1, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_secs(2));
+        Output::empty()
+    });
+    task.set_timeout(Duration::from_millis(100));
+    task.set_retry(RetryPolicy::new(1));
+    let mut job = Dag::with_tasks(vec![task]);
+    let start = std::time::Instant::now();
+    assert!(!job.start().unwrap());
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn default_timeouts_and_retries() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let sleeping = |name: &str, millis: u64| {
+        DefaultTask::with_closure(name, move |_, _| {
+            std::thread::sleep(Duration::from_millis(millis));
+            Output::empty()
+        })
+    };
+    // The timeout of a task takes precedence over the default one.
+    let mut patient = sleeping("patient", 100);
+    patient.set_timeout(Duration::from_secs(10));
+    let mut job = Dag::with_tasks(vec![patient, sleeping("hasty", 2000)]);
+    job.set_default_timeout(Duration::from_millis(50));
+    assert!(!job.start().unwrap());
+    assert_eq!(job.task_state("patient"), Some(TaskState::Succeeded));
+    let report = job.run_report();
+    let hasty = report
+        .tasks
+        .iter()
+        .find(|task| task.name == "hasty")
+        .unwrap();
+    assert!(hasty.error.as_ref().unwrap().contains("timed out"));
+
+    // So does the retry policy of a task.
+    let runs = Arc::new(AtomicUsize::new(0));
+    let failing = |name: &str, runs: Arc<AtomicUsize>| {
+        DefaultTask::with_closure(name, move |_, _| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Output::error("failed".to_string())
+        })
+    };
+    let mut once = failing("once", runs.clone());
+    once.set_retry(RetryPolicy::new(1));
+    let mut job = Dag::with_tasks(vec![once, failing("default", runs.clone())]).keep_going();
+    job.set_default_retry(RetryPolicy::new(3));
+    assert!(!job.start().unwrap());
+    assert_eq!(runs.load(Ordering::SeqCst), 2 + 4);
+
+    // The tasks parsed from configuration files use the defaults.
+    let yaml = "dagrs:\n  a:\n    name: a\n    cmd: sleep 2\n";
+    let mut job = Dag::with_yaml_str(yaml, HashMap::new()).unwrap();
+    job.set_default_timeout(Duration::from_millis(50));
+    assert!(!job.start().unwrap());
+    assert_eq!(job.task_state("a"), Some(TaskState::Failed));
+}
+
+#[test]
+fn ready_tasks() {
+    let (release, released) = std::sync::mpsc::channel::<()>();
+    let released = Arc::new(Mutex::new(released));
+    let tasks = ["a", "b"]
+        .into_iter()
+        .map(|name| {
+            let released = released.clone();
+            let mut task = DefaultTask::with_closure(name, move |_, _| {
+                released.lock().unwrap().recv().unwrap();
+                Output::empty()
+            });
+            task.set_resource_request(600, 100);
+            task
+        })
+        .collect();
+    let mut job = Dag::with_tasks(tasks);
+    job.set_resource_budget(1000, 1000);
+    let (ready, running) = (job.ready_tasks_handle(), job.running_tasks_handle());
+    let poller = std::thread::spawn(move || loop {
+        let (waiting, started) = (ready.snapshot(), running.snapshot());
+        if waiting.len() == 1 && started.len() == 1 {
+            release.send(()).unwrap();
+            release.send(()).unwrap();
+            return (waiting, started);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    });
+    assert!(job.start().unwrap());
+    let (waiting, started) = poller.join().unwrap();
+    assert_ne!(waiting, started);
+    assert!(job.ready_tasks().is_empty());
+}
+
+#[test]
+fn step_execution() {
+    let mut a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    a.set_weight(2.0);
+    let b = DefaultTask::with_closure("b", |_, _| Output::error("b failed".to_string()));
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::empty());
+    c.set_predecessors(&[&a, &b]);
+    let completed = Arc::new(Mutex::new(0));
+    let mut job = Dag::with_tasks(vec![a, b, c]);
+    let counter = completed.clone();
+    job.on_complete(move |_| *counter.lock().unwrap() += 1);
+
+    let mut steps = Vec::new();
+    while let Some(step) = job.step().unwrap() {
+        assert_eq!(job.task_state(&step.name), Some(step.state));
+        steps.push((step.name, step.state, step.error));
+    }
+    assert_eq!(
+        steps,
+        vec![
+            ("a".to_string(), TaskState::Succeeded, None),
+            (
+                "b".to_string(),
+                TaskState::Failed,
+                Some("b failed".to_string())
+            ),
+            ("c".to_string(), TaskState::Skipped, None),
+        ]
+    );
+    assert!(job.step().unwrap().is_none());
+    assert_eq!(*completed.lock().unwrap(), 1);
+}
+
+#[test]
+fn step_order_follows_creation() {
+    // The ties between ready tasks of the same weight are broken by the order the tasks were
+    // created in, whatever the order of the tasks in the hash maps of the dag.
+    let names: Vec<String> = (0..20).map(|i| format!("t{}", i)).collect();
+    for _ in 0..5 {
+        let tasks = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| DefaultTask::with_closure(name, move |_, _| Output::new(i)))
+            .collect();
+        let mut job = Dag::with_tasks(tasks);
+        let mut steps = Vec::new();
+        while let Some(step) = job.step().unwrap() {
+            steps.push(step.name);
+        }
+        assert_eq!(steps, names);
+        assert_eq!(*job.get_result::<usize>().unwrap(), 19);
+    }
+}
+
+#[test]
+fn step_mixed_with_start() {
+    let runs = Arc::new(Mutex::new(Vec::new()));
+    let log = runs.clone();
+    let a = DefaultTask::with_closure("a", move |_, _| {
+        log.lock().unwrap().push("a");
+        Output::empty()
+    });
+    let log = runs.clone();
+    let mut b = DefaultTask::with_closure("b", move |_, _| {
+        log.lock().unwrap().push("b");
+        Output::empty()
+    });
+    b.set_predecessors(&[&a]);
+    let mut stepped = Dag::with_tasks(vec![a, b]);
+    let mut job = stepped.clone_structure();
+
+    // A stepped run ended by `start`, after which the dag cannot run anymore.
+    assert_eq!(job.step().unwrap().unwrap().name, "a");
+    assert!(job.start().unwrap());
+    assert!(job.step().unwrap().is_none());
+    assert!(!job.start().unwrap());
+    assert_eq!(*runs.lock().unwrap(), vec!["a", "a", "b"]);
+
+    // A complete stepped run followed by `start`, which runs all the tasks again.
+    runs.lock().unwrap().clear();
+    while stepped.step().unwrap().is_some() {}
+    assert!(stepped.start().unwrap());
+    assert!(stepped.step().unwrap().is_none());
+    assert_eq!(*runs.lock().unwrap(), vec!["a", "b", "a", "b"]);
+}
+
+#[test]
+fn lazy_outputs() {
+    let computed = Arc::new(Mutex::new(0));
+    let lazy = |computed: &Arc<Mutex<usize>>| {
+        let computed = computed.clone();
+        DefaultTask::with_closure("produce", move |_, _| {
+            let computed = computed.clone();
+            Output::lazy(move || {
+                *computed.lock().unwrap() += 1;
+                7usize
+            })
+        })
+    };
+    let read = |name: &str| {
+        DefaultTask::with_closure(name, |input: Input, _| {
+            Output::new(*input.get_iter().next().unwrap().get::<usize>().unwrap() * 2)
+        })
+    };
+
+    // The only successor is disabled, so nothing reads the output.
+    let produce = lazy(&computed);
+    let mut skipped = read("skipped");
+    skipped.set_predecessors(&[&produce]);
+    skipped.set_enabled(false);
+    let mut job = Dag::with_tasks(vec![produce, skipped]);
+    assert!(job.start().unwrap());
+    assert_eq!(*computed.lock().unwrap(), 0);
+
+    // Both successors read the output, which is computed once and shared.
+    let produce = lazy(&computed);
+    let mut first = read("first");
+    let mut second = read("second");
+    first.set_predecessors(&[&produce]);
+    second.set_predecessors(&[&produce]);
+    let mut job = Dag::with_tasks(vec![produce, first, second]);
+    assert!(job.start().unwrap());
+    assert_eq!(*computed.lock().unwrap(), 1);
+    assert_eq!(job.get_result_of::<usize>("first"), Some(Arc::new(14)));
+    assert_eq!(job.get_result_of::<usize>("second"), Some(Arc::new(14)));
+}
+
+#[test]
+fn missing_interpreters() {
+    let env = Arc::new(EnvVar::new());
+    let missing = RunningError::InterpreterNotFound("dagrs-missing".to_string());
+    let run = |action: Arc<dyn Complex + Send + Sync>| {
+        DefaultTask::with_action_dyn("missing", action)
+            .run_isolated(Input::new(Vec::new()), env.clone())
+    };
+
+    let mut direct = CommandAction::new("dagrs-missing --version");
+    direct.set_shell(false);
+    let out = run(Arc::new(direct));
+    assert_eq!(out.running_error(), Some(&missing));
+
+    let out = run(Arc::new(CommandAction::new("dagrs-missing --version")));
+    assert_eq!(out.running_error(), Some(&missing));
+    assert_eq!(out.exit_code(), Some(127));
+
+    let out = run(Arc::new(ScriptAction::new("dagrs-missing", "print(1)\n")));
+    assert_eq!(out.running_error(), Some(&missing));
+
+    let out = run(Arc::new(CommandAction::new("exit 127")));
+    assert!(out.is_err());
+    assert_eq!(out.running_error(), None);
+
+    let mut job = Dag::with_tasks(vec![DefaultTask::with_action(
+        "missing",
+        CommandAction::new("dagrs-missing"),
+    )]);
+    assert!(!job.start().unwrap());
+    assert_eq!(
+        job.run_report().task("missing").unwrap().error,
+        Some(missing.to_string())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn nice_commands() {
+    let env = Arc::new(EnvVar::new());
+    let nice = |action: Arc<dyn Complex + Send + Sync>| {
+        let out = DefaultTask::with_action_dyn("nice", action)
+            .run_isolated(Input::new(Vec::new()), env.clone());
+        match out {
+            Output::OutWithExitCode(_, Some(content)) => content.get::<i64>().copied(),
+            _ => None,
+        }
+    };
+    let base = {
+        let mut action = CommandAction::new("nice");
+        action.set_output_format(OutputFormat::Int);
+        nice(Arc::new(action)).unwrap()
+    };
+
+    let mut action = CommandAction::new("nice");
+    action.set_output_format(OutputFormat::Int);
+    action.set_nice(19);
+    assert_eq!(nice(Arc::new(action)), Some(19));
+
+    let mut script = ScriptAction::new("sh", "nice\n");
+    script.set_output_format(OutputFormat::Int);
+    script.set_nice(base.max(10) as i32);
+    assert_eq!(nice(Arc::new(script)), Some(base.max(10)));
+}
+
+#[test]
+fn file_outputs() {
+    let dir = tempfile::tempdir().unwrap();
+    let (owned, kept) = (dir.path().join("owned.txt"), dir.path().join("kept.txt"));
+    let write = |name: &str, path: &std::path::Path, temp: bool| {
+        let path = path.to_path_buf();
+        DefaultTask::with_closure(name, move |_, _| {
+            std::fs::write(&path, "line\n".repeat(3)).unwrap();
+            if temp {
+                Output::temp_file(&path)
+            } else {
+                Output::file(&path)
+            }
+        })
+    };
+    let a = write("a", &owned, true);
+    let b = write("b", &kept, false);
+    let mut count = DefaultTask::with_closure("count", |input: Input, _| {
+        let lines = input
+            .paths()
+            .map(|path| std::fs::read_to_string(path).unwrap().lines().count())
+            .sum::<usize>();
+        Output::new(lines)
+    });
+    count.set_predecessors(&[&a, &b]);
+    // The shell gives the first argument to the command line as $0.
+    let mut cat = DefaultTask::with_action("cat", CommandAction::new("cat \"$0\""));
+    cat.set_predecessors(&[&a]);
+    let mut job = Dag::with_tasks(vec![a, b, count, cat]);
+    job.set_eager_output_drop(true);
+    assert!(job.start().unwrap());
+
+    assert_eq!(job.get_result_of::<usize>("count"), Some(Arc::new(6)));
+    let lines = job.get_result_of::<Content>("cat").unwrap();
+    let (stdout, _) = lines.get::<(Vec<String>, Vec<String>)>().unwrap();
+    assert_eq!(stdout.len(), 3);
+    assert!(!owned.exists());
+    drop(job);
+    assert!(kept.exists());
+}
+
+#[test]
+fn explicit_result_task() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::new(2usize));
+    b.set_predecessors(&[&a]);
+    let c = DefaultTask::with_closure("c", |_, _| Output::new(3usize));
+    let mut job = Dag::with_tasks(vec![a, b, c]);
+    job.set_result_task("a");
+    job.set_eager_output_drop(true);
+    assert_eq!(job.start_and_get::<usize>().unwrap(), Some(1));
+
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut job = Dag::with_tasks(vec![a]);
+    job.set_result_task("missing");
+    assert!(matches!(job.start(), Err(DagError::TaskNotFound(name)) if name == "missing"));
+}
+
+#[test]
+fn import_subgraphs() {
+    let value = |name: &str, value: usize| {
+        DefaultTask::with_closure(name, move |input: Input, _| {
+            let sum: usize = input.get_iter().map(|c| *c.get::<usize>().unwrap()).sum();
+            Output::new(sum + value)
+        })
+    };
+    let fetch = value("fetch", 1);
+    let mut build = value("build", 10);
+    build.set_predecessors(&[&fetch]);
+    let mut test = value("test", 100);
+    test.set_predecessors(&[&build]);
+    let lint = value("lint", 1000);
+    let source = Dag::with_tasks(vec![fetch, build, test, lint]);
+
+    // A fetch task is already there.
+    let fetch = || value("fetch", 2);
+    let mut job = Dag::with_tasks(vec![fetch()]);
+    assert!(matches!(
+        job.import_subgraph(&source, "test", &NameCollision::Fail),
+        Err(DagError::DuplicateTask(name)) if name == "fetch"
+    ));
+    assert!(matches!(
+        job.import_subgraph(&source, "deploy", &NameCollision::Fail),
+        Err(DagError::TaskNotFound(_))
+    ));
+    job.import_subgraph(&source, "test", &NameCollision::Reuse)
+        .unwrap();
+    assert_eq!(job.levels().unwrap().concat().len(), 3);
+    assert_eq!(job.start_and_get::<usize>().unwrap(), Some(112));
+
+    let mut job = Dag::with_tasks(vec![fetch()]);
+    job.import_subgraph(
+        &source,
+        "test",
+        &NameCollision::Rename("-imported".to_string()),
+    )
+    .unwrap();
+    let mut names = job.levels().unwrap().concat();
+    names.sort();
+    assert_eq!(names, vec!["build", "fetch", "fetch-imported", "test"]);
+    job.set_result_task("test");
+    assert_eq!(job.start_and_get::<usize>().unwrap(), Some(111));
+}
+
+#[test]
+fn saturation_callback() {
+    let run = |request: u32| {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let tasks = ["a", "b", "c"].map(|name| {
+            let mut task = DefaultTask::with_closure(name, |_, _| {
+                std::thread::sleep(Duration::from_millis(50));
+                Output::empty()
+            });
+            task.set_resource_request(request, 0);
+            task
+        });
+        let mut job = Dag::with_tasks(tasks.into());
+        job.set_resource_budget(1000, 1000);
+        let seen = calls.clone();
+        job.set_saturation_callback(move |saturated| seen.lock().unwrap().push(saturated));
+        assert!(job.start().unwrap());
+        let calls = calls.lock().unwrap().clone();
+        calls
+    };
+    assert_eq!(run(600), vec![true, false]);
+    assert!(run(300).is_empty());
+}
+
+#[test]
+fn capture_buffer_sizes() {
+    let env = Arc::new(EnvVar::new());
+    let lines =
+        |action: Arc<dyn Complex + Send + Sync>| match DefaultTask::with_action_dyn("seq", action)
+            .run_isolated(Input::new(Vec::new()), env.clone())
+        {
+            Output::OutWithExitCode(0, Some(content)) => content
+                .get::<Content>()
+                .unwrap()
+                .get::<(Vec<String>, Vec<String>)>()
+                .unwrap()
+                .clone(),
+            _ => panic!("the command failed"),
+        };
+    let cmd = "seq 1 5000; echo done >&2";
+    let expected = lines(Arc::new(CommandAction::new(cmd)));
+    assert_eq!(expected.0.len(), 5000);
+    for size in [0, 7, 1 << 20] {
+        let mut action = CommandAction::new(cmd);
+        action.set_capture_buffer(size);
+        assert_eq!(lines(Arc::new(action)), expected);
+
+        let streamed = Arc::new(Mutex::new(0));
+        let count = streamed.clone();
+        let mut action = CommandAction::new(cmd);
+        action.set_capture_buffer(size);
+        action.set_stream(move |lines| *count.lock().unwrap() += lines.len());
+        assert_eq!(lines(Arc::new(action)), expected);
+        assert_eq!(*streamed.lock().unwrap(), 5000);
+
+        let mut script = ScriptAction::new("sh", cmd);
+        script.set_capture_buffer(size);
+        assert_eq!(lines(Arc::new(script)), expected);
+    }
+}
+
+#[test]
+fn success_checks() {
+    let attempts = Arc::new(Mutex::new(0usize));
+    let count = attempts.clone();
+    let mut task = DefaultTask::with_closure("count", move |_, _| {
+        let mut attempts = count.lock().unwrap();
+        *attempts += 1;
+        Output::new(*attempts)
+    });
+    task.set_success_check(
+        |out| matches!(out, Output::Out(Some(c)) if c.get::<usize>() == Some(&3)),
+    );
+    let mut job = Dag::with_tasks(vec![task.clone()]);
+    assert!(!job.start().unwrap());
+    let report = job.run_report();
+    assert_eq!(report.task("count").unwrap().state, TaskState::Failed);
+    assert!(report
+        .task("count")
+        .unwrap()
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("success check"));
+
+    *attempts.lock().unwrap() = 0;
+    task.set_retry(RetryPolicy::new(3));
+    let mut job = Dag::with_tasks(vec![task]);
+    assert!(job.start().unwrap());
+    assert_eq!(*attempts.lock().unwrap(), 3);
+    assert_eq!(job.get_result::<usize>(), Some(Arc::new(3)));
+}
+
+#[test]
+fn topology_fingerprints() {
+    let build = |reversed: bool, extra_edge: bool| {
+        let a = DefaultTask::with_closure("a", |_, _| Output::empty());
+        let mut b = DefaultTask::with_closure("b", |_, _| Output::empty());
+        let mut c = DefaultTask::with_closure("c", |_, _| Output::empty());
+        b.set_predecessors(&[&a]);
+        if extra_edge {
+            c.set_predecessors(&[&a, &b]);
+        } else {
+            c.set_predecessors(&[&b]);
+        }
+        let mut tasks = vec![a, b, c];
+        if reversed {
+            tasks.reverse();
+        }
+        Dag::with_tasks(tasks)
+    };
+    let dag = build(false, false);
+    let expected = Topology::new(["a", "b", "c"], [("b", "c"), ("a", "b")]);
+    assert_eq!(dag.topology(), expected);
+    assert_eq!(dag.topology_fingerprint(), "b8c4ac3759bf17a1");
+    assert_eq!(
+        build(true, false).topology_fingerprint(),
+        dag.topology_fingerprint()
+    );
+
+    let changed = build(true, true);
+    assert_ne!(changed.topology_fingerprint(), dag.topology_fingerprint());
+    let diff = expected.diff(&changed.topology());
+    assert_eq!(diff.added_edges, vec![("a".to_string(), "c".to_string())]);
+    assert!(diff.removed_edges.is_empty() && diff.added_tasks.is_empty());
+    assert_eq!(diff.to_string(), "+ edge a -> c\n");
+}
+
+#[test]
+fn gantt_bars() {
+    let sleep = |name: &str| {
+        DefaultTask::with_closure(name, |_, _| {
+            std::thread::sleep(Duration::from_millis(50));
+            Output::empty()
+        })
+    };
+    let (a, b) = (sleep("a"), sleep("b"));
+    let mut c = sleep("c");
+    c.set_predecessors(&[&a, &b]);
+    let mut job = Dag::with_tasks(vec![a, b, c]);
+    assert!(job.gantt_data().is_empty());
+    assert!(job.start().unwrap());
+    let bars = job.gantt_data();
+    let names: Vec<&str> = bars.iter().map(|bar| bar.task.as_str()).collect();
+    assert_eq!(names[2], "c");
+    let mut lanes: Vec<usize> = bars.iter().map(|bar| bar.lane).collect();
+    lanes[..2].sort();
+    assert_eq!(lanes, vec![0, 1, 0]);
+    assert!(bars[2].start >= bars[0].start + bars[0].duration);
+    assert!(bars
+        .iter()
+        .all(|bar| bar.duration >= Duration::from_millis(50) && bar.state == TaskState::Succeeded));
+
+    let fail = DefaultTask::with_closure("fail", |_, _| Output::error("no".to_string()));
+    let mut next = sleep("next");
+    next.set_predecessors(&[&fail]);
+    let mut job = Dag::with_tasks(vec![fail, next]);
+    assert!(!job.start().unwrap());
+    let skipped: Vec<GanttBar> = job
+        .gantt_data()
+        .into_iter()
+        .filter(|bar| bar.state == TaskState::Skipped)
+        .collect();
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(
+        (skipped[0].task.as_str(), skipped[0].duration),
+        ("next", Duration::ZERO)
+    );
+}
+
+#[test]
+fn seeded_inputs() {
+    let build = |a_runs: Arc<Mutex<usize>>| {
+        let a = DefaultTask::with_closure("a", move |_, _| {
+            *a_runs.lock().unwrap() += 1;
+            Output::new(1usize)
+        });
+        let add = |input: Input, n: usize| {
+            let sum: usize = input.get_iter().map(|c| *c.get::<usize>().unwrap()).sum();
+            Output::new(sum + n)
+        };
+        let mut b = DefaultTask::with_closure("b", move |input, _| add(input, 10));
+        let mut c = DefaultTask::with_closure("c", move |input, _| add(input, 100));
+        b.set_predecessors(&[&a]);
+        c.set_predecessors(&[&b]);
+        Dag::with_tasks(vec![a, b, c])
+    };
+    let seed = || Input::new(vec![Content::new(1000usize), Content::new(2000usize)]);
+
+    let a_runs = Arc::new(Mutex::new(0));
+    let mut job = build(a_runs.clone());
+    job.seed_input("b", seed()).unwrap();
+    assert!(job.start().unwrap());
+    assert_eq!(*a_runs.lock().unwrap(), 1);
+    assert_eq!(job.get_result::<usize>(), Some(Arc::new(3110)));
+
+    let a_runs = Arc::new(Mutex::new(0));
+    let mut job = build(a_runs.clone());
+    job.seed_input("b", seed()).unwrap();
+    assert!(job.rerun_from("b").unwrap());
+    assert_eq!(*a_runs.lock().unwrap(), 0);
+    assert_eq!(job.task_state("a"), Some(TaskState::Pending));
+    assert_eq!(job.get_result::<usize>(), Some(Arc::new(3110)));
+
+    let mut job = build(a_runs.clone());
+    job.rerun_from("c").unwrap();
+    assert_eq!(job.task_state("c"), Some(TaskState::Skipped));
+    assert!(matches!(
+        job.seed_input("d", seed()),
+        Err(DagError::TaskNotFound(_))
+    ));
+}
+
+#[test]
+fn type_drift_detection() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = dir.path().join("types.json");
+    let run = |a: fn() -> Output, b: fn() -> Output| {
+        let mut job = Dag::with_tasks(vec![
+            DefaultTask::with_closure("a", move |_, _| a()),
+            DefaultTask::with_closure("b", move |_, _| b()),
+        ]);
+        job.set_type_drift_detection(&store);
+        assert!(job.start().unwrap());
+        job.type_changes()
+    };
+    assert!(run(|| Output::new(1usize), || Output::new(true)).is_empty());
+    assert!(store.is_file());
+    assert!(run(Output::empty, || Output::new(false)).is_empty());
+
+    let changes = run(|| Output::new("1".to_string()), || Output::new(true));
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        (changes[0].task.as_str(), changes[0].before.as_str()),
+        ("a", "usize")
+    );
+    assert!(changes[0].after.ends_with("String"));
+
+    let mut job = Dag::with_tasks(vec![DefaultTask::with_closure("a", |_, _| {
+        Output::new(1usize)
+    })]);
+    assert!(job.start().unwrap());
+    assert!(job.type_changes().is_empty());
+}
+
+#[test]
+fn finalizers_run_on_cancel() {
+    let build = |log: Arc<Mutex<Vec<(&'static str, usize)>>>| {
+        let slow = DefaultTask::with_closure("slow", |_, _| {
+            std::thread::sleep(Duration::from_millis(500));
+            Output::new(1usize)
+        });
+        let finalizer = |name: &'static str| {
+            let log = log.clone();
+            let mut task = DefaultTask::with_closure(name, move |input, _| {
+                let mut log = log.lock().unwrap();
+                log.push((name, input.get_iter().count()));
+                // The first attempt of `report` fails, and is retried.
+                match (name, log.iter().filter(|(run, _)| *run == name).count()) {
+                    ("report", 1) => Output::error("flaky".to_string()),
+                    _ => Output::empty(),
+                }
+            });
+            task.set_run_on_cancel(true);
+            task.set_retry(RetryPolicy::new(1));
+            task
+        };
+        let mut unlock = finalizer("unlock");
+        let mut cleanup = finalizer("cleanup");
+        unlock.set_predecessors(&[&slow]);
+        cleanup.set_predecessors(&[&unlock]);
+        let broken = DefaultTask::with_closure("broken", |_, _| Output::error("no".to_string()));
+        let mut report = finalizer("report");
+        report.set_predecessors(&[&broken]);
+        Dag::with_tasks(vec![cleanup, unlock, slow, report, broken]).keep_going()
+    };
+
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut job = build(log.clone());
+    assert!(!job.start().unwrap());
+    let mut runs = log.lock().unwrap().clone();
+    runs.sort();
+    assert_eq!(
+        runs,
+        vec![("cleanup", 0), ("report", 0), ("report", 0), ("unlock", 1)]
+    );
+
+    // The finalizers depending on the task that is still running when the run times out are
+    // skipped.
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut job = build(log.clone());
+    job.set_timeout(Duration::from_millis(100));
+    assert!(matches!(job.start(), Err(DagError::Timeout)));
+    assert_eq!(*log.lock().unwrap(), vec![("report", 0), ("report", 0)]);
+    assert_eq!(job.task_state("report"), Some(TaskState::Succeeded));
+    assert_eq!(job.task_state("unlock"), Some(TaskState::Pending));
+}
+
+#[test]
+fn unconsumed_output_policies() {
+    let run = |policy: UnconsumedOutput| {
+        let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+        let mut b = DefaultTask::with_closure("b", |input, _| {
+            Output::new(*input.get_iter().next().unwrap().get::<usize>().unwrap() + 1)
+        });
+        b.set_predecessors(&[&a]);
+        let orphan = DefaultTask::with_closure("orphan", |_, _| Output::new(3usize));
+        let mut stop = DefaultTask::with_closure("stop", |_, _| Output::finish(4usize));
+        stop.set_predecessors(&[&b]);
+        let mut job = Dag::with_tasks(vec![a, b, orphan, stop]);
+        job.set_result_task("b");
+        job.set_unconsumed_output_policy(policy);
+        assert!(job.start().unwrap());
+        ["a", "b", "orphan", "stop"].map(|name| job.get_result_of::<usize>(name).map(|v| *v))
+    };
+    assert_eq!(
+        run(UnconsumedOutput::Keep),
+        [Some(1), Some(2), Some(3), Some(4)]
+    );
+    for policy in [UnconsumedOutput::Discard, UnconsumedOutput::Log] {
+        assert_eq!(run(policy), [Some(1), Some(2), None, Some(4)]);
+    }
+}
@@ -0,0 +1,188 @@
+//! Tests of the structural queries and checks of a dag, which do not depend on the outputs of
+//! the tasks.
+
+use dagrs::{Dag, DagError, DefaultTask, Output, Task};
+
+fn task(name: &str) -> DefaultTask {
+    DefaultTask::with_closure(name, |_, _| Output::empty())
+}
+
+#[test]
+fn levels_and_width() {
+    let a = task("a");
+    let mut b = task("b");
+    let mut c = task("c");
+    let mut d = task("d");
+    // A component that is not connected to the others.
+    let x = task("x");
+    let mut y = task("y");
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&a]);
+    d.set_predecessors(&[&b, &c]);
+    y.set_predecessors(&[&x]);
+
+    let dag = Dag::with_tasks(vec![a, b, c, d, x, y]);
+    assert_eq!(
+        dag.levels().unwrap(),
+        vec![
+            vec!["a".to_string(), "x".to_string()],
+            vec!["b".to_string(), "c".to_string(), "y".to_string()],
+            vec!["d".to_string()],
+        ]
+    );
+    assert_eq!(dag.max_parallel_width().unwrap(), 3);
+}
+
+#[test]
+fn levels_of_loop_graph() {
+    let mut a = task("a");
+    let mut b = task("b");
+    a.set_predecessors(&[&b]);
+    b.set_predecessors(&[&a]);
+
+    let dag = Dag::with_tasks(vec![a, b]);
+    assert!(matches!(dag.levels(), Err(DagError::LoopGraph)));
+    assert!(matches!(dag.max_parallel_width(), Err(DagError::LoopGraph)));
+}
+
+#[test]
+fn weights() {
+    let a = task("a");
+    let mut b = task("b");
+    let mut c = task("c");
+    let mut d = task("d");
+    b.set_predecessors(&[&a]);
+    b.set_weight(5.0);
+    c.set_predecessors(&[&a]);
+    c.set_weight(2.5);
+    d.set_predecessors(&[&b, &c]);
+
+    let dag = Dag::with_tasks(vec![a, b, c, d]);
+    assert_eq!(dag.total_weight(), 9.5);
+    assert_eq!(dag.critical_path_weight().unwrap(), 7.0);
+    assert_eq!(dag.level_weights().unwrap(), vec![1.0, 7.5, 1.0]);
+}
+
+#[test]
+fn redundant_edges() {
+    let a = task("a");
+    let mut b = task("b");
+    let mut c = task("c");
+    let mut d = task("d");
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&b, &a]);
+    d.set_predecessors(&[&c, &a, &b]);
+
+    let dag = Dag::with_tasks(vec![a, b, c, d]);
+    assert_eq!(
+        dag.redundant_edges().unwrap(),
+        vec![
+            ("a".to_string(), "c".to_string()),
+            ("a".to_string(), "d".to_string()),
+            ("b".to_string(), "d".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn redundant_edges_of_long_chain() {
+    // A chain with a shortcut from the first task to every other task.
+    let first = task("0");
+    let mut tasks = vec![first];
+    for i in 1..200 {
+        let mut t = task(&i.to_string());
+        t.set_predecessors(&[&tasks[i - 1]]);
+        if i > 1 {
+            t.set_predecessors(&[&tasks[0]]);
+        }
+        tasks.push(t);
+    }
+    let dag = Dag::with_tasks(tasks);
+    assert_eq!(dag.redundant_edges().unwrap().len(), 198);
+}
+
+#[test]
+fn require_connected() {
+    let a = task("a");
+    let mut b = task("b");
+    let mut c = task("c");
+    let x = task("x");
+    let mut y = task("y");
+    let lonely = task("lonely");
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&a]);
+    y.set_predecessors(&[&x]);
+
+    let mut dag = Dag::with_tasks(vec![a, b, c, x, y, lonely]);
+    dag.set_require_connected(true);
+    match dag.start() {
+        Err(DagError::Disconnected(names)) => assert_eq!(names, vec!["lonely", "x", "y"]),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn connected_with_several_roots() {
+    let a = task("a");
+    let b = task("b");
+    let mut c = task("c");
+    let mut d = task("d");
+    c.set_predecessors(&[&a, &b]);
+    d.set_predecessors(&[&b]);
+
+    let mut dag = Dag::with_tasks(vec![a, b, c, d]);
+    dag.set_require_connected(true);
+    assert!(dag.start().unwrap());
+}
+
+#[test]
+fn max_depth() {
+    let chain = || {
+        let a = task("a");
+        let mut b = task("b");
+        let mut c = task("c");
+        let mut d = task("d");
+        let mut e = task("e");
+        b.set_predecessors(&[&a]);
+        c.set_predecessors(&[&b]);
+        d.set_predecessors(&[&a, &c]);
+        e.set_predecessors(&[&a]);
+        Dag::with_tasks(vec![a, b, c, d, e])
+    };
+
+    let mut dag = chain();
+    dag.set_max_depth(4);
+    assert!(dag.start().unwrap());
+
+    let mut dag = chain();
+    dag.set_max_depth(3);
+    match dag.start() {
+        Err(DagError::TooDeep { max, path }) => {
+            assert_eq!(max, 3);
+            assert_eq!(path, vec!["a", "b", "c", "d"]);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn mermaid_styles_states_after_run() {
+    let a = task("a");
+    let mut b = DefaultTask::with_closure("b <fails>", |_, _| Output::error("no".to_string()));
+    let mut c = task("c");
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&b]);
+    let (a_id, b_id, c_id) = (a.id(), b.id(), c.id());
+
+    let mut dag = Dag::with_tasks(vec![a, b, c]);
+    let before = dag.to_mermaid();
+    assert!(before.contains(&format!("t{}[\"b #lt;fails#gt;\"]", b_id)));
+    assert!(before.contains(&format!("t{} --> t{}", b_id, c_id)));
+    assert!(!before.contains("classDef"));
+
+    assert!(!dag.start().unwrap());
+    let after = dag.to_mermaid();
+    assert!(after.contains(&format!("class t{} succeeded", a_id)));
+    assert!(after.contains(&format!("class t{} failed", b_id)));
+    assert!(after.contains(&format!("class t{} skipped", c_id)));
+}
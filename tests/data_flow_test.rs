@@ -0,0 +1,53 @@
+//! Warnings about the outputs that are empty or not read by the successors.
+
+use std::sync::Mutex;
+
+use dagrs::{Dag, DefaultTask, Output};
+use log::{Log, Metadata, Record};
+
+/// A logger that keeps every record in memory.
+struct CaptureLogger(Mutex<Vec<String>>);
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.0.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+
+#[test]
+fn warn_on_empty_consumed() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+
+    let run = |warn: bool| {
+        let empty = DefaultTask::with_closure("empty", |_, _| Output::empty());
+        let value = DefaultTask::with_closure("value", |_, _| Output::new(1usize));
+        let mut reader =
+            DefaultTask::with_closure("reader", |input, _| Output::new(input.get_iter().count()));
+        reader.set_predecessors(&[&empty, &value]);
+        let mut ignorer = DefaultTask::with_closure("ignorer", |_, _| Output::new(0usize));
+        ignorer.set_predecessors(&[&value]);
+        let mut dag = Dag::with_tasks(vec![empty, value, reader, ignorer]);
+        dag.set_warn_on_empty_consumed(warn);
+        assert!(dag.start().unwrap());
+        std::mem::take(&mut *LOGGER.0.lock().unwrap())
+    };
+
+    assert!(run(false).is_empty());
+    let logs = run(true);
+    assert_eq!(logs.len(), 2);
+    assert!(logs
+        .iter()
+        .any(|line| line.contains("name: empty") && line.contains("empty output")));
+    assert!(logs
+        .iter()
+        .any(|line| line.contains("name: ignorer") && line.contains("did not read")));
+}
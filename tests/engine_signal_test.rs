@@ -0,0 +1,39 @@
+//! A handled signal cancels the run of an Engine, so that the Dags that have not started yet
+//! are not run. The signal is received by the whole process, so this test lives in its own
+//! binary.
+#![cfg(unix)]
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use dagrs::{CommandAction, Dag, DefaultTask, Engine};
+
+#[test]
+fn signal_cancels_engine_run() {
+    let mut engine = Engine::default();
+    let sleep = DefaultTask::with_action("sleep", CommandAction::new("sleep 30"));
+    engine
+        .append_dag("first", Dag::with_tasks(vec![sleep]))
+        .unwrap();
+    let echo = DefaultTask::with_action("echo", CommandAction::new("echo done"));
+    engine
+        .append_dag("second", Dag::with_tasks(vec![echo]))
+        .unwrap();
+    engine.set_handle_signals(true);
+    thread::spawn(|| {
+        thread::sleep(Duration::from_millis(500));
+        // SAFETY: sending a signal has no memory safety requirements.
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGINT);
+        }
+    });
+
+    let start = Instant::now();
+    let report = engine.run_sequential_with_deadline(Duration::from_secs(60));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(report.failed, vec!["first"]);
+    assert_eq!(report.not_started, vec!["second"]);
+    assert!(report.completed.is_empty());
+}
@@ -0,0 +1,183 @@
+//! Tests of running several dags with an engine.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use dagrs::{Dag, DagError, DefaultTask, Engine, Output};
+
+fn dag(sleep: Duration) -> Dag {
+    let a = DefaultTask::with_closure("a", |_, _| Output::empty());
+    let mut b = DefaultTask::with_closure("b", move |_, _| {
+        std::thread::sleep(sleep);
+        Output::empty()
+    });
+    b.set_predecessors(&[&a]);
+    Dag::with_tasks(vec![a, b])
+}
+
+#[test]
+fn run_parallel() {
+    let mut engine = Engine::default();
+    engine
+        .append_dag("first", dag(Duration::from_millis(300)))
+        .unwrap();
+    engine
+        .append_dag("second", dag(Duration::from_millis(300)))
+        .unwrap();
+    let start = Instant::now();
+    assert_eq!(engine.run_parallel(), vec![true, true]);
+    assert!(start.elapsed() < Duration::from_millis(590));
+}
+
+#[test]
+fn run_sequential_with_deadline() {
+    let mut engine = Engine::default();
+    engine.append_dag("quick", dag(Duration::ZERO)).unwrap();
+    engine
+        .append_dag("slow", dag(Duration::from_secs(2)))
+        .unwrap();
+    engine.append_dag("late", dag(Duration::ZERO)).unwrap();
+
+    let start = Instant::now();
+    let report = engine.run_sequential_with_deadline(Duration::from_millis(500));
+    assert!(start.elapsed() < Duration::from_millis(1500));
+    assert_eq!(report.completed, vec!["quick"]);
+    assert_eq!(report.failed, vec!["slow"]);
+    assert_eq!(report.not_started, vec!["late"]);
+}
+
+#[test]
+fn run_parallel_with_deadline() {
+    let mut engine = Engine::default();
+    engine.append_dag("quick", dag(Duration::ZERO)).unwrap();
+    engine
+        .append_dag("slow", dag(Duration::from_secs(2)))
+        .unwrap();
+
+    let report = engine.run_parallel_with_deadline(Duration::from_millis(500));
+    assert_eq!(report.completed, vec!["quick"]);
+    assert_eq!(report.failed, vec!["slow"]);
+    assert!(report.not_started.is_empty());
+}
+
+#[test]
+fn duplicate_dag_names() {
+    let mut engine = Engine::default();
+    engine.append_dag("first", dag(Duration::ZERO)).unwrap();
+    engine.append_dag("second", dag(Duration::ZERO)).unwrap();
+    assert!(matches!(
+        engine.append_dag("first", dag(Duration::ZERO)),
+        Err(DagError::DuplicateDag(name)) if name == "first"
+    ));
+
+    let failing = DefaultTask::with_closure("a", |_, _| Output::error("no".to_string()));
+    engine
+        .replace_dag("first", Dag::with_tasks(vec![failing]))
+        .unwrap();
+    engine.replace_dag("third", dag(Duration::ZERO)).unwrap();
+    assert_eq!(engine.run_sequential(), vec![false, true, true]);
+}
+
+#[test]
+fn dag_dependencies() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let engine = || {
+        let recording = |name: &'static str, ok: bool| {
+            let log = log.clone();
+            let task = DefaultTask::with_closure(name, move |_, _| {
+                std::thread::sleep(Duration::from_millis(50));
+                log.lock().unwrap().push(name);
+                if ok {
+                    Output::empty()
+                } else {
+                    Output::error("no".to_string())
+                }
+            });
+            Dag::with_tasks(vec![task])
+        };
+        let mut engine = Engine::default();
+        engine
+            .append_dag("deploy", recording("deploy", true))
+            .unwrap();
+        engine
+            .append_dag("build", recording("build", true))
+            .unwrap();
+        engine.append_dag("lint", recording("lint", false)).unwrap();
+        engine
+            .append_dag("publish", recording("publish", true))
+            .unwrap();
+        engine.add_dependency("build", "deploy").unwrap();
+        engine.add_dependency("lint", "publish").unwrap();
+        engine
+    };
+
+    let mut parallel = engine();
+    assert!(matches!(
+        parallel.add_dependency("deploy", "build"),
+        Err(DagError::LoopGraph)
+    ));
+    assert!(matches!(
+        parallel.add_dependency("build", "test"),
+        Err(DagError::DagNotFound(name)) if name == "test"
+    ));
+    assert_eq!(parallel.run_parallel(), vec![true, true, false, false]);
+    let order = std::mem::take(&mut *log.lock().unwrap());
+    assert_eq!(order.len(), 3);
+    let position = |name| order.iter().position(|n| *n == name).unwrap();
+    assert!(position("build") < position("deploy"));
+
+    assert_eq!(engine().run_sequential(), vec![true, true, false, false]);
+    assert_eq!(*log.lock().unwrap(), vec!["build", "deploy", "lint"]);
+}
+
+#[test]
+fn engine_introspection() {
+    let mut engine = Engine::default();
+    assert!(engine.dag_names().is_empty() && engine.sequence_order().is_empty());
+    for name in ["test", "build", "lint"] {
+        engine.append_dag(name, dag(Duration::ZERO)).unwrap();
+    }
+    engine.add_dependency("build", "test").unwrap();
+    engine.replace_dag("test", dag(Duration::ZERO)).unwrap();
+    assert_eq!(engine.dag_names(), vec!["test", "build", "lint"]);
+    assert_eq!(engine.sequence_order(), vec!["build", "test", "lint"]);
+}
+
+#[test]
+fn run_matching_dags() {
+    let runs = Arc::new(Mutex::new(Vec::new()));
+    let counted = |name: &'static str, success: bool| {
+        let runs = runs.clone();
+        Dag::with_tasks(vec![DefaultTask::with_closure(name, move |_, _| {
+            runs.lock().unwrap().push(name);
+            match success {
+                true => Output::empty(),
+                false => Output::error("failed".to_string()),
+            }
+        })])
+    };
+    let engine = || {
+        let mut engine = Engine::default();
+        engine.append_dag("setup", counted("setup", true)).unwrap();
+        engine.append_dag("nightly-a", counted("a", true)).unwrap();
+        engine.append_dag("daily", counted("daily", true)).unwrap();
+        engine.append_dag("nightly-b", counted("b", false)).unwrap();
+        engine.add_dependency("setup", "nightly-a").unwrap();
+        engine
+    };
+
+    let nightly = |name: &str| name.starts_with("nightly-");
+    let expected = vec![
+        ("setup".to_string(), true),
+        ("nightly-a".to_string(), true),
+        ("nightly-b".to_string(), false),
+    ];
+    assert_eq!(engine().run_matching(nightly), expected);
+    assert_eq!(*runs.lock().unwrap(), vec!["setup", "a", "b"]);
+    runs.lock().unwrap().clear();
+    assert_eq!(engine().run_matching_parallel(nightly), expected);
+    assert!(!runs.lock().unwrap().contains(&"daily"));
+    assert!(engine().run_matching(|_| false).is_empty());
+}
@@ -1,6 +1,6 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use dagrs::EnvVar;
+use dagrs::{Dag, DefaultTask, EnvVar, Output};
 
 #[test]
 fn env_set_get_test() {
@@ -34,6 +34,64 @@ fn multi_thread_immutable_env_test() {
         .for_each(|handle| handle.join().unwrap());
 }
 
+#[test]
+fn env_file_test() {
+    let mut env = EnvVar::new();
+    env.set("NAME", "from code".to_string());
+    env.load_env_file("tests/config/local.env").unwrap();
+    let get = |name| env.get::<String>(name);
+    assert_eq!(get("HOST").as_deref(), Some("localhost"));
+    assert_eq!(get("PORT").as_deref(), Some("8080"));
+    assert_eq!(get("GREETING").as_deref(), Some("hello \"world\"\n"));
+    assert_eq!(get("LITERAL").as_deref(), Some("no $expansion \\n here"));
+    assert_eq!(get("EMPTY").as_deref(), Some(""));
+    assert_eq!(get("1INVALID"), None);
+    // Variables set in code take precedence over the file.
+    assert_eq!(get("NAME").as_deref(), Some("from code"));
+    assert!(EnvVar::new()
+        .load_env_file("tests/config/missing.env")
+        .is_err());
+
+    // The file is layered under the environment of the dag, even when it is set afterwards.
+    let task = DefaultTask::with_closure("task", |_, env| {
+        Output::new(format!(
+            "{}:{}",
+            env.get::<String>("HOST").unwrap(),
+            env.get::<String>("PORT").unwrap()
+        ))
+    });
+    let mut dag = Dag::with_tasks(vec![task]);
+    dag.load_env_file("tests/config/local.env").unwrap();
+    let mut env = EnvVar::new();
+    env.set("HOST", "example.com".to_string());
+    dag.set_env(env);
+    assert!(dag.start().unwrap());
+    assert_eq!(*dag.get_result::<String>().unwrap(), "example.com:8080");
+}
+
+#[test]
+fn shared_state_test() {
+    let collect = |name: &str| {
+        let name = name.to_string();
+        DefaultTask::with_closure(&name.clone(), move |_, env| {
+            let seen = env.shared_state::<Vec<String>>().unwrap();
+            seen.lock().unwrap().push(name.clone());
+            Output::new(env.shared_state::<usize>().is_none())
+        })
+    };
+    let a = collect("a");
+    let mut b = collect("b");
+    b.set_predecessors(&[&a]);
+    let seen = Arc::new(Mutex::new(Vec::<String>::new()));
+    let mut dag = Dag::with_tasks(vec![a, b]);
+    // The state is kept when the environment is replaced.
+    dag.set_shared_state(seen.clone());
+    dag.set_env(EnvVar::new());
+    assert!(dag.start().unwrap());
+    assert!(*dag.get_result::<bool>().unwrap());
+    assert_eq!(*seen.lock().unwrap(), vec!["a", "b"]);
+}
+
 fn init_env() -> EnvVar {
     let mut env = EnvVar::new();
 
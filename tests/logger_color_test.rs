@@ -0,0 +1,57 @@
+//! Tests of the colors of the dagrs logger.
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use dagrs::utils::logger::{init_logger_with, ColorMode};
+use env_logger::WriteStyle;
+use log::LevelFilter;
+
+/// A writer appending to a shared buffer.
+#[derive(Clone)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn never_colored() {
+    let buffer = Buffer(Arc::new(Mutex::new(Vec::new())));
+    let mut builder = env_logger::Builder::new();
+    builder
+        .target(env_logger::Target::Pipe(Box::new(buffer.clone())))
+        .write_style(ColorMode::Never.into());
+    init_logger_with(LevelFilter::Info, builder).unwrap();
+
+    log::error!(target: "dagrs::engine::dag", "an error");
+    log::warn!(target: "dagrs::engine::dag", "a warning");
+
+    let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("ERROR") && logs.contains("a warning"));
+    assert!(!logs.contains('\u{1b}'));
+    assert_eq!("never".parse(), Ok(ColorMode::Never));
+    assert!("sometimes".parse::<ColorMode>().is_err());
+}
+
+#[test]
+fn color_mode_styles() {
+    assert_eq!(WriteStyle::from(ColorMode::Never), WriteStyle::Never);
+    assert_eq!(WriteStyle::from(ColorMode::Always), WriteStyle::Always);
+    std::env::remove_var("NO_COLOR");
+    assert_eq!(WriteStyle::from(ColorMode::Auto), WriteStyle::Auto);
+    std::env::set_var("NO_COLOR", "1");
+    assert_eq!(WriteStyle::from(ColorMode::Auto), WriteStyle::Never);
+    // An empty NO_COLOR is ignored.
+    std::env::set_var("NO_COLOR", "");
+    assert_eq!(WriteStyle::from(ColorMode::Auto), WriteStyle::Auto);
+}
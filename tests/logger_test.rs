@@ -0,0 +1,46 @@
+//! Tests of the per-target levels of the dagrs logger.
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use dagrs::utils::logger::{clear_target_level, init_logger_with, set_target_level};
+use log::LevelFilter;
+
+/// A writer appending to a shared buffer.
+#[derive(Clone)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn per_target_levels() {
+    let buffer = Buffer(Arc::new(Mutex::new(Vec::new())));
+    let mut builder = env_logger::Builder::new();
+    builder.target(env_logger::Target::Pipe(Box::new(buffer.clone())));
+    init_logger_with(LevelFilter::Info, builder).unwrap();
+
+    set_target_level("engine", LevelFilter::Debug);
+    log::debug!(target: "dagrs::engine::dag", "scheduling detail");
+    log::debug!(target: "dagrs::task::cmd", "task detail");
+    log::info!(target: "dagrs::task::cmd", "task info");
+
+    clear_target_level("engine");
+    log::debug!(target: "dagrs::engine::dag", "hidden again");
+
+    let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("scheduling detail"));
+    assert!(logs.contains("task info"));
+    assert!(!logs.contains("task detail"));
+    assert!(!logs.contains("hidden again"));
+}
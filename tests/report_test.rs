@@ -0,0 +1,164 @@
+//! Tests of the run reports and their comparison.
+
+use std::{
+    net::TcpListener,
+    time::{Duration, Instant},
+};
+
+use dagrs::{
+    Dag, DefaultTask, Output, RunEvent, RunEventKind, RunReport, StateChange, TaskReport,
+    TaskState, WebhookNotifier,
+};
+
+fn task(name: &str, state: TaskState, millis: u64) -> TaskReport {
+    TaskReport {
+        name: name.to_string(),
+        state,
+        error: None,
+        duration: Some(Duration::from_millis(millis)),
+        resources: None,
+    }
+}
+
+#[test]
+fn report_records_durations() {
+    let a = DefaultTask::with_closure("a", |_, _| {
+        std::thread::sleep(Duration::from_millis(50));
+        Output::empty()
+    });
+    let mut dag = Dag::with_tasks(vec![a]);
+    assert!(dag.start().unwrap());
+    let duration = dag.run_report().task("a").unwrap().duration.unwrap();
+    assert!(duration >= Duration::from_millis(50));
+}
+
+#[test]
+fn diff_reports() {
+    let yesterday = RunReport {
+        success: true,
+        tasks: vec![
+            task("build", TaskState::Succeeded, 100),
+            task("lint", TaskState::Succeeded, 100),
+            task("test", TaskState::Succeeded, 100),
+        ],
+        groups: Vec::new(),
+    };
+    let today = RunReport {
+        success: false,
+        tasks: vec![
+            task("build", TaskState::Succeeded, 110),
+            task("deploy", TaskState::Skipped, 0),
+            task("test", TaskState::Failed, 300),
+        ],
+        groups: Vec::new(),
+    };
+
+    let diff = yesterday.diff(&today);
+    assert_eq!(
+        diff.state_changes,
+        vec![StateChange {
+            name: "test".to_string(),
+            before: TaskState::Succeeded,
+            after: TaskState::Failed,
+        }]
+    );
+    assert_eq!(diff.regressions.len(), 1);
+    assert_eq!(diff.regressions[0].name, "test");
+    assert_eq!(diff.added, vec!["deploy".to_string()]);
+    assert_eq!(diff.removed, vec!["lint".to_string()]);
+    assert_eq!(
+        yesterday
+            .diff_with_threshold(&today, 0.05)
+            .regressions
+            .len(),
+        2
+    );
+
+    let summary = diff.to_string();
+    assert!(summary.contains("test: Succeeded -> Failed"));
+    assert!(summary.contains("deploy: added"));
+    assert!(yesterday.diff(&yesterday).is_empty());
+}
+
+#[test]
+fn event_log_in_order() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new("done".to_string()));
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::error("broken".to_string()));
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::empty());
+    b.set_predecessors(&[&a]);
+    c.set_predecessors(&[&b]);
+    let mut dag = Dag::with_tasks(vec![a, b, c]);
+    assert!(!dag.start().unwrap());
+
+    let events = dag.event_log();
+    let kinds = |task: &str| -> Vec<RunEventKind> {
+        events
+            .iter()
+            .filter(|event| event.task == task)
+            .map(|event| event.kind)
+            .collect()
+    };
+    use RunEventKind::*;
+    assert_eq!(kinds("a"), vec![Queued, Started, Finished]);
+    assert_eq!(kinds("b"), vec![Queued, Started, Failed]);
+    assert_eq!(kinds("c"), vec![Queued, Skipped]);
+    assert!(events
+        .windows(2)
+        .all(|pair| pair[0].timestamp_ms <= pair[1].timestamp_ms));
+
+    let finished = events.iter().find(|event| event.kind == Finished).unwrap();
+    assert_eq!(finished.output, Some(serde_json::json!("done")));
+    let json = serde_json::to_string(&events).unwrap();
+    assert!(json.contains(r#""kind":"failed""#));
+    let replayed: Vec<RunEvent> = serde_json::from_str(&json).unwrap();
+    assert_eq!(replayed, events);
+}
+
+#[test]
+fn slowest_tasks() {
+    let report = RunReport {
+        success: true,
+        tasks: vec![
+            task("build", TaskState::Succeeded, 300),
+            task("lint", TaskState::Succeeded, 100),
+            task("doc", TaskState::Succeeded, 300),
+            TaskReport {
+                duration: None,
+                ..task("deploy", TaskState::Skipped, 0)
+            },
+        ],
+        groups: Vec::new(),
+    };
+    let ms = Duration::from_millis;
+    assert_eq!(
+        report.slowest_tasks(2),
+        vec![("build".to_string(), ms(300)), ("doc".to_string(), ms(300))]
+    );
+    assert_eq!(report.slowest_tasks(10).len(), 3);
+
+    let a = DefaultTask::with_closure("a", |_, _| {
+        std::thread::sleep(Duration::from_millis(50));
+        Output::empty()
+    });
+    let b = DefaultTask::with_closure("b", |_, _| Output::empty());
+    let mut dag = Dag::with_tasks(vec![a, b]);
+    assert!(dag.start().unwrap());
+    assert_eq!(dag.slowest_tasks(1)[0].0, "a");
+}
+
+#[test]
+fn webhook_notifications_time_out() {
+    // The webhook accepts the connection but never answers.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let mut notifier = WebhookNotifier::new(&url);
+    notifier.set_timeout(Duration::from_millis(500));
+    let report = RunReport {
+        success: true,
+        tasks: vec![task("build", TaskState::Succeeded, 100)],
+        groups: Vec::new(),
+    };
+    let started = Instant::now();
+    notifier.notify(&report);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
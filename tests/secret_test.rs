@@ -0,0 +1,49 @@
+//! Secrets stored in the environment variables must never appear in the logs.
+
+use std::sync::Mutex;
+
+use dagrs::{CommandAction, Dag, DefaultTask, EnvVar, Output};
+use log::{Log, Metadata, Record};
+
+/// A logger that keeps every record in memory.
+struct CaptureLogger(Mutex<Vec<String>>);
+
+impl Log for CaptureLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        self.0.lock().unwrap().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+
+#[test]
+fn secret_never_logged() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let token = DefaultTask::with_closure("token", |_, env| {
+        Output::new(env.get::<String>("TOKEN").unwrap())
+    });
+    let mut echo = DefaultTask::with_action("echo", CommandAction::new("echo"));
+    echo.set_predecessors(&[&token]);
+    let mut fail = DefaultTask::with_closure("fail", |_, env| {
+        Output::error(format!("rejected {}", env.get::<String>("TOKEN").unwrap()))
+    });
+    fail.set_predecessors(&[&echo]);
+
+    let mut env = EnvVar::new();
+    env.set_secret("TOKEN", "s3cr3t-value".to_string());
+    let mut dag = Dag::with_tasks(vec![token, echo, fail]);
+    dag.set_env(env);
+    assert!(!dag.start().unwrap());
+
+    let logs = LOGGER.0.lock().unwrap();
+    assert!(logs.iter().any(|line| line.contains("***")));
+    assert!(logs.iter().all(|line| !line.contains("s3cr3t-value")));
+}
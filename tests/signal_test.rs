@@ -0,0 +1,32 @@
+//! A handled signal cancels the dag and kills the commands it spawned. The signal is received
+//! by the whole process, so this test lives in its own binary.
+#![cfg(unix)]
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use dagrs::{CommandAction, Dag, DagError, DefaultTask, TaskState};
+
+#[test]
+fn signal_cancels_dag() {
+    let sleep = DefaultTask::with_action("sleep", CommandAction::new("sleep 30"));
+    let mut after = DefaultTask::with_action("after", CommandAction::new("echo done"));
+    after.set_predecessors(&[&sleep]);
+
+    let mut dag = Dag::with_tasks(vec![sleep, after]);
+    dag.set_handle_signals(true);
+    thread::spawn(|| {
+        thread::sleep(Duration::from_millis(500));
+        // SAFETY: sending a signal has no memory safety requirements.
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGTERM);
+        }
+    });
+
+    let start = Instant::now();
+    assert!(matches!(dag.start(), Err(DagError::Cancelled)));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_ne!(dag.task_state("after"), Some(TaskState::Succeeded));
+}
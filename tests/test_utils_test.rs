@@ -0,0 +1,56 @@
+//! Tests of the helpers for testing pipelines.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use dagrs::{
+    test_utils::{assert_task_output, run_and_collect},
+    Dag, DefaultTask, Output,
+};
+
+fn dag() -> Dag {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new(1usize));
+    let mut b = DefaultTask::with_closure("b", |_, _| Output::error("broken".to_string()));
+    b.set_predecessors(&[&a]);
+    let mut c = DefaultTask::with_closure("c", |_, _| Output::new(3usize));
+    c.set_predecessors(&[&b]);
+    Dag::with_tasks(vec![a, b, c])
+}
+
+/// The message of the panic of `check`.
+fn panic_message(check: impl FnOnce()) -> String {
+    let payload = panic::catch_unwind(AssertUnwindSafe(check)).unwrap_err();
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().unwrap().to_string(),
+    }
+}
+
+#[test]
+fn collect_outputs() {
+    let mut dag = dag();
+    let outputs = run_and_collect(&mut dag);
+    assert_eq!(outputs.len(), 3);
+    assert!(
+        matches!(&outputs["a"], Output::Out(Some(content)) if content.get::<usize>() == Some(&1))
+    );
+    assert!(matches!(&outputs["b"], Output::Err(err) if err == "broken"));
+    assert!(matches!(&outputs["c"], Output::Out(None)));
+}
+
+#[test]
+fn task_output_mismatches() {
+    let mut dag = dag();
+    run_and_collect(&mut dag);
+    assert_task_output(&dag, "a", 1usize);
+
+    let message = panic_message(|| assert_task_output(&dag, "a", 1i32));
+    assert_eq!(message, "task 'a' produced a value of type usize, not i32");
+    let message = panic_message(|| assert_task_output(&dag, "a", 2usize));
+    assert!(message.contains("produced another value than expected"));
+    let message = panic_message(|| assert_task_output(&dag, "b", 2usize));
+    assert_eq!(message, "task 'b' failed: broken");
+    let message = panic_message(|| assert_task_output(&dag, "c", 3usize));
+    assert_eq!(message, "task 'c' produced no output");
+    let message = panic_message(|| assert_task_output(&dag, "d", 3usize));
+    assert_eq!(message, "task 'd' does not exist");
+}
@@ -0,0 +1,108 @@
+//! Tests of executing WebAssembly modules as task actions.
+
+use dagrs::{Dag, DefaultTask, Output, TaskState, WasmAction};
+
+/// A module whose `echo` function returns its input, whose `boom` function traps, whose `spin`
+/// function never returns, whose `grow` function traps if it cannot grow its memory by
+/// 100 pages and whose `huge` function returns an output larger than its memory.
+const MODULE: &str = r#"
+(module
+  (memory (export "memory") 1)
+  (global $next (mut i32) (i32.const 1024))
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    global.get $next
+    local.set $ptr
+    global.get $next
+    local.get $len
+    i32.add
+    global.set $next
+    local.get $ptr)
+  (func (export "echo") (param $ptr i32) (param $len i32) (result i64)
+    local.get $ptr
+    i64.extend_i32_u
+    i64.const 32
+    i64.shl
+    local.get $len
+    i64.extend_i32_u
+    i64.or)
+  (func (export "boom") (param i32 i32) (result i64)
+    unreachable)
+  (func (export "spin") (param i32 i32) (result i64)
+    (loop $forever
+      br $forever)
+    i64.const 0)
+  (func (export "grow") (param i32 i32) (result i64)
+    (if (i32.eq (memory.grow (i32.const 100)) (i32.const -1))
+      (then unreachable))
+    i64.const 0)
+  (func (export "huge") (param i32 i32) (result i64)
+    i64.const 0xffffffff))
+"#;
+
+#[test]
+fn wasm_echo_input() {
+    let a = DefaultTask::with_closure("a", |_, _| Output::new("hello".to_string()));
+    let mut b = DefaultTask::with_action("b", WasmAction::from_bytes(MODULE, "echo"));
+    b.set_predecessors(&[&a]);
+
+    let mut dag = Dag::with_tasks(vec![a, b]);
+    assert!(dag.start().unwrap());
+    let out = dag.get_result::<Vec<u8>>().unwrap();
+    assert_eq!(String::from_utf8_lossy(&out), r#"["hello"]"#);
+}
+
+#[test]
+fn wasm_trap_fails_task() {
+    let a = DefaultTask::with_action("a", WasmAction::from_bytes(MODULE, "boom"));
+    let mut dag = Dag::with_tasks(vec![a]);
+    assert!(!dag.start().unwrap());
+    assert_eq!(dag.task_state("a"), Some(TaskState::Failed));
+    assert!(dag
+        .run_report()
+        .task("a")
+        .unwrap()
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("wasm trap"));
+}
+
+#[test]
+fn wasm_limits() {
+    let mut spin = WasmAction::from_bytes(MODULE, "spin");
+    spin.set_fuel(100_000);
+    let a = DefaultTask::with_action("a", spin);
+    let mut dag = Dag::with_tasks(vec![a]);
+    assert!(!dag.start().unwrap());
+    assert!(dag
+        .run_report()
+        .task("a")
+        .unwrap()
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("fuel"));
+
+    let mut grow = WasmAction::from_bytes(MODULE, "grow");
+    grow.set_memory_limit(10 << 16);
+    let a = DefaultTask::with_action("a", grow);
+    let mut dag = Dag::with_tasks(vec![a]);
+    assert!(!dag.start().unwrap());
+
+    let a = DefaultTask::with_action("a", WasmAction::from_bytes(MODULE, "grow"));
+    let mut dag = Dag::with_tasks(vec![a]);
+    assert!(dag.start().unwrap());
+
+    let a = DefaultTask::with_action("a", WasmAction::from_bytes(MODULE, "huge"));
+    let mut dag = Dag::with_tasks(vec![a]);
+    assert!(!dag.start().unwrap());
+    assert!(dag
+        .run_report()
+        .task("a")
+        .unwrap()
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("out of the bounds"));
+}
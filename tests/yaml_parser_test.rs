@@ -1,11 +1,16 @@
 use std::collections::HashMap;
 
-use dagrs::{ParseError, Parser, Task, YamlParser};
+use dagrs::{
+    yaml::pipeline_schema, CommandAction, Dag, DagError, DefaultTask, Output, OutputFormat,
+    ParseError, Parser, ScriptAction, Task, TaskState, YamlParser, YamlTaskError,
+};
+use serde_json::Value;
+use yaml_rust::{Yaml, YamlLoader};
 
 #[test]
 fn file_not_found_test() {
     let no_such_file: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("./no_such_file.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("./no_such_file.yaml", HashMap::new());
     // let err = no_such_file.unwrap_err().to_string();
     // println!("{err}");
     assert!(no_such_file.is_err())
@@ -14,7 +19,7 @@ fn file_not_found_test() {
 #[test]
 fn illegal_yaml_content() {
     let illegal_content: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("tests/config/illegal_content.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("tests/config/illegal_content.yaml", HashMap::new());
     // let err = illegal_content.unwrap_err().to_string();
     // println!("{err}");
     assert!(illegal_content.is_err())
@@ -23,7 +28,7 @@ fn illegal_yaml_content() {
 #[test]
 fn empty_content() {
     let empty_content: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("tests/config/empty_file.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("tests/config/empty_file.yaml", HashMap::new());
     // let err = empty_content.unwrap_err().to_string();
     // println!("{err}");
     assert!(empty_content.is_err())
@@ -32,7 +37,7 @@ fn empty_content() {
 #[test]
 fn yaml_no_start_with_dagrs() {
     let forget_dagrs: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("tests/config/no_start_with_dagrs.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("tests/config/no_start_with_dagrs.yaml", HashMap::new());
     // let err = forget_dagrs.unwrap_err().to_string();
     // println!("{err}");
     assert!(forget_dagrs.is_err())
@@ -41,7 +46,7 @@ fn yaml_no_start_with_dagrs() {
 #[test]
 fn yaml_task_no_name() {
     let no_task_name: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("tests/config/no_task_name.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("tests/config/no_task_name.yaml", HashMap::new());
     // let err = no_task_name.unwrap_err().to_string();
     // println!("{err}");
     assert!(no_task_name.is_err())
@@ -50,7 +55,7 @@ fn yaml_task_no_name() {
 #[test]
 fn yaml_task_not_found_precursor() {
     let not_found_pre: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("tests/config/precursor_not_found.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("tests/config/precursor_not_found.yaml", HashMap::new());
     // let err = not_found_pre.unwrap_err().to_string();
     // println!("{err}");
     assert!(not_found_pre.is_err())
@@ -59,7 +64,7 @@ fn yaml_task_not_found_precursor() {
 #[test]
 fn yaml_task_no_script_config() {
     let script: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("tests/config/no_script.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("tests/config/no_script.yaml", HashMap::new());
     // let err = script.unwrap_err().to_string();
     // println!("{err}");
     assert!(script.is_err())
@@ -68,6 +73,349 @@ fn yaml_task_no_script_config() {
 #[test]
 fn correct_parse() {
     let tasks: Result<Vec<Box<dyn Task>>, ParseError> =
-        YamlParser.parse_tasks("tests/config/correct.yaml", HashMap::new());
+        YamlParser::new().parse_tasks("tests/config/correct.yaml", HashMap::new());
     assert!(tasks.is_ok());
 }
+
+#[test]
+fn yaml_task_templates() {
+    let dag = Dag::with_yaml("tests/config/templates.yaml", HashMap::new()).unwrap();
+    // `b` overrides the `after` of its template, `a` inherits it.
+    assert_eq!(
+        dag.levels().unwrap(),
+        vec![
+            vec!["Root".to_string()],
+            vec!["Task a".to_string()],
+            vec!["Task b".to_string()]
+        ]
+    );
+}
+
+#[test]
+fn yaml_task_template_not_found() {
+    let not_found: Result<Vec<Box<dyn Task>>, ParseError> =
+        YamlParser::new().parse_tasks("tests/config/template_not_found.yaml", HashMap::new());
+    assert!(not_found.is_err())
+}
+
+#[test]
+fn yaml_round_trip() {
+    let dag = Dag::with_yaml("tests/config/correct.yaml", HashMap::new()).unwrap();
+    let copy = Dag::with_yaml_str(&dag.to_yaml().unwrap(), HashMap::new()).unwrap();
+    assert_eq!(copy.levels().unwrap(), dag.levels().unwrap());
+
+    let mut echo = CommandAction::new("echo a");
+    echo.set_shell(false);
+    let a = DefaultTask::with_action("Task a", echo);
+    let mut count = CommandAction::new("echo 3");
+    count.set_output_format(OutputFormat::Int);
+    let mut b = DefaultTask::with_action("Task b", count);
+    b.set_predecessors(&[&a]);
+    b.set_label("tier", "deploy");
+    let mut c = DefaultTask::with_action(
+        "Task c",
+        ScriptAction::new("sh", "for i in 1 2; do\n  echo \"$i\"\ndone\n"),
+    );
+    c.set_predecessors(&[&a, &b]);
+    c.set_enabled(false);
+    let dag = Dag::with_tasks(vec![a, b, c]);
+    let yaml = dag.to_yaml().unwrap();
+    let mut copy = Dag::with_yaml_str(&yaml, HashMap::new()).unwrap();
+    assert_eq!(copy.levels().unwrap(), dag.levels().unwrap());
+    assert_eq!(
+        copy.to_yaml().unwrap().lines().count(),
+        yaml.lines().count()
+    );
+    assert!(copy
+        .run_subset(|labels| labels.matches("tier", "deploy"))
+        .unwrap());
+    assert_eq!(copy.task_state("Task b"), Some(TaskState::Succeeded));
+    assert_eq!(copy.task_state("Task c"), Some(TaskState::Skipped));
+
+    let closure = DefaultTask::with_closure("closure", |_, _| Output::empty());
+    assert!(matches!(
+        Dag::with_tasks(vec![closure]).to_yaml(),
+        Err(DagError::Unserializable(name)) if name == "closure"
+    ));
+}
+
+/// Convert a yaml document into the JSON value it stands for.
+fn yaml_to_json(yaml: &Yaml) -> Value {
+    match yaml {
+        Yaml::String(s) => Value::from(s.as_str()),
+        Yaml::Integer(i) => Value::from(*i),
+        Yaml::Real(r) => Value::from(r.parse::<f64>().unwrap()),
+        Yaml::Boolean(b) => Value::from(*b),
+        Yaml::Array(items) => items.iter().map(yaml_to_json).collect(),
+        Yaml::Hash(hash) => Value::Object(
+            hash.iter()
+                .map(|(k, v)| (k.as_str().unwrap().to_owned(), yaml_to_json(v)))
+                .collect(),
+        ),
+        _ => Value::Null,
+    }
+}
+
+/// Check a value against the subset of JSON Schema used by `pipeline_schema`.
+fn validate(root: &Value, schema: &Value, value: &Value) -> Result<(), String> {
+    if let Some(path) = schema["$ref"].as_str() {
+        let name = path.trim_start_matches("#/definitions/");
+        return validate(root, &root["definitions"][name], value);
+    }
+    let type_name = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    let types = match &schema["type"] {
+        Value::String(t) => vec![t.as_str()],
+        Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+        _ => vec![type_name],
+    };
+    if !types.contains(&type_name) {
+        return Err(format!("{} is not of type {:?}", value, types));
+    }
+    if let Some(allowed) = schema["enum"].as_array() {
+        if !allowed.contains(value) {
+            return Err(format!("{} is not one of {:?}", value, allowed));
+        }
+    }
+    if let Value::Array(items) = value {
+        for item in items {
+            validate(root, &schema["items"], item)?;
+        }
+    }
+    if let Value::Object(map) = value {
+        for key in schema["required"].as_array().into_iter().flatten() {
+            if !map.contains_key(key.as_str().unwrap()) {
+                return Err(format!("missing {}", key));
+            }
+        }
+        for (key, item) in map {
+            match (&schema["properties"][key], &schema["additionalProperties"]) {
+                (Value::Null, Value::Bool(false)) => return Err(format!("unknown {}", key)),
+                (Value::Null, Value::Object(_)) => {
+                    validate(root, &schema["additionalProperties"], item)?
+                }
+                (Value::Null, _) => {}
+                (property, _) => validate(root, property, item)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn pipeline_schema_validates_config_files() {
+    let schema = pipeline_schema();
+    let check = |content: &str| {
+        let docs = YamlLoader::load_from_str(content).unwrap();
+        validate(&schema, &schema, &yaml_to_json(&docs[0]))
+    };
+
+    // Every file accepted by the parser follows the schema.
+    let mut checked = 0;
+    for entry in std::fs::read_dir("tests/config").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("yaml") {
+            continue;
+        }
+        let file = path.to_str().unwrap();
+        if YamlParser::new().parse_tasks(file, HashMap::new()).is_ok() {
+            let content = std::fs::read_to_string(file).unwrap();
+            check(&content).unwrap_or_else(|err| panic!("{}: {}", file, err));
+            checked += 1;
+        }
+    }
+    assert!(checked >= 5);
+
+    let dag = Dag::with_yaml("tests/config/templates.yaml", HashMap::new()).unwrap();
+    check(&dag.to_yaml().unwrap()).unwrap();
+
+    assert!(check("dagrs:\n  a:\n    name: a\n    cmd: echo a\n    afer: [ b ]\n").is_err());
+    assert!(check("dagrs:\n  a:\n    cmd: echo a\n    output_type: text\n").is_err());
+    assert!(check("dagrs:\n  a:\n    cmd: echo a\n    enabled: no-thanks\n").is_err());
+    assert!(check("tasks:\n  a:\n    cmd: echo a\n").is_err());
+}
+
+#[test]
+fn yaml_includes() {
+    let mut dag = Dag::with_yaml("tests/config/includes.yaml", HashMap::new()).unwrap();
+    assert_eq!(
+        dag.levels().unwrap(),
+        vec![vec!["Build"], vec!["Test"], vec!["Deploy"]]
+    );
+    assert!(dag
+        .run_subset(|labels| labels.matches("team", "infra"))
+        .unwrap());
+    assert_eq!(dag.task_state("Deploy"), Some(TaskState::Succeeded));
+
+    let cycle = YamlParser::new().parse_tasks("tests/config/include_cycle.yaml", HashMap::new());
+    assert!(cycle.unwrap_err().to_string().contains("cycle"));
+    let duplicate =
+        YamlParser::new().parse_tasks("tests/config/include_duplicate.yaml", HashMap::new());
+    assert!(duplicate.unwrap_err().to_string().contains("'build'"));
+}
+
+#[test]
+fn yaml_merge_duplicates() {
+    let mut parser = YamlParser::new();
+    parser.set_merge_duplicates(true);
+    let mut dag = Dag::with_config_file_and_parser(
+        "tests/config/include_merge.yaml",
+        Box::new(parser.clone()),
+        HashMap::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        dag.levels().unwrap(),
+        vec![vec!["Build", "Lint"], vec!["Test"]]
+    );
+    assert!(dag.start().unwrap());
+
+    let duplicate = parser.parse_tasks("tests/config/include_duplicate.yaml", HashMap::new());
+    assert!(duplicate.unwrap_err().to_string().contains("'build'"));
+    let merged = YamlParser::new().parse_tasks("tests/config/include_merge.yaml", HashMap::new());
+    assert!(merged
+        .unwrap_err()
+        .to_string()
+        .contains("defined in several files"));
+}
+
+#[test]
+fn yaml_tasks_into_default() {
+    let tasks = YamlParser::new()
+        .parse_yaml_tasks("tests/config/correct.yaml", HashMap::new())
+        .unwrap();
+    let tasks: Vec<DefaultTask> = tasks
+        .into_iter()
+        .map(|task| {
+            let mut task = task.into_default();
+            task.set_weight(2.0);
+            task
+        })
+        .collect();
+    let mut dag = Dag::with_tasks(tasks);
+    let parsed = Dag::with_yaml("tests/config/correct.yaml", HashMap::new()).unwrap();
+    assert_eq!(dag.levels().unwrap(), parsed.levels().unwrap());
+    assert_eq!(dag.total_weight(), 2.0 * parsed.total_weight());
+    assert!(dag.start().unwrap());
+}
+
+#[test]
+fn yaml_success_codes() {
+    let yaml = r#"
+dagrs:
+  search:
+    name: search
+    cmd: echo a | grep b
+    success_codes: [0, 1]
+"#;
+    let mut dag = Dag::with_yaml_str(yaml, HashMap::new()).unwrap();
+    assert!(dag.start().unwrap());
+    let mut copy = Dag::with_yaml_str(&dag.to_yaml().unwrap(), HashMap::new()).unwrap();
+    assert!(copy.start().unwrap());
+
+    let yaml = yaml.replace("[0, 1]", "[0, one]");
+    assert!(Dag::with_yaml_str(&yaml, HashMap::new()).is_err());
+}
+
+#[test]
+fn required_field_checks() {
+    let yaml = r#"
+templates:
+  owned:
+    labels:
+      team: infra
+dagrs:
+  a:
+    name: a
+    use: owned
+    cmd: echo a
+  b:
+    name: b
+    cmd: echo b
+    after: [a]
+  c:
+    name: c
+    cmd: echo c
+"#;
+    let mut parser = YamlParser::new();
+    parser.add_required_field_check(|task| match task.label("team") {
+        Some(_) => Ok(()),
+        None => Err("no team label".to_string()),
+    });
+    parser.add_required_field_check(|task| match task.get("after") {
+        Some(_) if task.id() != "b" => Err("unexpected after".to_string()),
+        _ => Ok(()),
+    });
+    let err = parser
+        .parse_tasks_from_str(yaml, HashMap::new())
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        YamlTaskError::RequiredFieldChecks(vec![
+            ("b".to_string(), "no team label".to_string()),
+            ("c".to_string(), "no team label".to_string()),
+        ])
+        .to_string()
+    );
+
+    let owned = yaml.replace("cmd: echo b", "cmd: echo b\n    use: owned");
+    let owned = owned.replace("cmd: echo c", "cmd: echo c\n    use: owned");
+    let mut dag =
+        Dag::with_config_str_and_parser(&owned, Box::new(parser), HashMap::new()).unwrap();
+    assert!(dag.start().unwrap());
+}
+
+#[test]
+fn yaml_groups() {
+    let mut dag = Dag::with_yaml("tests/config/groups.yaml", HashMap::new()).unwrap();
+    let mut levels = dag.levels().unwrap();
+    levels.iter_mut().for_each(|level| level.sort());
+    assert_eq!(
+        levels,
+        vec![vec!["Fetch", "Setup"], vec!["Build"], vec!["Lint", "Unit"]]
+    );
+    assert!(dag.start().unwrap());
+
+    let parse = |content: &str| YamlParser::new().parse_tasks_from_str(content, HashMap::new());
+    let task = "dagrs:\n  a:\n    name: a\n    cmd: echo a\n";
+    let err = parse(&format!("groups:\n  a:\n    tasks: [ a ]\n{}", task)).unwrap_err();
+    assert!(err.to_string().contains("same name as a task"));
+    let err = parse(&format!("groups:\n  g:\n    tasks: [ b ]\n{}", task)).unwrap_err();
+    assert!(err.to_string().contains("'b', which is not a task"));
+    let err = parse(&format!(
+        "groups:\n  g:\n    after: [ b ]\n    tasks: [ a ]\n{}",
+        task
+    ));
+    assert!(err.is_err());
+}
+
+#[test]
+fn yaml_lenient_deps() {
+    let yaml = r#"
+dagrs:
+  a:
+    name: a
+    cmd: echo a
+  b:
+    name: b
+    after: [a, removed]
+    cmd: echo b
+"#;
+    let mut parser = YamlParser::new();
+    let err = parser
+        .parse_tasks_from_str(yaml, HashMap::new())
+        .unwrap_err();
+    assert!(err.to_string().contains("predecessor. [b]"));
+
+    parser.set_lenient_deps(true);
+    let mut dag = Dag::with_config_str_and_parser(yaml, Box::new(parser), HashMap::new()).unwrap();
+    assert_eq!(dag.levels().unwrap(), vec![vec!["a"], vec!["b"]]);
+    assert!(dag.start().unwrap());
+}